@@ -0,0 +1,60 @@
+//! Reading a dozen members from many records, by name and through a KeySet
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use json_relaxed::keys::KeySet;
+use json_relaxed::MaybeValue;
+use serde_json::{json, Value};
+
+const FIELDS: [&str; 12] = ["id", "name", "email", "active", "age", "score", "city", "zip", "country", "created", "updated", "version"];
+
+fn records() -> Vec<Value> {
+    (0..10_000).map(|i| json!({
+        "id": i, "name": "Ada", "email": "ada@example.com", "active": "true", "age": "36",
+        "score": i * 3, "city": "London", "zip": "NW1", "country": "UK", "created": 1_600_000_000,
+        "updated": 1_700_000_000, "version": "7", "notes": "", "tags": ["a", "b"], "parent": null,
+        "owner": {"id": 1}, "flags": 0, "region": "eu", "source": "import", "type": "person"
+    })).collect()
+}
+
+fn by_name(records: &[Value]) -> i64 {
+    records.iter().map(|r| {
+        let mut sum = 0;
+        for (i, key) in FIELDS.iter().enumerate() {
+            sum += match i % 3 {
+                0 => r.maybe_int(*key).relaxed(),
+                1 => r.maybe_str(*key).relaxed().len() as i64,
+                _ => r.maybe_bool(*key).relaxed() as i64
+            };
+        }
+        sum
+    }).sum()
+}
+
+fn by_slot(records: &[Value], keys: &KeySet) -> i64 {
+    records.iter().map(|r| {
+        let fields = keys.select(r);
+        let mut sum = 0;
+        for i in 0..keys.len() {
+            sum += match i % 3 {
+                0 => fields.maybe_int(i).relaxed(),
+                1 => fields.maybe_str(i).relaxed().len() as i64,
+                _ => fields.maybe_bool(i).relaxed() as i64
+            };
+        }
+        sum
+    }).sum()
+}
+
+fn extract(c: &mut Criterion) {
+    let records = records();
+    let keys = KeySet::new(&FIELDS);
+    assert_eq!(by_name(&records), by_slot(&records, &keys));
+
+    let mut group = c.benchmark_group("extract 12 of 20 members");
+    group.bench_function("by name", |b| b.iter(|| by_name(black_box(&records))));
+    group.bench_function("key set", |b| b.iter(|| by_slot(black_box(&records), &keys)));
+    group.finish();
+}
+
+criterion_group!(benches, extract);
+criterion_main!(benches);
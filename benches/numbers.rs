@@ -0,0 +1,33 @@
+//! Reading numbers and booleans of a numeric-heavy payload as strings
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use json_relaxed::MaybeValue;
+use serde_json::{json, Value};
+
+fn readings() -> Vec<Value> {
+    (0..10_000i64).map(|i| json!({
+        "sensor": i, "counter": (i as u64) << 40, "offset": -i * 7,
+        "value": i as f64 * 0.37, "ratio": 1.0 / (i + 1) as f64, "valid": i % 2 == 0
+    })).collect()
+}
+
+const KEYS: [&str; 6] = ["sensor", "counter", "offset", "value", "ratio", "valid"];
+
+fn as_strings(readings: &[Value]) -> usize {
+    readings.iter().map(|r| KEYS.iter().map(|key| r.maybe_string(*key).relaxed().len()).sum::<usize>()).sum()
+}
+
+fn as_strs(readings: &[Value]) -> usize {
+    readings.iter().map(|r| KEYS.iter().map(|key| r.maybe_str(*key).relaxed().len()).sum::<usize>()).sum()
+}
+
+fn numbers(c: &mut Criterion) {
+    let readings = readings();
+    let mut group = c.benchmark_group("numbers as text");
+    group.bench_function("maybe_string", |b| b.iter(|| as_strings(black_box(&readings))));
+    group.bench_function("maybe_str", |b| b.iter(|| as_strs(black_box(&readings))));
+    group.finish();
+}
+
+criterion_group!(benches, numbers);
+criterion_main!(benches);
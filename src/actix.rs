@@ -0,0 +1,92 @@
+//! An actix-web extractor reading request bodies with this crate
+//!
+//! Like the [axum extractor](crate::axum) the content type is not checked, the body
+//! is read with [`read`](crate::read) and converted with [`TryFromJson`]. Rejections
+//! are responders rendering problem details (RFC 7807).
+
+use actix_web::body::BoxBody;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, ResponseError};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::{web, TryFromJson};
+
+pub use crate::web::RelaxedJsonRejection;
+
+/// Extracts `T` from a json request body, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RelaxedJson<T>(pub T);
+
+impl <T: TryFromJson + 'static> FromRequest for RelaxedJson<T> {
+    type Error = RelaxedJsonRejection;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, RelaxedJsonRejection>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let bytes = actix_web::web::Bytes::from_request(req, payload);
+        Box::pin(async move {
+            let bytes = bytes.await.map_err(|e| RelaxedJsonRejection::Body(e.to_string()))?;
+            web::extract(&bytes).map(RelaxedJson)
+        })
+    }
+}
+
+impl ResponseError for RelaxedJsonRejection {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status()).unwrap_or(StatusCode::BAD_REQUEST)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .content_type("application/problem+json")
+            .body(self.problem().to_string())
+    }
+}
+
+impl Responder for RelaxedJsonRejection {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse {
+        self.error_response()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use actix_web::{FromRequest, Responder};
+    use serde_json::Value;
+
+    use super::RelaxedJson;
+    use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+    #[derive(Debug)]
+    struct Reading(f64);
+
+    impl TryFromJson for Reading {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            value.maybe_string("celsius").relaxed().parse().map(Reading)
+                .map_err(|_| FromJsonError::with_message("no reading"))
+        }
+    }
+
+    async fn extract(body: &'static str) -> Result<RelaxedJson<Reading>, super::RelaxedJsonRejection> {
+        let (req, mut payload) = TestRequest::default().set_payload(body).to_http_parts();
+        RelaxedJson::from_request(&req, &mut payload).await
+    }
+
+    #[tokio::test]
+    async fn actix_extractor() {
+        assert_eq!(extract("{\"celsius\": 21.5}").await.unwrap().0.0, 21.5);
+
+        let rejection = extract("{\"celsius\": \"warm\"}").await.unwrap_err();
+        assert_eq!(rejection.problem()["detail"], "no reading");
+        let response = rejection.respond_to(&TestRequest::default().to_http_request());
+        assert_eq!(response.status().as_u16(), 422);
+        assert_eq!(response.headers().get("content-type").unwrap(), "application/problem+json");
+
+        assert_eq!(extract("{").await.unwrap_err().status(), 400);
+    }
+}
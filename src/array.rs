@@ -0,0 +1,193 @@
+//! Options for reading arrays with [`maybe_array_with`](crate::MaybeValue::maybe_array_with)
+//! and helpers for arrays of records
+//!
+//! [`dedup_by`] and [`group_by`] key records with a relaxed accessor, so
+//! `{"id": 1}` and `{"id": "1"}` have the same key:
+//!
+//! ```
+//! use json_relaxed::array::{dedup_by, group_by};
+//! use json_relaxed::MaybeValue;
+//! use serde_json::json;
+//!
+//! let rows = vec!(json!({"id": 1, "team": "a"}), json!({"id": "1", "team": "a"}), json!({"id": 2, "team": "b"}));
+//! assert_eq!(dedup_by(&rows, |r| r.maybe_int("id")).len(), 2);
+//!
+//! let teams = group_by(&rows, |r| r.maybe_string("team"));
+//! assert_eq!(teams.groups["a"].len(), 2);
+//! ```
+
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Maybe;
+
+/// How elements of an array are read
+///
+/// By default every element is read and elements that fail are skipped, making
+/// the array `Relaxed`.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub(crate) fail_fast: bool,
+    pub(crate) limit: Option<usize>,
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Stop at the first element that fails and return its error, see
+    /// [`maybe_array_par`](crate::MaybeValue::maybe_array_par) for parallel reading
+    pub fn fail_fast(mut self, enable: bool) -> Self {
+        self.fail_fast = enable;
+        self
+    }
+
+    /// Read at most `limit` elements, a longer array is `Relaxed`
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// The first record of each key in order. Records whose key is `Null` or
+/// an `Error` are kept, they are not duplicates of anything.
+pub fn dedup_by<K: Ord>(records: &[Value], key: impl Fn(&Value) -> Maybe<K>) -> Vec<&Value> {
+    let mut seen = BTreeSet::new();
+    records.iter()
+        .filter(|r| match key(r) {
+            Maybe::Strict(k) | Maybe::Relaxed(k) => seen.insert(k),
+            Maybe::Null | Maybe::Error(_) => true
+        })
+        .collect()
+}
+
+/// Records grouped by key, see [`group_by`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groups<'a, K> {
+    pub groups: BTreeMap<K, Vec<&'a Value>>,
+    /// Indices of the records whose key is `Null` or an `Error`
+    pub ungrouped: Vec<usize>,
+}
+
+/// The records of each key in order
+pub fn group_by<'a, K: Ord>(records: &'a [Value], key: impl Fn(&Value) -> Maybe<K>) -> Groups<'a, K> {
+    let mut groups: BTreeMap<K, Vec<&Value>> = BTreeMap::new();
+    let mut ungrouped = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        match key(record) {
+            Maybe::Strict(k) | Maybe::Relaxed(k) => groups.entry(k).or_default().push(record),
+            Maybe::Null | Maybe::Error(_) => ungrouped.push(i)
+        }
+    }
+    Groups { groups, ungrouped }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{dedup_by, group_by, Options};
+    use crate::{coerce, FromJsonError, Maybe, MaybeValue, TryFromJson};
+
+    #[derive(Debug, PartialEq)]
+    struct Id(u64);
+
+    impl TryFromJson for Id {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            match coerce::to_uint(Some(value)) {
+                Maybe::Strict(n) | Maybe::Relaxed(n) => Ok(Id(n)),
+                Maybe::Error(e) => Err(e),
+                Maybe::Null => Err(FromJsonError::with_message("missing id"))
+            }
+        }
+    }
+
+    #[test]
+    fn array_options() {
+        let json = json!({"ids": [1, "2", [3], 4]});
+
+        assert_eq!(json.maybe_array::<Id, _>("ids").relaxed(), vec!(Id(1), Id(2), Id(4)));
+        match json.maybe_array_with::<Id, _>("ids", &Options::new().fail_fast(true)) {
+            Maybe::Error(e) => assert!(e.message().starts_with("element 2: ")),
+            _ => panic!("expected the error of the third element")
+        }
+        assert!(matches!(json.maybe_array_with::<Id, _>("ids", &Options::new().fail_fast(true).limit(2)), Maybe::Relaxed(v) if v == vec!(Id(1), Id(2))));
+        assert!(matches!(json.maybe_array_with::<Id, _>("ids", &Options::new().limit(9)), Maybe::Relaxed(v) if v.len() == 3));
+    }
+
+    #[test]
+    fn record_helpers() {
+        let rows = vec!(
+            json!({"sku": "a", "qty": 1, "store": {"id": 7}}),
+            json!({"sku": "b", "qty": "2", "store": {"id": "7"}}),
+            json!({"sku": "a", "qty": 3, "store": {"id": 8}}),
+            json!({"qty": 4, "store": {}}),
+            json!({"sku": ["x"], "qty": 5}),
+        );
+        let first: Vec<_> = dedup_by(&rows, |r| r.maybe_string("sku")).iter().map(|r| r["qty"].clone()).collect();
+        assert_eq!(first, vec!(json!(1), json!("2"), json!(4), json!(5)));
+
+        let stores = group_by(&rows, |r| r.get("store").maybe_uint("id"));
+        assert_eq!(stores.groups.keys().collect::<Vec<_>>(), vec!(&7, &8));
+        assert_eq!(stores.groups[&7], vec!(&rows[0], &rows[1]));
+        assert_eq!(stores.ungrouped, vec!(3, 4));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_arrays() {
+        let mut ids: Vec<Value> = (0..10_000).map(|i| json!(i)).collect();
+        let json = json!({"ids": ids.clone()});
+        let read = json.maybe_array_par::<Id, _>("ids", &Options::new());
+        assert!(matches!(read, Maybe::Strict(v) if v.len() == 10_000 && v[9_999] == Id(9_999)));
+
+        ids[17] = json!([17]);
+        ids[4_000] = json!("x");
+        let json = json!({"ids": ids});
+        assert!(matches!(json.maybe_array_par::<Id, _>("ids", &Options::new().limit(17)), Maybe::Relaxed(v) if v.len() == 17));
+        match json.maybe_array_par::<Id, _>("ids", &Options::new().fail_fast(true)) {
+            Maybe::Error(e) => {
+                assert!(e.message().starts_with("element 17: "));
+                assert!(e.message().contains("; element 4000: "));
+            },
+            _ => panic!("expected the errors of both elements")
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_scopes() {
+        use crate::session::Session;
+        use crate::words::WordPack;
+
+        struct Switch(bool);
+
+        impl TryFromJson for Switch {
+            fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+                match value.maybe_bool("on") {
+                    Maybe::Strict(on) | Maybe::Relaxed(on) => Ok(Switch(on)),
+                    _ => Err(FromJsonError::with_message("no switch"))
+                }
+            }
+        }
+
+        let json = json!({"items": [{"on": "ja"}, {"on": "nein"}, {"on": [1]}]});
+        let read = |par: bool| Session::new().words(WordPack::german()).run(|| {
+            let switches = match par {
+                true => json.maybe_array_par::<Switch, _>("items", &Options::new()),
+                false => json.maybe_array_with::<Switch, _>("items", &Options::new())
+            };
+            switches.relaxed().into_iter().map(|s| s.0).collect::<Vec<_>>()
+        });
+        let (sequential, trace) = read(false);
+        let (parallel, par_trace) = read(true);
+        assert_eq!(parallel, sequential);
+        assert_eq!(parallel, vec!(true, false));
+        assert_eq!(par_trace.events, trace.events);
+        assert!(trace.events.iter().any(|e| e.path == "/items/2/on"));
+        assert_eq!(par_trace.unused(&json), trace.unused(&json));
+    }
+}
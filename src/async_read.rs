@@ -0,0 +1,240 @@
+//! Async counterparts of the [`read`](crate::read) functions, based on tokio's io traits
+
+use futures_core::Stream;
+use serde_json::Value;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, Lines, ReadBuf};
+
+use crate::read::{self, Options, Parsed};
+use crate::{FromJsonError, Maybe};
+
+/// Read and parse a single json document from an async reader
+pub async fn from_async_reader<R: AsyncRead + Unpin>(mut reader: R) -> Result<Value, FromJsonError> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await?;
+    read::from_slice(&buf)
+}
+
+/// Read and parse a single json document from an async reader using `options`
+pub async fn from_async_reader_with<R: AsyncRead + Unpin>(mut reader: R, options: &Options) -> Result<Value, FromJsonError> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await?;
+    read::from_slice_with(&buf, options)
+}
+
+/// Like [`from_async_reader_with`], additionally returning the warnings of
+/// [`read::parse`]
+pub async fn parse_async_reader<R: AsyncRead + Unpin>(mut reader: R, options: &Options) -> Parsed {
+    let mut buf = Vec::new();
+    match reader.read_to_end(&mut buf).await {
+        Ok(_) => read::parse(&buf, options),
+        Err(e) => Parsed { value: Maybe::Error(e.into()), warnings: Vec::new() }
+    }
+}
+
+/// Stream the documents of newline delimited json read from `reader`.
+/// Blank lines are skipped.
+pub fn ndjson_stream<R: AsyncBufRead + Unpin>(reader: R) -> AsyncNdJson<R> {
+    ndjson_stream_with(reader, Options::default())
+}
+
+/// Like [`ndjson_stream`], parsing each line with `options`
+pub fn ndjson_stream_with<R: AsyncBufRead + Unpin>(reader: R, options: Options) -> AsyncNdJson<R> {
+    AsyncNdJson {
+        lines: reader.lines(),
+        options,
+    }
+}
+
+/// Stream over the documents of a newline delimited json input, see [`ndjson_stream`]
+pub struct AsyncNdJson<R> {
+    lines: Lines<R>,
+    options: Options,
+}
+
+impl <R: AsyncBufRead + Unpin> Stream for AsyncNdJson<R> {
+    type Item = Result<Value, FromJsonError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.lines).poll_next_line(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(None)) => return Poll::Ready(None),
+                Poll::Ready(Ok(Some(line))) if line.trim().is_empty() => continue,
+                Poll::Ready(Ok(Some(line))) => return Poll::Ready(Some(read::from_str_with(&line, &self.options))),
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e.into())))
+            }
+        }
+    }
+}
+
+/// Stream the json documents read from `reader` that are concatenated or
+/// separated by whitespace, see [`read::documents`]
+pub fn documents_stream<R: AsyncRead + Unpin>(reader: R) -> AsyncDocuments<R> {
+    AsyncDocuments {
+        reader,
+        options: Options::default(),
+        buf: Vec::new(),
+        line: 1,
+        column: 0,
+        eof: false,
+        done: false,
+    }
+}
+
+/// Stream over concatenated json documents, see [`documents_stream`].
+/// The stream ends after the first error, as the input can not be resynchronized.
+pub struct AsyncDocuments<R> {
+    reader: R,
+    options: Options,
+    /// Read but not yet parsed
+    buf: Vec<u8>,
+    /// Position of the last byte parsed
+    line: usize,
+    column: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl <R: AsyncRead + Unpin> AsyncDocuments<R> {
+
+    /// Parse each document with `options`, the limits apply per document
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Parse the first `end` bytes of the buffer, with positions in the whole input
+    fn parse(&mut self, end: usize) -> Result<Value, FromJsonError> {
+        let parsed = read::from_slice_with(&self.buf[..end], &self.options).map_err(|e| match e.position() {
+            Some((1, c)) => e.with_position(self.line, self.column + c),
+            Some((l, c)) => e.with_position(self.line + l - 1, c),
+            None => e
+        });
+        for b in self.buf.drain(..end) {
+            match b {
+                b'\n' => {
+                    self.line += 1;
+                    self.column = 0;
+                },
+                _ => self.column += 1
+            }
+        }
+        parsed
+    }
+}
+
+/// The end of the first complete document of `bytes`, if there is one
+fn document_end(bytes: &[u8]) -> Option<usize> {
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace())?;
+    let (mut depth, mut string, mut escape) = (0usize, false, false);
+    for (i, &b) in bytes.iter().enumerate().skip(start) {
+        match (string, b) {
+            (true, _) if escape => escape = false,
+            (true, b'\\') => escape = true,
+            (true, b'"') => {
+                string = false;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            },
+            (true, _) => {},
+            (false, b'"') if depth == 0 && i > start => return Some(i),
+            (false, b'"') => string = true,
+            (false, b'[' | b'{') if depth == 0 && i > start => return Some(i),
+            (false, b'[' | b'{') => depth += 1,
+            (false, b']' | b'}') if depth > 1 => depth -= 1,
+            (false, b']' | b'}') if depth == 1 => return Some(i + 1),
+            (false, b) if depth == 0 && i > start && (b.is_ascii_whitespace() || b == b']' || b == b'}') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+impl <R: AsyncRead + Unpin> Stream for AsyncDocuments<R> {
+    type Item = Result<Value, FromJsonError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if let Some(end) = document_end(&this.buf) {
+                let parsed = this.parse(end);
+                this.done = parsed.is_err();
+                return Poll::Ready(Some(parsed));
+            }
+            if this.eof {
+                // an incomplete document or a scalar at the end of the input
+                this.done = true;
+                return match this.buf.iter().all(|b| b.is_ascii_whitespace()) {
+                    true => Poll::Ready(None),
+                    false => Poll::Ready(Some(this.parse(this.buf.len())))
+                };
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut read = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.reader).poll_read(cx, &mut read) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(())) if read.filled().is_empty() => this.eof = true,
+                Poll::Ready(Ok(())) => this.buf.extend_from_slice(read.filled()),
+                Poll::Ready(Err(e)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use futures_core::Stream;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    use crate::read::{DuplicateKeys, Options};
+    use crate::{Maybe, MaybeValue};
+
+    #[tokio::test]
+    async fn async_document_and_stream() {
+        let doc = super::from_async_reader(&b"{\"foo\": \"23\"}"[..]).await.unwrap();
+        assert_eq!(doc.maybe_int("foo").relaxed(), 23);
+
+        let mut stream = super::ndjson_stream(&b"{\"id\": 1}\n\n{\"id\": 2}\n"[..]);
+        let mut ids = Vec::new();
+        while let Some(doc) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            ids.push(doc.unwrap().maybe_int("id").relaxed());
+        }
+        assert_eq!(ids, vec!(1, 2));
+    }
+
+    #[tokio::test]
+    async fn options_and_documents() {
+        let lenient = Options::new().lenient(true);
+        let doc = super::from_async_reader_with(&b"{foo: [1, 2,], // two\n}"[..], &lenient).await.unwrap();
+        assert_eq!(doc["foo"].as_array().map(Vec::len), Some(2));
+        let parsed = super::parse_async_reader(&b"{\"a\": 1, \"a\": 2}"[..], &Options::new().duplicate_keys(DuplicateKeys::Warn)).await;
+        assert!(matches!(parsed.value, Maybe::Relaxed(_)));
+        assert_eq!(parsed.warnings.len(), 1);
+
+        let mut stream = super::documents_stream(&b"{\"id\": \"}\"}[2] 3\n\"4\"{\"id\": 5} 6"[..]);
+        let mut docs = Vec::new();
+        while let Some(doc) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            docs.push(doc.unwrap().to_string());
+        }
+        assert_eq!(docs, vec!("{\"id\":\"}\"}", "[2]", "3", "\"4\"", "{\"id\":5}", "6"));
+
+        let mut stream = super::documents_stream(&b"{\"a\": 1}\n{\"a\": x}"[..]);
+        let first = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await.unwrap();
+        assert!(first.is_ok());
+        let error = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await.unwrap().unwrap_err();
+        assert_eq!(error.position(), Some((2, 7)));
+        assert!(poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await.is_none());
+    }
+}
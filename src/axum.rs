@@ -0,0 +1,84 @@
+//! An axum extractor reading request bodies with this crate
+//!
+//! Unlike `axum::Json` the content type is not checked, the body is read with
+//! [`read`](crate::read) and converted with [`TryFromJson`], so models written with
+//! the relaxed accessors accept numeric strings or missing optional values.
+
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::{web, TryFromJson};
+
+pub use crate::web::RelaxedJsonRejection;
+
+/// Extracts `T` from a json request body, see the [module documentation](self)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RelaxedJson<T>(pub T);
+
+impl IntoResponse for RelaxedJsonRejection {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status()).unwrap_or(StatusCode::BAD_REQUEST);
+        (status, [(header::CONTENT_TYPE, "application/problem+json")], self.problem().to_string()).into_response()
+    }
+}
+
+impl <T: TryFromJson, S: Send + Sync> FromRequest<S> for RelaxedJson<T> {
+    type Rejection = RelaxedJsonRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, RelaxedJsonRejection> {
+        let bytes = Bytes::from_request(req, state).await
+            .map_err(|e| RelaxedJsonRejection::Body(e.body_text()))?;
+        web::extract(&bytes).map(RelaxedJson)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use axum::body::{to_bytes, Body};
+    use axum::extract::{FromRequest, Request};
+    use axum::response::IntoResponse;
+    use serde_json::{json, Value};
+
+    use super::RelaxedJson;
+    use crate::{FromJsonError, Maybe, MaybeValue, TryFromJson};
+
+    #[derive(Debug)]
+    struct Order {
+        id: u64,
+        note: String,
+    }
+
+    impl TryFromJson for Order {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            Ok(Order {
+                id: match value.maybe_uint("id") {
+                    Maybe::Strict(id) | Maybe::Relaxed(id) => id,
+                    Maybe::Null => return Err(FromJsonError::with_message("missing id")),
+                    Maybe::Error(e) => return Err(e)
+                },
+                note: value.maybe_string("note").relaxed(),
+            })
+        }
+    }
+
+    async fn extract(body: &'static str) -> Result<RelaxedJson<Order>, super::RelaxedJsonRejection> {
+        RelaxedJson::from_request(Request::new(Body::from(body)), &()).await
+    }
+
+    #[tokio::test]
+    async fn axum_extractor() {
+        let RelaxedJson(order) = extract("{\"id\": \"17\"}").await.unwrap();
+        assert_eq!((order.id, order.note.as_str()), (17, ""));
+
+        let rejection = extract("{\"id\": [").await.unwrap_err();
+        let response = rejection.into_response();
+        assert_eq!(response.status(), 400);
+        let body: Value = serde_json::from_slice(&to_bytes(response.into_body(), 1024).await.unwrap()).unwrap();
+        assert_eq!((&body["status"], &body["line"]), (&json!(400), &json!(1)));
+
+        assert_eq!(extract("{\"note\": \"no id\"}").await.unwrap_err().status(), 422);
+    }
+}
@@ -0,0 +1,542 @@
+//! The view of a value tree the relaxed extraction works on
+//!
+//! [`MaybeValue`](crate::MaybeValue) is implemented for every [`JsonLike`] type, so
+//! other value trees get the same coercions by implementing this small trait.
+//! Implementations for `serde_json::Value`, `serde_json::Map`, `[Value]` and
+//! `Vec<Value>` are provided, and one for `simd_json::OwnedValue` with the
+//! `simd-json` feature. References and options of references to any of them work
+//! the same, `None` reading as null.
+
+use serde_json::{Map, Value};
+
+/// The json type of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl Kind {
+    /// Name of the type, as used in error messages
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Null => "null",
+            Kind::Bool => "bool",
+            Kind::Number => "number",
+            Kind::String => "string",
+            Kind::Array => "array",
+            Kind::Object => "object",
+        }
+    }
+}
+
+/// A node of a json value tree
+pub trait JsonLike {
+    /// Type of the members and elements
+    type Node: JsonLike<Node = Self::Node>;
+
+    fn kind(&self) -> Kind;
+
+    /// Member `key` of an object, None for other types
+    fn get_key(&self, key: &str) -> Option<&Self::Node>;
+
+    /// Element `index` of an array, None for other types
+    fn get_index(&self, index: usize) -> Option<&Self::Node>;
+
+    /// Number of elements of an array if known, used to size buffers
+    fn array_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn as_bool(&self) -> Option<bool>;
+    fn as_i64(&self) -> Option<i64>;
+    fn as_u64(&self) -> Option<u64>;
+    fn as_f64(&self) -> Option<f64>;
+    fn as_str(&self) -> Option<&str>;
+
+    /// Text of a number as written in json
+    fn number_string(&self) -> Option<String> {
+        match (self.as_i64(), self.as_u64(), self.as_f64()) {
+            (Some(i), _, _) => Some(s!(itoa::Buffer::new().format(i))),
+            (_, Some(u), _) => Some(s!(itoa::Buffer::new().format(u))),
+            (_, _, Some(f)) => Some(f.to_string()),
+            _ => None
+        }
+    }
+}
+
+/// Keys for looking up a member or element of a [`JsonLike`]
+pub trait Key {
+    fn lookup<'a, J: JsonLike + ?Sized>(&self, json: &'a J) -> Option<&'a J::Node>;
+
+    /// Mutable lookup in a `serde_json::Value`, for moving values out
+    fn lookup_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value>;
+
+    /// The key as text, for messages and events
+    fn name(&self) -> String;
+}
+
+impl Key for str {
+    fn lookup<'a, J: JsonLike + ?Sized>(&self, json: &'a J) -> Option<&'a J::Node> {
+        json.get_key(self)
+    }
+
+    fn lookup_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        value.as_object_mut()?.get_mut(self)
+    }
+
+    fn name(&self) -> String {
+        s!(self)
+    }
+}
+
+impl Key for String {
+    fn lookup<'a, J: JsonLike + ?Sized>(&self, json: &'a J) -> Option<&'a J::Node> {
+        json.get_key(self)
+    }
+
+    fn lookup_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        value.as_object_mut()?.get_mut(self)
+    }
+
+    fn name(&self) -> String {
+        self.clone()
+    }
+}
+
+impl Key for usize {
+    fn lookup<'a, J: JsonLike + ?Sized>(&self, json: &'a J) -> Option<&'a J::Node> {
+        json.get_index(*self)
+    }
+
+    fn lookup_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        value.as_array_mut()?.get_mut(*self)
+    }
+
+    fn name(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl <T: Key + ?Sized> Key for &T {
+    fn lookup<'a, J: JsonLike + ?Sized>(&self, json: &'a J) -> Option<&'a J::Node> {
+        (**self).lookup(json)
+    }
+
+    fn lookup_mut<'a>(&self, value: &'a mut Value) -> Option<&'a mut Value> {
+        (**self).lookup_mut(value)
+    }
+
+    fn name(&self) -> String {
+        (**self).name()
+    }
+}
+
+impl JsonLike for Value {
+    type Node = Value;
+
+    fn kind(&self) -> Kind {
+        match self {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Array(_) => Kind::Array,
+            Value::Object(_) => Kind::Object,
+        }
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Value> {
+        self.as_array()?.get(index)
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        self.as_array().map(Vec::len)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        Value::as_bool(self)
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        Value::as_i64(self)
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        Value::as_u64(self)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Value::as_f64(self)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        Value::as_str(self)
+    }
+
+    fn number_string(&self) -> Option<String> {
+        match self {
+            Value::Number(n) => Some(format_number(n)),
+            _ => None
+        }
+    }
+}
+
+/// The members of an object, as from `Value::as_object`
+impl JsonLike for Map<String, Value> {
+    type Node = Value;
+
+    fn kind(&self) -> Kind {
+        Kind::Object
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Value> {
+        self.get(key)
+    }
+
+    fn get_index(&self, _index: usize) -> Option<&Value> {
+        None
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        None
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        None
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        None
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The elements of an array, as from `Value::as_array`
+impl JsonLike for [Value] {
+    type Node = Value;
+
+    fn kind(&self) -> Kind {
+        Kind::Array
+    }
+
+    fn get_key(&self, _key: &str) -> Option<&Value> {
+        None
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Value> {
+        self.get(index)
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        None
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        None
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        None
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The records of a document already split into elements
+impl JsonLike for Vec<Value> {
+    type Node = Value;
+
+    fn kind(&self) -> Kind {
+        Kind::Array
+    }
+
+    fn get_key(&self, _key: &str) -> Option<&Value> {
+        None
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Value> {
+        self.get(index)
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        None
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        None
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        None
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl <T: JsonLike + ?Sized> JsonLike for &T {
+    type Node = T::Node;
+
+    fn kind(&self) -> Kind {
+        (**self).kind()
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self::Node> {
+        (**self).get_key(key)
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Self::Node> {
+        (**self).get_index(index)
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        (**self).array_len()
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        (**self).as_bool()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        (**self).as_i64()
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        (**self).as_u64()
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        (**self).as_f64()
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        (**self).as_str()
+    }
+
+    fn number_string(&self) -> Option<String> {
+        (**self).number_string()
+    }
+}
+
+/// A node that may be missing, as from `Value::get`. `None` reads as null, so
+/// `doc.get("a").maybe_int("b")` is `Null` if either is missing.
+impl <T: JsonLike + ?Sized> JsonLike for Option<&T> {
+    type Node = T::Node;
+
+    fn kind(&self) -> Kind {
+        self.map_or(Kind::Null, |v| v.kind())
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self::Node> {
+        (*self)?.get_key(key)
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Self::Node> {
+        (*self)?.get_index(index)
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        (*self)?.array_len()
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        (*self)?.as_bool()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        (*self)?.as_i64()
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        (*self)?.as_u64()
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        (*self)?.as_f64()
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        (*self)?.as_str()
+    }
+
+    fn number_string(&self) -> Option<String> {
+        (*self)?.number_string()
+    }
+}
+
+/// The text of Number's Display, integers are written by itoa without a Formatter.
+/// Floats keep serde_json's own shortest representation, like `1e+21`, which
+/// ryu would write differently.
+#[cfg(not(feature = "arbitrary_precision"))]
+fn format_number(n: &serde_json::Number) -> String {
+    match (n.as_i64(), n.as_u64()) {
+        (Some(i), _) => s!(itoa::Buffer::new().format(i)),
+        (_, Some(u)) => s!(itoa::Buffer::new().format(u)),
+        _ => n.to_string()
+    }
+}
+
+/// Numbers keep their text as written
+#[cfg(feature = "arbitrary_precision")]
+fn format_number(n: &serde_json::Number) -> String {
+    s!(n.as_str())
+}
+
+#[cfg(feature = "simd-json")]
+impl JsonLike for simd_json::OwnedValue {
+    type Node = simd_json::OwnedValue;
+
+    fn kind(&self) -> Kind {
+        use simd_json::{OwnedValue, StaticNode};
+        match self {
+            OwnedValue::Static(StaticNode::Null) => Kind::Null,
+            OwnedValue::Static(StaticNode::Bool(_)) => Kind::Bool,
+            OwnedValue::Static(_) => Kind::Number,
+            OwnedValue::String(_) => Kind::String,
+            OwnedValue::Array(_) => Kind::Array,
+            OwnedValue::Object(_) => Kind::Object,
+        }
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self> {
+        match self {
+            simd_json::OwnedValue::Object(o) => o.get(key),
+            _ => None
+        }
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Self> {
+        match self {
+            simd_json::OwnedValue::Array(a) => a.get(index),
+            _ => None
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match self {
+            simd_json::OwnedValue::Array(a) => Some(a.len()),
+            _ => None
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        simd_json::prelude::ValueAsScalar::as_bool(self)
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        simd_json::prelude::ValueAsScalar::as_i64(self)
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        simd_json::prelude::ValueAsScalar::as_u64(self)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        simd_json::prelude::ValueAsScalar::cast_f64(self)
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        simd_json::prelude::ValueAsScalar::as_str(self)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{JsonLike, Kind};
+    use crate::{Maybe, MaybeValue};
+
+    #[test]
+    fn value_backend() {
+        let doc = json!({"a": [1, "2"], "b": {"c": true}});
+        assert_eq!(doc.kind(), Kind::Object);
+        assert_eq!(doc.get_key("a").and_then(|a| a.get_index(1)).and_then(|v| JsonLike::as_str(v)), Some("2"));
+        assert_eq!(doc.get_index(0), None);
+        assert_eq!(doc["a"].maybe_int(1).relaxed(), 2);
+        assert_eq!(doc.maybe_string(s!("a")).strict(), None);
+
+        let members = doc["b"].as_object().unwrap();
+        assert!(members.maybe_bool("c").strict().unwrap());
+        assert!(matches!(members.maybe_int("d"), Maybe::Null));
+        assert_eq!(members.maybe_int(0usize).strict(), None);
+    }
+
+    #[test]
+    fn optional_chaining() {
+        let doc = json!({"a": {"b": "7", "c": null}});
+        assert_eq!(doc.get("a").maybe_int("b").relaxed(), 7);
+        assert!(matches!(doc.get("x").maybe_int("b"), Maybe::Null));
+        assert!(matches!(doc.get("a").and_then(|a| a.get("c")).maybe_string(0usize), Maybe::Null));
+        assert_eq!(doc.get("a").kind(), Kind::Object);
+
+        fn port<J: MaybeValue>(node: J) -> u64 {
+            node.maybe_uint("port").relaxed()
+        }
+        let server = json!({"port": 80});
+        assert_eq!(port(&server), 80);
+        assert_eq!(port(server.as_object().unwrap()), 80);
+        assert_eq!(port(doc.get("server")), 0);
+    }
+
+    #[test]
+    fn element_backends() {
+        let records = vec!(json!({"id": "1"}), json!("2"), json!(null));
+        assert_eq!(records.maybe_int(1usize).relaxed(), 2);
+        assert!(matches!(records.maybe_int(2usize), Maybe::Null));
+        assert!(matches!(records.maybe_int("id"), Maybe::Null));
+        assert_eq!(records[..1].maybe_object::<Value, _>(0usize).strict(), Some(json!({"id": "1"})));
+        assert_eq!(records.as_slice().array_len(), Some(3));
+
+        for n in [json!(-7), json!(u64::MAX), json!(0.1), json!(1e21), json!(-2.5e-8)] {
+            assert_eq!(n.number_string(), Some(n.to_string()));
+        }
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn simd_backend() {
+        let mut text = br#"{"port": "8080", "debug": 1, "hosts": ["a", "b"]}"#.to_vec();
+        let doc = simd_json::to_owned_value(&mut text).unwrap();
+
+        assert_eq!(doc.maybe_uint("port").relaxed(), 8080);
+        assert!(doc.maybe_bool("debug").relaxed());
+        assert_eq!(doc.get_key("hosts").unwrap().maybe_string(1).strict(), Some(s!("b")));
+    }
+}
@@ -0,0 +1,157 @@
+//! Validate, fix, pretty-print and query json with the relaxed reader
+//!
+//! ```text
+//! json-relaxed [--strict] [--transcode] [--duplicates POLICY] COMMAND [FILE]
+//!
+//!   validate         check the input, report errors with an excerpt
+//!   fix              write the input as compact strict json
+//!   pretty           write the input as indented strict json
+//!   query POINTER    write the values at a JSON Pointer, `*` matches any member or element
+//! ```
+//!
+//! Input is read from FILE or standard input. Comments, trailing commas,
+//! unquoted keys and single quoted strings are accepted unless `--strict` is given.
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+use json_relaxed::diagnostic::diagnostic;
+use json_relaxed::read::{self, DuplicateKeys, Options};
+use json_relaxed::validate::select;
+use json_relaxed::{FromJsonError, Maybe};
+
+const USAGE: &str = "usage: json-relaxed [--strict] [--transcode] [--duplicates last|first|warn|merge|error] \
+    (validate | fix | pretty | query POINTER) [FILE]";
+
+enum Command {
+    Validate,
+    Fix,
+    Pretty,
+    Query(String),
+}
+
+struct Args {
+    command: Command,
+    file: Option<String>,
+    options: Options,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut options = Options::new().lenient(true);
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => options = options.lenient(false),
+            "--transcode" => options = options.transcode(true),
+            "--duplicates" => {
+                let policy = match args.next().as_deref() {
+                    Some("last") => DuplicateKeys::LastWins,
+                    Some("first") => DuplicateKeys::FirstWins,
+                    Some("warn") => DuplicateKeys::Warn,
+                    Some("merge") => DuplicateKeys::Merge,
+                    Some("error") => DuplicateKeys::Error,
+                    _ => return Err(String::from("--duplicates takes last, first, warn, merge or error"))
+                };
+                options = options.duplicate_keys(policy);
+            },
+            "-h" | "--help" => return Err(String::from(USAGE)),
+            _ => positional.push(arg)
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next().as_deref() {
+        Some("validate") => Command::Validate,
+        Some("fix") => Command::Fix,
+        Some("pretty") => Command::Pretty,
+        Some("query") => match positional.next() {
+            Some(pointer) => Command::Query(pointer),
+            None => return Err(String::from("query needs a JSON Pointer"))
+        },
+        _ => return Err(String::from(USAGE))
+    };
+    let file = positional.next();
+    match positional.next() {
+        Some(extra) => Err(format!("unexpected argument {}", extra)),
+        None => Ok(Args { command, file, options })
+    }
+}
+
+fn run(args: Args) -> Result<bool, FromJsonError> {
+    let mut input = Vec::new();
+    match &args.file {
+        Some(path) => input = std::fs::read(path)?,
+        None => {
+            io::stdin().read_to_end(&mut input)?;
+        }
+    }
+    let origin = args.file.as_deref().unwrap_or("<stdin>");
+    let source = String::from_utf8_lossy(&input);
+
+    let parsed = read::parse(&input, &args.options);
+    for warning in parsed.warnings.iter() {
+        eprintln!("{}", diagnostic(warning, &source).origin(origin));
+    }
+    let value = match parsed.value {
+        Maybe::Strict(v) | Maybe::Relaxed(v) => v,
+        Maybe::Error(e) => {
+            eprintln!("{}", diagnostic(&e, &source).origin(origin));
+            return Ok(false);
+        },
+        Maybe::Null => return Ok(false)
+    };
+
+    let mut out = io::stdout().lock();
+    match args.command {
+        Command::Validate => writeln!(out, "ok")?,
+        Command::Fix => writeln!(out, "{}", value)?,
+        Command::Pretty => writeln!(out, "{}", serde_json::to_string_pretty(&value).map_err(FromJsonError::from)?)?,
+        Command::Query(pointer) => {
+            let found = select(&value, &pointer);
+            for (_, v) in found.iter() {
+                writeln!(out, "{}", v)?;
+            }
+            return Ok(!found.is_empty());
+        }
+    }
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("{}", msg);
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use json_relaxed::read;
+    use serde_json::json;
+
+    #[test]
+    fn arguments() {
+        let args = super::parse_args(vec!("--duplicates", "error", "query", "/a").into_iter().map(String::from)).unwrap();
+        assert_eq!(args.file, None);
+        assert!(super::parse_args(vec!(String::from("nope")).into_iter()).is_err());
+
+        let input = b"{a: 1, // c\n \"b\": [1,2,],}";
+        let fix = super::parse_args(vec!(String::from("fix")).into_iter()).unwrap();
+        assert_eq!(read::parse(input, &fix.options).value.relaxed(), json!({"a": 1, "b": [1, 2]}));
+        let strict = super::parse_args(vec!("--strict", "validate").into_iter().map(String::from)).unwrap();
+        assert!(read::from_slice_with(input, &strict.options).is_err());
+    }
+}
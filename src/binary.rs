@@ -0,0 +1,204 @@
+//! Reading MessagePack and CBOR documents into json values
+//!
+//! Binary payloads have no json counterpart and are written as base64 text or as
+//! arrays of byte values, see [`Bytes`]. MessagePack extension values are read as
+//! their payload, CBOR tags are dropped. Integers outside the 64 bit range become
+//! strings, NaN or infinite floats null and scalar keys their text.
+
+use serde_json::{Map, Number, Value};
+
+use crate::FromJsonError;
+
+/// Representation of binary payloads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bytes {
+    /// Standard base64 text with padding
+    #[default]
+    Base64,
+    /// An array of numbers from 0 to 255
+    Array,
+}
+
+/// Options for reading binary formats
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    bytes: Bytes,
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn bytes(mut self, bytes: Bytes) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    fn payload(&self, bytes: &[u8]) -> Value {
+        match self.bytes {
+            Bytes::Base64 => Value::String(base64(bytes)),
+            Bytes::Array => Value::Array(bytes.iter().map(|b| Value::Number((*b).into())).collect())
+        }
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char),
+                false => out.push('=')
+            }
+        }
+    }
+    out
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+}
+
+/// Object keys must be strings, other scalars are written as their text
+fn key(value: Value) -> Result<String, FromJsonError> {
+    match value {
+        Value::String(s) => Ok(s),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        Value::Null => Ok(s!("null")),
+        _ => Err(FromJsonError::with_message("key must be a scalar"))
+    }
+}
+
+/// Parse a single MessagePack document
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack(bytes: &[u8], options: &Options) -> Result<Value, FromJsonError> {
+    let mut rest = bytes;
+    let value = match rmpv::decode::read_value(&mut rest) {
+        Ok(v) => v,
+        Err(e) => return Err(FromJsonError::with_message(&e.to_string()))
+    };
+    match rest.is_empty() {
+        true => msgpack(value, options),
+        false => Err(FromJsonError::with_message(&format!("trailing bytes at offset {}", bytes.len() - rest.len())))
+    }
+}
+
+#[cfg(feature = "msgpack")]
+fn msgpack(value: rmpv::Value, options: &Options) -> Result<Value, FromJsonError> {
+    use rmpv::Value as Msgpack;
+
+    Ok(match value {
+        Msgpack::Nil => Value::Null,
+        Msgpack::Boolean(b) => Value::Bool(b),
+        Msgpack::Integer(i) => {
+            match (i.as_i64(), i.as_u64()) {
+                (Some(n), _) => Value::Number(n.into()),
+                (_, Some(n)) => Value::Number(n.into()),
+                _ => Value::String(i.to_string())
+            }
+        },
+        Msgpack::F32(f) => float(f as f64),
+        Msgpack::F64(f) => float(f),
+        Msgpack::String(s) => {
+            match s.as_str() {
+                Some(text) => Value::String(s!(text)),
+                None => Value::String(String::from_utf8_lossy(s.as_bytes()).into_owned())
+            }
+        },
+        Msgpack::Binary(b) | Msgpack::Ext(_, b) => options.payload(&b),
+        Msgpack::Array(a) => {
+            Value::Array(a.into_iter().map(|v| msgpack(v, options)).collect::<Result<_, _>>()?)
+        },
+        Msgpack::Map(entries) => {
+            let mut map = Map::new();
+            for (k, v) in entries {
+                map.insert(key(msgpack(k, options)?)?, msgpack(v, options)?);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+/// Parse a single CBOR document
+#[cfg(feature = "cbor")]
+pub fn from_cbor(bytes: &[u8], options: &Options) -> Result<Value, FromJsonError> {
+    match ciborium::de::from_reader::<ciborium::Value, _>(bytes) {
+        Ok(v) => cbor(v, options),
+        Err(e) => Err(FromJsonError::with_message(&e.to_string()))
+    }
+}
+
+#[cfg(feature = "cbor")]
+fn cbor(value: ciborium::Value, options: &Options) -> Result<Value, FromJsonError> {
+    use ciborium::Value as Cbor;
+    use std::convert::TryFrom;
+
+    Ok(match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(b),
+        Cbor::Integer(i) => {
+            let wide = i128::from(i);
+            match (i64::try_from(wide), u64::try_from(wide)) {
+                (Ok(n), _) => Value::Number(n.into()),
+                (_, Ok(n)) => Value::Number(n.into()),
+                _ => Value::String(wide.to_string())
+            }
+        },
+        Cbor::Float(f) => float(f),
+        Cbor::Text(s) => Value::String(s),
+        Cbor::Bytes(b) => options.payload(&b),
+        Cbor::Tag(_, inner) => cbor(*inner, options)?,
+        Cbor::Array(a) => {
+            Value::Array(a.into_iter().map(|v| cbor(v, options)).collect::<Result<_, _>>()?)
+        },
+        Cbor::Map(entries) => {
+            let mut map = Map::new();
+            for (k, v) in entries {
+                map.insert(key(cbor(k, options)?)?, cbor(v, options)?);
+            }
+            Value::Object(map)
+        },
+        _ => return Err(FromJsonError::unexpected())
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Bytes, Options};
+
+    #[test]
+    fn base64() {
+        assert_eq!(super::base64(b""), "");
+        assert_eq!(super::base64(b"f"), "Zg==");
+        assert_eq!(super::base64(b"fo"), "Zm8=");
+        assert_eq!(super::base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(Options::new().bytes(Bytes::Array).payload(b"\x01\xFF"), json!([1, 255]));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_documents() {
+        // {"id": 7, 1: bin[0x01, 0xFF], "t": -1.5}
+        let msgpack = b"\x83\xA2id\x07\x01\xC4\x02\x01\xFF\xA1t\xCB\xBF\xF8\x00\x00\x00\x00\x00\x00";
+        assert_eq!(super::from_msgpack(msgpack, &Options::new()).unwrap(), json!({"id": 7, "1": "Af8=", "t": -1.5}));
+        let array = Options::new().bytes(Bytes::Array);
+        assert_eq!(super::from_msgpack(msgpack, &array).unwrap()["1"], json!([1, 255]));
+        assert!(super::from_msgpack(b"\x07\x07", &array).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_documents() {
+        // {"a": [1, tag 1(1000)], "b": h'01ff'}
+        let cbor = b"\xA2\x61a\x82\x01\xC1\x19\x03\xE8\x61b\x42\x01\xFF";
+        assert_eq!(super::from_cbor(cbor, &Options::new()).unwrap(), json!({"a": [1, 1000], "b": "Af8="}));
+    }
+}
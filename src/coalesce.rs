@@ -0,0 +1,65 @@
+//! Fallback chains across documents
+//!
+//! Settings often come from several documents in order of precedence, like
+//! the request, the user's profile, the tenant's and the global defaults.
+//! [`maybe_coalesce`] reads the same value from each and keeps the first that
+//! is not `Null`, with the index of the document that supplied it:
+//!
+//! ```
+//! use json_relaxed::coalesce::maybe_coalesce;
+//! use json_relaxed::MaybeValue;
+//! use serde_json::json;
+//!
+//! let request = json!({});
+//! let profile = json!({"limits": {"rate": null}});
+//! let tenant = json!({"limits": {"rate": "50"}});
+//! let global = json!({"limits": {"rate": 10}});
+//!
+//! let rate = maybe_coalesce([&request, &profile, &tenant, &global], |doc| doc.get("limits").maybe_uint("rate"));
+//! assert_eq!(rate.source, Some(2));
+//! assert_eq!(rate.value.relaxed(), 50);
+//! ```
+
+use crate::Maybe;
+
+/// The first result that is not `Null` and the index of its document
+pub struct Coalesced<T> {
+    pub value: Maybe<T>,
+    /// None if every document was `Null`
+    pub source: Option<usize>,
+}
+
+/// Read each of `documents` with `read` until one is not `Null`. An `Error`
+/// is not skipped, a malformed value in a preferred document is reported with
+/// its source instead of being replaced by a fallback.
+pub fn maybe_coalesce<D, T>(documents: impl IntoIterator<Item = D>, read: impl Fn(D) -> Maybe<T>) -> Coalesced<T> {
+    for (index, document) in documents.into_iter().enumerate() {
+        match read(document) {
+            Maybe::Null => continue,
+            value => return Coalesced { value, source: Some(index) }
+        }
+    }
+    Coalesced { value: Maybe::Null, source: None }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::maybe_coalesce;
+    use crate::{Maybe, MaybeValue};
+
+    #[test]
+    fn first_non_null() {
+        let layers = [json!({"theme": null, "size": "x"}), json!({"theme": "dark", "size": 3}), json!({"theme": "light"})];
+        let theme = maybe_coalesce(&layers, |doc| doc.maybe_string("theme"));
+        assert_eq!((theme.source, theme.value.strict()), (Some(1), Some(s!("dark"))));
+
+        let size = maybe_coalesce(&layers, |doc| doc.maybe_int("size"));
+        assert!(matches!((size.source, size.value), (Some(0), Maybe::Error(_))));
+
+        let missing = maybe_coalesce(&layers, |doc| doc.maybe_bool("debug"));
+        assert!(matches!((missing.source, missing.value), (None, Maybe::Null)));
+    }
+}
@@ -0,0 +1,405 @@
+//! The coercion rules behind the maybe_* accessors, applied to a single value
+
+use std::borrow::Cow;
+
+use crate::backend::{JsonLike, Kind};
+use crate::{array, limits, session, words, FromJsonError, Maybe, TryFromJson, TryFromJsonRef};
+
+pub(crate) fn to_object<J: JsonLike + ?Sized, T: TryFromJson<J>>(value: Option<&J>) -> Maybe<T> {
+
+    let _depth = match limits::enter() {
+        Ok(guard) => guard,
+        Err(e) => return Maybe::Error(e)
+    };
+
+    match value {
+
+        Some(v) => {
+            let t: Result<T,_> = T::try_from_json(v);
+            match t {
+                Ok(tv) => Maybe::Strict(tv),
+                Err(e) => Maybe::Error(e)
+            }
+        },
+
+        None => Maybe::Null
+
+    }
+}
+
+pub(crate) fn to_object_ref<'a, J: JsonLike + ?Sized, T: TryFromJsonRef<'a, J>>(value: Option<&'a J>) -> Maybe<T> {
+
+    let _depth = match limits::enter() {
+        Ok(guard) => guard,
+        Err(e) => return Maybe::Error(e)
+    };
+
+    match value {
+        Some(v) => match T::try_from_json_ref(v) {
+            Ok(tv) => Maybe::Strict(tv),
+            Err(e) => Maybe::Error(e)
+        },
+        None => Maybe::Null
+    }
+}
+
+pub(crate) fn to_node<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<&J> {
+    match value {
+        Some(v) if v.kind() != Kind::Null => Maybe::Strict(v),
+        _ => Maybe::Null
+    }
+}
+
+pub(crate) fn to_array<J: JsonLike<Node = J>, T: TryFromJson<J>>(value: Option<&J>) -> Maybe<Vec<T>> {
+    to_array_with(value, &array::Options::default())
+}
+
+pub(crate) fn to_array_with<J: JsonLike<Node = J>, T: TryFromJson<J>>(value: Option<&J>, options: &array::Options) -> Maybe<Vec<T>> {
+
+    let _depth = match limits::enter() {
+        Ok(guard) => guard,
+        Err(e) => return Maybe::Error(e)
+    };
+
+    match value {
+        Some(a) if a.kind() == Kind::Array => {
+
+            let limit = options.limit.unwrap_or(usize::MAX);
+            let mut collect: Vec<T> = Vec::with_capacity(a.array_len().unwrap_or(0).min(limit));
+            let mut clean = true;
+            let elements = (0..).map_while(|i| a.get_index(i));
+            for (index, i) in elements.enumerate() {
+                if index == limit {
+                    clean = false;
+                    break;
+                }
+                let _path = session::enter_index(index);
+                match T::try_from_json(i) {
+                    Ok(v) => collect.push(v),
+                    Err(e) if options.fail_fast => {
+                        return Maybe::Error(FromJsonError::with_message(&format!("element {}: {}", index, e.message())));
+                    },
+                    Err(_) => {
+                        clean = false;
+                    }
+                }
+            }
+
+            match clean {
+                true => Maybe::Strict(collect),
+                false => Maybe::Relaxed(collect)
+            }
+
+        },
+        Some(v) => {
+            match T::try_from_json(v) {
+                Ok(t) => Maybe::Relaxed(vec!(t)),
+                Err(e) => Maybe::Error(e)
+            }
+        },
+        None => {
+            Maybe::Null
+        }
+    }
+}
+
+/// Like [`to_array_with`], with the elements read on the rayon thread pool
+#[cfg(feature = "rayon")]
+pub(crate) fn to_array_par<J: JsonLike<Node = J> + Sync, T: TryFromJson<J> + Send>(value: Option<&J>, options: &array::Options) -> Maybe<Vec<T>> {
+    use rayon::prelude::*;
+
+    let a = match value {
+        Some(a) if a.kind() == Kind::Array => a,
+        _ => return to_array_with(value, options)
+    };
+    let _depth = match limits::enter() {
+        Ok(guard) => guard,
+        Err(e) => return Maybe::Error(e)
+    };
+
+    let limit = options.limit.unwrap_or(usize::MAX);
+    let mut elements: Vec<&J> = Vec::with_capacity(a.array_len().unwrap_or(0).min(limit));
+    elements.extend((0..).map_while(|i| a.get_index(i)).take(limit));
+    let mut clean = a.get_index(elements.len()).is_none();
+
+    let fork = session::fork();
+    let results: Vec<(Result<T,FromJsonError>, session::Joined)> = elements.par_iter()
+        .enumerate()
+        .map(|(index, e)| session::resume(&fork, || {
+            let _path = session::enter_index(index);
+            T::try_from_json(e)
+        }))
+        .collect();
+
+    let mut collect = Vec::with_capacity(results.len());
+    let mut errors = Vec::new();
+    for (index, (r, joined)) in results.into_iter().enumerate() {
+        session::join(joined);
+        match r {
+            Ok(v) => collect.push(v),
+            Err(e) => {
+                clean = false;
+                errors.push(format!("element {}: {}", index, e.message()));
+            }
+        }
+    }
+
+    match (clean, options.fail_fast && !errors.is_empty()) {
+        (_, true) => Maybe::Error(FromJsonError::with_message(&errors.join("; "))),
+        (true, false) => Maybe::Strict(collect),
+        (false, false) => Maybe::Relaxed(collect)
+    }
+}
+
+pub(crate) fn to_string<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<String> {
+    match to_str(value) {
+        Maybe::Strict(s) => Maybe::Strict(s.into_owned()),
+        Maybe::Relaxed(s) => Maybe::Relaxed(s.into_owned()),
+        Maybe::Error(e) => Maybe::Error(e),
+        Maybe::Null => Maybe::Null
+    }
+}
+
+/// Strings are borrowed, only relaxed values and resolved secrets are formatted
+pub(crate) fn to_str<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<Cow<'_, str>> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    match v.kind() {
+        Kind::Null => Maybe::Null,
+        Kind::Bool => Maybe::Relaxed(Cow::Borrowed(match v.as_bool().unwrap_or_default() {
+            true => "true",
+            false => "false"
+        })),
+        Kind::Number => {
+            match v.number_string() {
+                Some(n) => Maybe::Relaxed(Cow::Owned(n)),
+                None => Maybe::Error(FromJsonError::unexpected())
+            }
+        },
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => Maybe::Null,
+        Kind::String => {
+            let s = v.as_str().unwrap_or_default();
+            match crate::secrets::resolve_scoped(s) {
+                None => Maybe::Strict(Cow::Borrowed(s)),
+                Some(Ok(secret)) => Maybe::Strict(Cow::Owned(secret)),
+                Some(Err(e)) => Maybe::Error(e)
+            }
+        },
+        Kind::Array | Kind::Object => Maybe::Error(mismatch(v))
+    }
+}
+
+pub(crate) fn to_bool<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<bool> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    match v.kind() {
+        Kind::Null => Maybe::Null,
+        Kind::Bool => Maybe::Strict(v.as_bool().unwrap_or_default()),
+        Kind::Number => {
+            match (v.as_i64(), v.as_u64(), v.as_f64()) {
+                (Some(i), _, _) => Maybe::Relaxed(i != 0),
+                (_, Some(u), _) => Maybe::Relaxed(u > 0),
+                (_, _, Some(f)) => Maybe::Relaxed(f != 0.0),
+                _ => Maybe::Error(FromJsonError::unexpected())
+            }
+        },
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => Maybe::Null,
+        Kind::String => {
+            let s = v.as_str().unwrap_or_default();
+            match words::bool_word(s) {
+                Some(b) => Maybe::Relaxed(b),
+                None => Maybe::Relaxed( !s.is_empty() && s != "0" && s.to_lowercase() != "false" )
+            }
+        },
+        Kind::Array | Kind::Object => Maybe::Error(mismatch(v))
+    }
+}
+
+pub(crate) fn to_uint<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<u64> {
+    match to_int(value) {
+        Maybe::Strict(n) => Maybe::Strict(n as u64),
+        Maybe::Relaxed(n) => Maybe::Relaxed(n as u64),
+        Maybe::Error(e) => Maybe::Error(e),
+        Maybe::Null => Maybe::Null,
+    }
+}
+
+pub(crate) fn to_int<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<i64> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    match v.kind() {
+        Kind::Null => Maybe::Null,
+        Kind::Bool => {
+            match v.as_bool().unwrap_or_default() {
+                true  => Maybe::Relaxed(1),
+                false => Maybe::Relaxed(0)
+            }
+        },
+        Kind::Number => {
+            match (v.as_i64(), v.as_u64(), v.as_f64()) {
+                (Some(i), _, _) => Maybe::Strict(i),
+                (_, Some(u), _) => Maybe::Strict(u as i64),
+                (_, _, Some(f)) => Maybe::Relaxed(f as i64),
+                _ => Maybe::Error(FromJsonError::unexpected())
+            }
+        },
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => Maybe::Null,
+        Kind::String => {
+            let n = v.as_str().unwrap_or_default().parse::<i64>();
+            match n {
+                Ok(i) => Maybe::Relaxed(i),
+                Err(_) => Maybe::Error(FromJsonError::with_message("parseIntError"))
+            }
+        },
+        Kind::Array | Kind::Object => Maybe::Error(mismatch(v))
+    }
+}
+
+/// Exact decimal text of numbers, numeric strings are trimmed and checked
+pub(crate) fn to_decimal<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<String> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    match v.kind() {
+        Kind::Null => Maybe::Null,
+        Kind::Bool => Maybe::Relaxed(s!(if v.as_bool().unwrap_or_default() { "1" } else { "0" })),
+        Kind::Number => {
+            match v.number_string() {
+                Some(n) => Maybe::Strict(n),
+                None => Maybe::Error(FromJsonError::unexpected())
+            }
+        },
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => Maybe::Null,
+        Kind::String => {
+            let s = v.as_str().unwrap_or_default().trim();
+            match s.parse::<serde_json::Number>() {
+                Ok(_) => Maybe::Relaxed(s!(s)),
+                Err(_) => Maybe::Error(FromJsonError::with_message("parseDecimalError"))
+            }
+        },
+        Kind::Array | Kind::Object => Maybe::Error(mismatch(v))
+    }
+}
+
+pub(crate) fn to_bigint<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<i128> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    match v.kind() {
+        Kind::Number => {
+            match (v.number_string().and_then(|n| n.parse::<i128>().ok()), v.as_f64()) {
+                (Some(i), _) => Maybe::Strict(i),
+                (None, Some(f)) => Maybe::Relaxed(f as i128),
+                _ => Maybe::Error(FromJsonError::unexpected())
+            }
+        },
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => Maybe::Null,
+        Kind::String => {
+            match v.as_str().unwrap_or_default().parse::<i128>() {
+                Ok(i) => Maybe::Relaxed(i),
+                Err(_) => Maybe::Error(FromJsonError::with_message("parseIntError"))
+            }
+        },
+        _ => {
+            match to_int(Some(v)) {
+                Maybe::Strict(i) => Maybe::Strict(i as i128),
+                Maybe::Relaxed(i) => Maybe::Relaxed(i as i128),
+                Maybe::Error(e) => Maybe::Error(e),
+                Maybe::Null => Maybe::Null
+            }
+        }
+    }
+}
+
+pub(crate) fn to_float<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<f64> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    match v.kind() {
+        Kind::Null => Maybe::Null,
+        Kind::Bool => Maybe::Relaxed(if v.as_bool().unwrap_or_default() { 1.0 } else { 0.0 }),
+        Kind::Number => {
+            match v.as_f64() {
+                Some(f) => Maybe::Strict(f),
+                None => Maybe::Error(FromJsonError::unexpected())
+            }
+        },
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => Maybe::Null,
+        Kind::String => {
+            match v.as_str().unwrap_or_default().parse::<f64>() {
+                Ok(f) => Maybe::Relaxed(f),
+                Err(_) => Maybe::Error(FromJsonError::with_message("parseFloatError"))
+            }
+        },
+        Kind::Array | Kind::Object => Maybe::Error(mismatch(v))
+    }
+}
+
+/// A (lat, lon) pair from `{"lat", "lon"}` or a GeoJSON `[lon, lat]` array or
+/// Point, relaxed from `lng`, `latitude`, ... members, numbers as strings or a
+/// `"lat,lon"` string
+pub(crate) fn to_geo<J: JsonLike + ?Sized>(value: Option<&J>) -> Maybe<(f64, f64)> {
+    let v = match value {
+        Some(v) => v,
+        None => return Maybe::Null
+    };
+    let (lat, lon, canonical) = match v.kind() {
+        Kind::Null => return Maybe::Null,
+        Kind::String if words::is_null(v.as_str().unwrap_or_default()) => return Maybe::Null,
+        Kind::String => match v.as_str().unwrap_or_default().split_once(',') {
+            Some((lat, lon)) => match (lat.trim().parse::<f64>(), lon.trim().parse::<f64>()) {
+                (Ok(lat), Ok(lon)) => (Maybe::Relaxed(lat), Maybe::Relaxed(lon), false),
+                _ => return Maybe::Error(FromJsonError::with_message("parseGeoError"))
+            },
+            None => return Maybe::Error(FromJsonError::with_message("parseGeoError"))
+        },
+        Kind::Array => match v.array_len() {
+            Some(2) | Some(3) => (to_float(v.get_index(1)), to_float(v.get_index(0)), true),
+            _ => return Maybe::Error(FromJsonError::with_message("expected [lon, lat]"))
+        },
+        Kind::Object if v.get_key("coordinates").is_some() => match to_geo(v.get_key("coordinates")) {
+            Maybe::Strict(point) if v.get_key("type").and_then(|t| t.as_str()) == Some("Point") => return Maybe::Strict(point),
+            Maybe::Strict(point) => return Maybe::Relaxed(point),
+            other => return other
+        },
+        Kind::Object => {
+            let member = |names: &[&'static str]| names.iter().find_map(|n| v.get_key(n).map(|m| (*n, m)));
+            match (member(&["lat", "latitude"]), member(&["lon", "lng", "long", "longitude"])) {
+                (Some((a, lat)), Some((o, lon))) => (to_float(Some(lat)), to_float(Some(lon)), a == "lat" && o == "lon"),
+                _ => return Maybe::Error(FromJsonError::with_message("expected lat and lon"))
+            }
+        },
+        _ => return Maybe::Error(mismatch(v))
+    };
+
+    let (lat, lon, strict) = match (lat, lon) {
+        (Maybe::Strict(lat), Maybe::Strict(lon)) => (lat, lon, canonical),
+        (Maybe::Strict(lat) | Maybe::Relaxed(lat), Maybe::Strict(lon) | Maybe::Relaxed(lon)) => (lat, lon, false),
+        (Maybe::Error(e), _) | (_, Maybe::Error(e)) => return Maybe::Error(e),
+        _ => return Maybe::Error(FromJsonError::with_message("expected lat and lon"))
+    };
+    match ((-90.0..=90.0).contains(&lat), (-180.0..=180.0).contains(&lon), strict) {
+        (false, _, _) => Maybe::Error(FromJsonError::with_message(&format!("latitude out of range: {}", lat))),
+        (_, false, _) => Maybe::Error(FromJsonError::with_message(&format!("longitude out of range: {}", lon))),
+        (true, true, true) => Maybe::Strict((lat, lon)),
+        (true, true, false) => Maybe::Relaxed((lat, lon))
+    }
+}
+
+/// Name of the json type of `value`, as used in error messages
+pub(crate) fn type_name<J: JsonLike + ?Sized>(value: &J) -> &'static str {
+    value.kind().name()
+}
+
+pub(crate) fn mismatch<J: JsonLike + ?Sized>(value: &J) -> FromJsonError {
+    FromJsonError::with_message(&format!("type mismatch: {}", type_name(value)))
+}
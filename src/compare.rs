@@ -0,0 +1,145 @@
+//! Comparing json documents with the coercion rules of this crate
+
+use serde_json::{Number, Value};
+
+use crate::{coerce, patch, Maybe};
+
+/// `a` and `b` are equal when coerced like the maybe_* accessors do,
+/// so `1 == "1"` and `true == 1`. Key order of objects is ignored.
+pub fn relaxed_eq(a: &Value, b: &Value) -> bool {
+    Compare::new().eq(a, b)
+}
+
+/// JSON Pointers to the places where `a` and `b` differ under relaxed comparison,
+/// `""` is the root
+pub fn relaxed_diff(a: &Value, b: &Value) -> Vec<String> {
+    Compare::new().diff(a, b)
+}
+
+/// Configurable relaxed comparison
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Compare {
+    /// Numbers are equal if they differ by at most this amount
+    pub tolerance: f64,
+    /// Numbers are equal if they differ by at most this fraction of the larger one
+    pub relative: f64,
+}
+
+impl Compare {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Allow numbers to differ relative to their size, like `1e-9` for floats
+    /// that went through text and back
+    pub fn relative_tolerance(mut self, relative: f64) -> Self {
+        self.relative = relative;
+        self
+    }
+
+    pub fn eq(&self, a: &Value, b: &Value) -> bool {
+        self.diff(a, b).is_empty()
+    }
+
+    pub fn diff(&self, a: &Value, b: &Value) -> Vec<String> {
+        let mut found = Vec::new();
+        self.walk(&mut found, String::new(), a, b);
+        found
+    }
+
+    fn walk(&self, found: &mut Vec<String>, path: String, a: &Value, b: &Value) {
+        match (a, b) {
+            (Value::Object(x), Value::Object(y)) => {
+                for (k, v) in x.iter() {
+                    match y.get(k) {
+                        Some(w) => self.walk(found, patch::child(&path, k), v, w),
+                        None => found.push(patch::child(&path, k))
+                    }
+                }
+                for k in y.keys().filter(|k| !x.contains_key(*k)) {
+                    found.push(patch::child(&path, k));
+                }
+            },
+            (Value::Array(x), Value::Array(y)) => {
+                for (i, (v, w)) in x.iter().zip(y.iter()).enumerate() {
+                    self.walk(found, format!("{}/{}", path, i), v, w);
+                }
+                for i in x.len().min(y.len())..x.len().max(y.len()) {
+                    found.push(format!("{}/{}", path, i));
+                }
+            },
+            _ => {
+                if !self.scalar_eq(a, b) {
+                    found.push(path);
+                }
+            }
+        }
+    }
+
+    fn scalar_eq(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(x), other) | (other, Value::Bool(x)) => {
+                match coerce::to_bool(Some(other)) {
+                    Maybe::Strict(v) | Maybe::Relaxed(v) => v == *x,
+                    _ => false
+                }
+            },
+            (Value::Number(x), Value::Number(y)) => self.numbers_eq(x, y),
+            (Value::Number(n), Value::String(s)) | (Value::String(s), Value::Number(n)) => {
+                match s.trim().parse::<Number>() {
+                    Ok(m) => self.numbers_eq(n, &m),
+                    Err(_) => false
+                }
+            },
+            (Value::String(x), Value::String(y)) => x == y,
+            _ => false
+        }
+    }
+
+    fn numbers_eq(&self, x: &Number, y: &Number) -> bool {
+        let exact = self.tolerance == 0.0 && self.relative == 0.0;
+        match (x.as_i64(), y.as_i64(), x.as_u64(), y.as_u64()) {
+            (Some(i), Some(j), _, _) if exact => i == j,
+            (_, _, Some(i), Some(j)) if exact => i == j,
+            _ => {
+                match (x.as_f64(), y.as_f64()) {
+                    (Some(f), Some(g)) => (f - g).abs() <= self.tolerance.max(self.relative * f.abs().max(g.abs())),
+                    _ => false
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Compare;
+
+    #[test]
+    fn relaxed_equality() {
+        let a = json!({"active": true, "id": 1, "ratio": 0.5, "tags": ["x", 2]});
+        let b = json!({"ratio": "0.5", "tags": ["x", "2"], "active": 1, "id": "1"});
+
+        assert!(super::relaxed_eq(&a, &b));
+        assert_eq!(super::relaxed_diff(&a, &json!({"id": 2, "active": "false", "tags": ["x"]})),
+            vec!("/active", "/id", "/ratio", "/tags/1"));
+
+        assert!(!super::relaxed_eq(&json!(0.30000000000000004), &json!(0.3)));
+        assert!(Compare::new().tolerance(1e-9).eq(&json!(0.30000000000000004), &json!(0.3)));
+
+        let relative = Compare::new().relative_tolerance(1e-9);
+        assert!(relative.eq(&json!({"sum": 0.1 + 0.2, "big": 1.0000000001e12}), &json!({"sum": "0.3", "big": 1e12})));
+        assert_eq!(relative.diff(&json!([1e-12, 100.0]), &json!([2e-12, 100.001])), vec!("/0", "/1"));
+        assert!(relative.tolerance(1e-11).eq(&json!(1e-12), &json!(2e-12)));
+    }
+}
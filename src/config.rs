@@ -0,0 +1,285 @@
+//! Layered configuration from defaults, files, environment variables and overrides
+//!
+//! Sources are merged with a [`Merger`] in the order they are added, later ones
+//! win. The resolved [`Config`] remembers which source set each value:
+//!
+//! ```no_run
+//! use json_relaxed::config::ConfigStack;
+//! use json_relaxed::MaybeValue;
+//! use serde_json::json;
+//!
+//! let config = ConfigStack::new()
+//!     .defaults(json!({"server": {"port": 8080, "host": "localhost"}}))
+//!     .file("config/app.json")
+//!     .optional_file("config/local.json")
+//!     .env("APP")
+//!     .overrides(std::env::args().skip(1))
+//!     .resolve()
+//!     .unwrap();
+//!
+//! let port = config.value()["server"].maybe_uint("port").relaxed();
+//! if let Some(origin) = config.origin("/server/port") {
+//!     println!("port {} from {}", port, origin);
+//! }
+//! ```
+//!
+//! Files are read by extension, `.toml`, `.yaml` and `.yml` with the matching
+//! features, everything else as json. Json files may contain comments and
+//! trailing commas, see [`read::Options::lenient`]. Values from json files know
+//! their line. Environment variables are typed after the values before them,
+//! overrides are strings, the relaxed accessors read them as numbers or booleans.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::env::Resolver;
+use crate::merge::Merger;
+use crate::{patch, read, FromJsonError, TryFromJson};
+
+enum Source {
+    Value(Value),
+    File(PathBuf, bool),
+    Env(Resolver),
+    Overrides(Vec<String>),
+}
+
+/// Configuration sources, see the [module documentation](self)
+#[derive(Default)]
+pub struct ConfigStack {
+    sources: Vec<Source>,
+    merger: Merger,
+}
+
+impl ConfigStack {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Values given in code, reported as `defaults`
+    pub fn defaults(mut self, value: Value) -> Self {
+        self.sources.push(Source::Value(value));
+        self
+    }
+
+    /// A file that must exist
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File(path.into(), true));
+        self
+    }
+
+    /// A file that is skipped if it does not exist
+    pub fn optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File(path.into(), false));
+        self
+    }
+
+    /// Variables like `APP_SERVER__PORT` for `prefix` `APP`, see [`env`](crate::env)
+    pub fn env(self, prefix: &str) -> Self {
+        self.env_with(Resolver::new(prefix))
+    }
+
+    /// Environment variables mapped by `resolver`
+    pub fn env_with(mut self, resolver: Resolver) -> Self {
+        self.sources.push(Source::Env(resolver));
+        self
+    }
+
+    /// Assignments like `server.port=8080`, e.g. from the command line.
+    /// Arguments without `=` are ignored.
+    pub fn overrides<S: Into<String>>(mut self, assignments: impl IntoIterator<Item = S>) -> Self {
+        self.sources.push(Source::Overrides(assignments.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Merge the sources with `merger` instead of a plain deep merge
+    pub fn merger(mut self, merger: Merger) -> Self {
+        self.merger = merger;
+        self
+    }
+
+    /// Read and merge all sources
+    pub fn resolve(&self) -> Result<Config, FromJsonError> {
+        let mut config = Config { value: Value::Object(Map::new()), provenance: BTreeMap::new() };
+        let no_lines = BTreeMap::new();
+        for source in &self.sources {
+            let (name, layer, lines) = match source {
+                Source::Value(v) => (s!("defaults"), v.clone(), None),
+                Source::File(path, required) => {
+                    match read_file(path, *required)? {
+                        Some((v, lines)) => (path.display().to_string(), v, lines),
+                        None => continue
+                    }
+                },
+                Source::Env(resolver) => (s!("env"), resolver.layer(&config.value, std::env::vars()), None),
+                Source::Overrides(assignments) => (s!("overrides"), from_assignments(assignments), None)
+            };
+            config.value = self.merger.merge(&config.value, &layer);
+            config.record("", &layer, &name, lines.as_ref().unwrap_or(&no_lines));
+        }
+        Ok(config)
+    }
+}
+
+/// Lines of values by JSON Pointer, see [`read::value_lines`]
+type Lines = BTreeMap<String, usize>;
+
+/// The value of the file with the lines of its values, if known
+fn read_file(path: &Path, required: bool) -> Result<Option<(Value, Option<Lines>)>, FromJsonError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(FromJsonError::with_message(&format!("{}: {}", path.display(), e)))
+    };
+    let value = match path.extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "toml")]
+        Some("toml") => crate::toml::from_str(&text).map(|v| (v, None)),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => crate::yaml::from_str(&text).map(|v| (v, None)),
+        _ => read::from_str_with(&text, &read::Options::new().lenient(true)).map(|v| (v, Some(read::value_lines(&text))))
+    };
+    value.map(Some).map_err(|e| {
+        let error = FromJsonError::with_message(&format!("{}: {}", path.display(), e.message()));
+        match e.position() {
+            Some((line, column)) => error.with_position(line, column),
+            None => error
+        }
+    })
+}
+
+fn from_assignments(assignments: &[String]) -> Value {
+    let mut root = Value::Object(Map::new());
+    for a in assignments {
+        if let Some((path, value)) = a.split_once('=') {
+            let segments: Vec<&str> = path.trim_start_matches('-').split('.').collect();
+            if segments.iter().any(|s| s.is_empty()) {
+                continue;
+            }
+            patch::insert(&mut root, &segments, Value::String(s!(value)));
+        }
+    }
+    root
+}
+
+/// Where a value of a [`Config`] was set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// The file path, `defaults`, `env` or `overrides`
+    pub source: String,
+    /// Line in the file, for json files
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.source, line),
+            None => write!(f, "{}", self.source)
+        }
+    }
+}
+
+/// A resolved configuration with the origin of every value
+#[derive(Debug, Clone)]
+pub struct Config {
+    value: Value,
+    provenance: BTreeMap<String, Origin>,
+}
+
+impl Config {
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Name of the source that set the value at JSON Pointer `path`, the file
+    /// path, `defaults`, `env` or `overrides`
+    pub fn source(&self, path: &str) -> Option<&str> {
+        self.provenance.get(path).map(|o| o.source.as_str())
+    }
+
+    /// Source and line of the value at JSON Pointer `path`
+    pub fn origin(&self, path: &str) -> Option<&Origin> {
+        self.provenance.get(path)
+    }
+
+    /// The value at JSON Pointer `path` with its origin, objects merged from
+    /// several sources have none
+    pub fn get(&self, path: &str) -> Option<(&Value, Option<&Origin>)> {
+        self.value.pointer(path).map(|v| (v, self.provenance.get(path)))
+    }
+
+    /// Origins of all values by JSON Pointer
+    pub fn provenance(&self) -> &BTreeMap<String, Origin> {
+        &self.provenance
+    }
+
+    pub fn extract<T: TryFromJson>(&self) -> Result<T, FromJsonError> {
+        T::try_from_json(&self.value)
+    }
+
+    /// Attribute the values of `layer` to `name`, objects are merged member by member
+    fn record(&mut self, path: &str, layer: &Value, name: &str, lines: &Lines) {
+        match layer {
+            Value::Object(o) if !o.is_empty() || path.is_empty() => {
+                // a value that was replaced by an object is no longer set by its source
+                self.provenance.remove(path);
+                for (k, v) in o {
+                    self.record(&patch::child(path, k), v, name, lines);
+                }
+            },
+            _ => {
+                let nested = format!("{}/", path);
+                self.provenance.retain(|p, _| !p.starts_with(&nested));
+                self.provenance.insert(s!(path), Origin { source: s!(name), line: lines.get(path).copied() });
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::ConfigStack;
+    use crate::MaybeValue;
+
+    #[test]
+    fn layered_sources() {
+        let dir = std::env::temp_dir().join(format!("json-relaxed-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("app.json");
+        std::fs::write(&file, "// app\n{\"server\": {\"port\": 9000},\n  /* logging */\n  log: {'level': \"info\", \"file\": \"a.log\",},\n}").unwrap();
+        std::env::set_var("JSON_RELAXED_CONFIG_TEST_SERVER__HOST", "example.org");
+
+        let config = ConfigStack::new()
+            .defaults(json!({"server": {"port": 8080, "host": "localhost"}, "log": "stderr"}))
+            .file(&file)
+            .optional_file(dir.join("missing.json"))
+            .env("JSON_RELAXED_CONFIG_TEST")
+            .overrides(["--server.port=7000", "verbose"])
+            .resolve()
+            .unwrap();
+
+        assert_eq!(config.value(), &json!({"server": {"port": "7000", "host": "example.org"}, "log": {"level": "info", "file": "a.log"}}));
+        assert_eq!(config.value()["server"].maybe_uint("port").relaxed(), 7000);
+        assert_eq!(config.source("/server/port"), Some("overrides"));
+        assert_eq!(config.source("/server/host"), Some("env"));
+        assert_eq!(config.source("/log/level"), Some(file.display().to_string().as_str()));
+        assert_eq!(config.source("/log"), None);
+        assert_eq!(config.origin("/log/file").unwrap().to_string(), format!("{}:4", file.display()));
+        assert_eq!(config.origin("/server/host").unwrap().line, None);
+        let (value, origin) = config.get("/server/port").unwrap();
+        assert_eq!((value, origin.map(|o| o.source.as_str())), (&json!("7000"), Some("overrides")));
+
+        assert!(ConfigStack::new().file(dir.join("missing.json")).resolve().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+//! Constraints chained onto accessors
+//!
+//! A value that violates a constraint becomes [`Maybe::Error`] with a message
+//! saying why, `Null` and errors pass through unchanged:
+//!
+//! ```
+//! use json_relaxed::{Maybe, MaybeValue};
+//! use serde_json::json;
+//!
+//! let config = json!({"retries": "12", "name": ""});
+//! let retries = config.maybe_int("retries").min(0).max(10);
+//! assert_eq!(retries.strict_ok().unwrap_err().message(), "12 is greater than 10");
+//! assert!(matches!(config.maybe_string("name").non_empty(), Maybe::Error(_)));
+//! ```
+//!
+//! [`matches`](Maybe::matches) needs the `regex` feature.
+
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display};
+
+use crate::{FromJsonError, Maybe};
+
+/// Values with a length, characters for strings
+pub trait Length {
+    fn length(&self) -> usize;
+}
+
+impl Length for String {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl Length for &str {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl Length for Cow<'_, str> {
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl <T> Length for Vec<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl Length for Map<String, Value> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl <T> Maybe<T> {
+
+    /// Keep values for which `check` returns true, others fail with `message`
+    pub fn ensure(self, check: impl FnOnce(&T) -> bool, message: &str) -> Self {
+        self.check(|v| match check(v) {
+            true => None,
+            false => Some(s!(message))
+        })
+    }
+
+    fn check(self, violation: impl FnOnce(&T) -> Option<String>) -> Self {
+        let message = match &self {
+            Maybe::Strict(v) | Maybe::Relaxed(v) => violation(v),
+            _ => None
+        };
+        match message {
+            Some(m) => Maybe::Error(FromJsonError::with_message(&m)),
+            None => self
+        }
+    }
+
+    /// Values of at least `min`
+    pub fn min(self, min: impl Into<T>) -> Self
+        where T: PartialOrd + Display
+    {
+        let min = min.into();
+        self.check(|v| match *v < min {
+            true => Some(format!("{} is less than {}", v, min)),
+            false => None
+        })
+    }
+
+    /// Values of at most `max`
+    pub fn max(self, max: impl Into<T>) -> Self
+        where T: PartialOrd + Display
+    {
+        let max = max.into();
+        self.check(|v| match *v > max {
+            true => Some(format!("{} is greater than {}", v, max)),
+            false => None
+        })
+    }
+
+    /// One of `allowed`
+    pub fn one_of(self, allowed: &[T]) -> Self
+        where T: PartialEq + Debug
+    {
+        self.check(|v| match allowed.contains(v) {
+            true => None,
+            false => Some(format!("{:?} is not one of {:?}", v, allowed))
+        })
+    }
+
+    /// Strings, arrays or objects that are not empty
+    pub fn non_empty(self) -> Self
+        where T: Length
+    {
+        self.check(|v| match v.length() {
+            0 => Some(s!("is empty")),
+            _ => None
+        })
+    }
+
+    pub fn min_length(self, min: usize) -> Self
+        where T: Length
+    {
+        self.check(|v| match v.length() {
+            l if l < min => Some(format!("length {} is less than {}", l, min)),
+            _ => None
+        })
+    }
+
+    pub fn max_length(self, max: usize) -> Self
+        where T: Length
+    {
+        self.check(|v| match v.length() {
+            l if l > max => Some(format!("length {} is greater than {}", l, max)),
+            _ => None
+        })
+    }
+
+    /// Strings matching the regular expression `pattern`, an invalid pattern is an error
+    #[cfg(feature = "regex")]
+    pub fn matches(self, pattern: &str) -> Self
+        where T: AsRef<str>
+    {
+        match regex::Regex::new(pattern) {
+            Ok(re) => self.matches_regex(&re),
+            Err(e) => match self {
+                Maybe::Strict(_) | Maybe::Relaxed(_) => Maybe::Error(FromJsonError::with_message(&e.to_string())),
+                m => m
+            }
+        }
+    }
+
+    /// Like `matches` with a compiled expression, to be reused for many values
+    #[cfg(feature = "regex")]
+    pub fn matches_regex(self, re: &regex::Regex) -> Self
+        where T: AsRef<str>
+    {
+        self.check(|v| match re.is_match(v.as_ref()) {
+            true => None,
+            false => Some(format!("{:?} does not match {}", v.as_ref(), re))
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{Maybe, MaybeValue};
+
+    #[test]
+    fn chained_constraints() {
+        let value = json!({"retries": 3, "offset": "-2", "name": "api", "tags": [], "mode": "test"});
+
+        assert!(matches!(value.maybe_int("retries").min(0).max(10), Maybe::Strict(3)));
+        assert_eq!(value.maybe_int("offset").min(0).strict_ok().unwrap_err().message(), "-2 is less than 0");
+        assert!(matches!(value.maybe_int("missing").min(1), Maybe::Null));
+        assert!(matches!(value.maybe_string("name").non_empty().max_length(3), Maybe::Strict(_)));
+        assert!(matches!(value.maybe_array::<serde_json::Value, _>("tags").non_empty(), Maybe::Error(_)));
+        assert!(matches!(value.maybe_string("mode").one_of(&[s!("dev"), s!("prod")]), Maybe::Error(_)));
+        assert!(matches!(value.maybe_int("retries").ensure(|r| r % 2 == 0, "must be even"), Maybe::Error(_)));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn patterns() {
+        let value = json!({"name": "api", "id": 42});
+        assert!(matches!(value.maybe_string("name").matches(r"^[a-z]+$"), Maybe::Strict(_)));
+        assert!(matches!(value.maybe_string("id").matches(r"^[a-z]+$"), Maybe::Error(_)));
+        assert!(matches!(value.maybe_str("name").matches("("), Maybe::Error(_)));
+    }
+}
@@ -0,0 +1,151 @@
+//! Reading CSV records into json objects keyed by the header row
+//!
+//! Cells are strings unless [`Options::infer`] is set, then integers, floats and
+//! `true` / `false` become json numbers and booleans and empty cells null.
+//! [`maybe_from_reader`] reports documents with inferred values as `Relaxed`.
+
+use serde_json::{Map, Number, Value};
+use std::io::Read;
+
+use crate::{FromJsonError, Maybe};
+
+impl From<csv::Error> for FromJsonError {
+    fn from(e: csv::Error) -> Self {
+        match e.position() {
+            Some(p) => FromJsonError::with_message(&e.to_string()).with_position(p.line() as usize, 1),
+            None => FromJsonError::with_message(&e.to_string())
+        }
+    }
+}
+
+/// Options for reading CSV input
+#[derive(Debug, Clone)]
+pub struct Options {
+    delimiter: u8,
+    infer: bool,
+    trim: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options { delimiter: b',', infer: false, trim: false }
+    }
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Read numbers, booleans and empty cells as typed values
+    pub fn infer(mut self, enable: bool) -> Self {
+        self.infer = enable;
+        self
+    }
+
+    /// Trim whitespace around headers and cells
+    pub fn trim(mut self, enable: bool) -> Self {
+        self.trim = enable;
+        self
+    }
+
+    fn cell(&self, text: &str, inferred: &mut bool) -> Value {
+        if !self.infer {
+            return Value::String(s!(text));
+        }
+        let typed = match text {
+            "" => Some(Value::Null),
+            t if t.eq_ignore_ascii_case("true") => Some(Value::Bool(true)),
+            t if t.eq_ignore_ascii_case("false") => Some(Value::Bool(false)),
+            t => {
+                match (t.parse::<i64>(), t.parse::<u64>(), t.parse::<f64>()) {
+                    (Ok(i), _, _) => Some(Value::Number(i.into())),
+                    (_, Ok(u), _) => Some(Value::Number(u.into())),
+                    (_, _, Ok(f)) => Number::from_f64(f).map(Value::Number),
+                    _ => None
+                }
+            }
+        };
+        match typed {
+            Some(v) => {
+                *inferred = true;
+                v
+            },
+            None => Value::String(s!(text))
+        }
+    }
+}
+
+/// Read all records of `reader`
+pub fn from_reader<R: Read>(reader: R, options: &Options) -> Result<Vec<Value>, FromJsonError> {
+    match maybe_from_reader(reader, options) {
+        Maybe::Strict(rows) | Maybe::Relaxed(rows) => Ok(rows),
+        Maybe::Error(e) => Err(e),
+        Maybe::Null => Err(FromJsonError::unexpected())
+    }
+}
+
+pub fn from_str(text: &str, options: &Options) -> Result<Vec<Value>, FromJsonError> {
+    from_reader(text.as_bytes(), options)
+}
+
+/// Read all records of `reader`, `Relaxed` if any cell was read as a typed value
+pub fn maybe_from_reader<R: Read>(reader: R, options: &Options) -> Maybe<Vec<Value>> {
+    let mut csv = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .trim(if options.trim { csv::Trim::All } else { csv::Trim::None })
+        .from_reader(reader);
+    let headers = match csv.headers() {
+        Ok(h) => h.clone(),
+        Err(e) => return Maybe::Error(e.into())
+    };
+
+    let mut inferred = false;
+    let mut rows = Vec::new();
+    for record in csv.records() {
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => return Maybe::Error(e.into())
+        };
+        let row: Map<String, Value> = headers.iter().zip(record.iter())
+            .map(|(h, cell)| (s!(h), options.cell(cell, &mut inferred)))
+            .collect();
+        rows.push(Value::Object(row));
+    }
+    match inferred {
+        true => Maybe::Relaxed(rows),
+        false => Maybe::Strict(rows)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Options;
+    use crate::Maybe;
+
+    #[test]
+    fn csv_records() {
+        let text = "id,name,active,score\n1,Ada, true,\n2,\"Lovelace, A\",false,1.5\n";
+        assert_eq!(super::from_str(text, &Options::new()).unwrap()[0], json!({"id": "1", "name": "Ada", "active": " true", "score": ""}));
+
+        match super::maybe_from_reader(text.as_bytes(), &Options::new().infer(true).trim(true)) {
+            Maybe::Relaxed(rows) => assert_eq!(rows, vec!(
+                json!({"id": 1, "name": "Ada", "active": true, "score": null}),
+                json!({"id": 2, "name": "Lovelace, A", "active": false, "score": 1.5})
+            )),
+            _ => panic!("expected inferred rows")
+        }
+
+        let error = super::from_str("a;b\n1;2\n3\n", &Options::new().delimiter(b';')).unwrap_err();
+        assert_eq!(error.position(), Some((3, 1)));
+    }
+}
@@ -0,0 +1,490 @@
+//! A serde Deserializer applying the coercions of this crate, the counterpart of [`ser`](crate::ser)
+//!
+//! Existing `#[derive(Deserialize)]` types can be read leniently:
+//! - numbers from numeric strings and from booleans, booleans from numbers and strings
+//! - floats from null as NaN, 128 bit integers from strings
+//! - strings from numbers and booleans
+//! - sequences from a single value, and from null as an empty sequence
+//! - structs from null as if all fields were missing
+//! - enum variants by name ignoring case
+//! - map keys converted to numbers or booleans as the key type requires
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Visitor};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::{coerce, read, FromJsonError, Maybe};
+
+impl de::Error for FromJsonError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        FromJsonError::with_message(&msg.to_string())
+    }
+}
+
+/// Deserialize `T` from `value` with relaxed coercions, borrowing strings where `T` allows it
+pub fn from_value<'a, T: Deserialize<'a>>(value: &'a Value) -> Result<T, FromJsonError> {
+    T::deserialize(Deserializer::new(value))
+}
+
+/// Parse `text` in the [lenient](read::Options::lenient) syntax and deserialize
+/// `T` from it with relaxed coercions
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, FromJsonError> {
+    from_slice(text.as_bytes())
+}
+
+/// Parse `bytes` in the [lenient](read::Options::lenient) syntax and deserialize
+/// `T` from it with relaxed coercions
+pub fn from_slice<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, FromJsonError> {
+    from_value(&read::from_slice_with(bytes, &read::Options::new().lenient(true))?)
+}
+
+/// Deserializer over a borrowed Value, see the [module documentation](self)
+pub struct Deserializer<'a> {
+    value: &'a Value,
+}
+
+impl <'a> Deserializer<'a> {
+    pub fn new(value: &'a Value) -> Self {
+        Deserializer { value }
+    }
+}
+
+fn accept<T>(maybe: Maybe<T>, expected: &str) -> Result<T, FromJsonError> {
+    match maybe {
+        Maybe::Strict(v) | Maybe::Relaxed(v) => Ok(v),
+        Maybe::Error(e) => Err(e),
+        Maybe::Null => Err(FromJsonError::with_message(&format!("invalid type: null, expected {}", expected)))
+    }
+}
+
+macro_rules! deserialize_int {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+                visitor.$visit(accept(coerce::to_int(Some(self.value)), "integer")?)
+            }
+        )*
+    };
+}
+
+/// Text of a number or string, exact for wide numbers with arbitrary_precision
+fn digits(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None
+    }
+}
+
+impl <'a> de::Deserializer<'a> for Deserializer<'a> {
+    type Error = FromJsonError;
+
+    fn deserialize_any<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Number(n) => {
+                match (n.as_u64(), n.as_i64(), n.as_f64()) {
+                    (Some(u), _, _) => visitor.visit_u64(u),
+                    (_, Some(i), _) => visitor.visit_i64(i),
+                    (_, _, Some(f)) => visitor.visit_f64(f),
+                    _ => Err(FromJsonError::unexpected())
+                }
+            },
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Array(a) => visitor.visit_seq(SeqAccess { iter: a.iter() }),
+            Value::Object(o) => visitor.visit_map(MapAccess::new(o)),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_bool(accept(coerce::to_bool(Some(self.value)), "bool")?)
+    }
+
+    deserialize_int! {
+        deserialize_i8 => visit_i64,
+        deserialize_i16 => visit_i64,
+        deserialize_i32 => visit_i64,
+        deserialize_i64 => visit_i64,
+    }
+
+    fn deserialize_u8<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u16<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u32<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u64<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        // large unsigned numbers do not fit the i64 based coercion
+        match self.value.as_u64() {
+            Some(u) => visitor.visit_u64(u),
+            None => {
+                match self.value {
+                    Value::String(s) if s.parse::<u64>().is_ok() => visitor.visit_u64(s.parse().expect("checked above")),
+                    _ => visitor.visit_i64(accept(coerce::to_int(Some(self.value)), "unsigned integer")?)
+                }
+            }
+        }
+    }
+
+    fn deserialize_i128<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match digits(self.value).and_then(|d| d.parse::<i128>().ok()) {
+            Some(i) => visitor.visit_i128(i),
+            None => self.deserialize_i64(visitor)
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match digits(self.value).and_then(|d| d.parse::<u128>().ok()) {
+            Some(u) => visitor.visit_u128(u),
+            None => self.deserialize_u64(visitor)
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        if self.value.is_null() {
+            return visitor.visit_f64(f64::NAN);
+        }
+        visitor.visit_f64(accept(coerce::to_float(Some(self.value)), "float")?)
+    }
+
+    fn deserialize_char<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            _ => visitor.visit_string(accept(coerce::to_string(Some(self.value)), "string")?)
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::String(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            _ => self.deserialize_seq(visitor)
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            v => Err(FromJsonError::with_message(&format!("invalid type: {}, expected null", coerce::type_name(v))))
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'a>>(self, _name: &'static str, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'a>>(self, _name: &'static str, visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::Array(a) => visitor.visit_seq(SeqAccess { iter: a.iter() }),
+            Value::Null => visitor.visit_seq(SeqAccess { iter: [].iter() }),
+            single => visitor.visit_seq(SeqAccess { iter: std::slice::from_ref(single).iter() })
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'a>>(self, _len: usize, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'a>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match self.value {
+            Value::Object(o) => visitor.visit_map(MapAccess::new(o)),
+            Value::Null => visitor.visit_map(MapAccess::empty()),
+            v => Err(FromJsonError::with_message(&format!("invalid type: {}, expected object", coerce::type_name(v))))
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'a>>(self, _name: &'static str, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'a>>(self, _name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, FromJsonError> {
+        let (variant, content) = match self.value {
+            Value::String(s) => (s.as_str(), None),
+            Value::Object(o) if o.len() == 1 => {
+                let (k, v) = o.iter().next().expect("checked above");
+                (k.as_str(), Some(v))
+            },
+            v => return Err(FromJsonError::with_message(&format!("invalid type: {}, expected enum", coerce::type_name(v))))
+        };
+        visitor.visit_enum(EnumAccess::new(variant, content, variants))
+    }
+
+    fn deserialize_identifier<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_unit()
+    }
+}
+
+struct SeqAccess<'a> {
+    iter: std::slice::Iter<'a, Value>,
+}
+
+impl <'a> de::SeqAccess<'a> for SeqAccess<'a> {
+    type Error = FromJsonError;
+
+    fn next_element_seed<T: DeserializeSeed<'a>>(&mut self, seed: T) -> Result<Option<T::Value>, FromJsonError> {
+        match self.iter.next() {
+            Some(v) => seed.deserialize(Deserializer::new(v)).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapAccess<'a> {
+    iter: Option<serde_json::map::Iter<'a>>,
+    value: Option<&'a Value>,
+}
+
+impl <'a> MapAccess<'a> {
+    fn new(map: &'a Map<String, Value>) -> Self {
+        MapAccess { iter: Some(map.iter()), value: None }
+    }
+
+    fn empty() -> Self {
+        MapAccess { iter: None, value: None }
+    }
+}
+
+impl <'a> de::MapAccess<'a> for MapAccess<'a> {
+    type Error = FromJsonError;
+
+    fn next_key_seed<K: DeserializeSeed<'a>>(&mut self, seed: K) -> Result<Option<K::Value>, FromJsonError> {
+        match self.iter.as_mut().and_then(|i| i.next()) {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(KeyDeserializer { key: k }).map(Some)
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'a>>(&mut self, seed: V) -> Result<V::Value, FromJsonError> {
+        match self.value.take() {
+            Some(v) => seed.deserialize(Deserializer::new(v)),
+            None => Err(FromJsonError::with_message("value requested before key"))
+        }
+    }
+}
+
+/// Object keys are strings, but map keys may be numbers or booleans
+struct KeyDeserializer<'a> {
+    key: &'a str,
+}
+
+macro_rules! deserialize_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+                match self.key.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => visitor.visit_borrowed_str(self.key)
+                }
+            }
+        )*
+    };
+}
+
+impl <'a> de::Deserializer<'a> for KeyDeserializer<'a> {
+    type Error = FromJsonError;
+
+    fn deserialize_any<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_borrowed_str(self.key)
+    }
+
+    fn deserialize_bool<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        match coerce::to_bool(Some(&Value::String(s!(self.key)))) {
+            Maybe::Strict(b) | Maybe::Relaxed(b) => visitor.visit_bool(b),
+            _ => visitor.visit_borrowed_str(self.key)
+        }
+    }
+
+    deserialize_key! {
+        deserialize_i8 => visit_i64,
+        deserialize_i16 => visit_i64,
+        deserialize_i32 => visit_i64,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u64,
+        deserialize_u16 => visit_u64,
+        deserialize_u32 => visit_u64,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f64,
+        deserialize_f64 => visit_f64,
+    }
+
+    fn deserialize_option<V: Visitor<'a>>(self, visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'a>>(self, _name: &'static str, visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'a>>(self, _name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, FromJsonError> {
+        visitor.visit_enum(EnumAccess::new(self.key, None, variants))
+    }
+
+    serde::forward_to_deserialize_any! {
+        <W: Visitor<'a>>
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumAccess<'a> {
+    variant: &'a str,
+    content: Option<&'a Value>,
+}
+
+impl <'a> EnumAccess<'a> {
+    /// Variant names match exactly or else ignoring case
+    fn new(variant: &'a str, content: Option<&'a Value>, variants: &'static [&'static str]) -> Self {
+        let variant = variants.iter()
+            .find(|name| **name == variant)
+            .or_else(|| variants.iter().find(|name| name.eq_ignore_ascii_case(variant)))
+            .copied()
+            .unwrap_or(variant);
+        EnumAccess { variant, content }
+    }
+}
+
+impl <'a> de::EnumAccess<'a> for EnumAccess<'a> {
+    type Error = FromJsonError;
+    type Variant = VariantAccess<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'a>>(self, seed: V) -> Result<(V::Value, VariantAccess<'a>), FromJsonError> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<FromJsonError>::new(self.variant))?;
+        Ok((variant, VariantAccess { content: self.content }))
+    }
+}
+
+struct VariantAccess<'a> {
+    content: Option<&'a Value>,
+}
+
+impl <'a> de::VariantAccess<'a> for VariantAccess<'a> {
+    type Error = FromJsonError;
+
+    fn unit_variant(self) -> Result<(), FromJsonError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'a>>(self, seed: T) -> Result<T::Value, FromJsonError> {
+        seed.deserialize(Deserializer::new(self.content.unwrap_or(&Value::Null)))
+    }
+
+    fn tuple_variant<V: Visitor<'a>>(self, _len: usize, visitor: V) -> Result<V::Value, FromJsonError> {
+        de::Deserializer::deserialize_seq(Deserializer::new(self.content.unwrap_or(&Value::Null)), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'a>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, FromJsonError> {
+        de::Deserializer::deserialize_map(Deserializer::new(self.content.unwrap_or(&Value::Null)), visitor)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config<'a> {
+        port: u16,
+        ratio: f64,
+        debug: bool,
+        name: &'a str,
+        id: String,
+        hosts: Vec<String>,
+        tags: Vec<String>,
+        level: Level,
+        limits: HashMap<u32, u8>,
+        timeout: Option<u32>,
+    }
+
+    #[test]
+    fn relaxed_derive() {
+        let doc = json!({
+            "port": "8080",
+            "ratio": "0.5",
+            "debug": 1,
+            "name": "main",
+            "id": 42,
+            "hosts": "example.com",
+            "tags": null,
+            "level": "INFO",
+            "limits": {"1": "3"},
+        });
+        let config: Config = super::from_value(&doc).unwrap();
+
+        assert_eq!(config, Config {
+            port: 8080,
+            ratio: 0.5,
+            debug: true,
+            name: "main",
+            id: s!("42"),
+            hosts: vec!(s!("example.com")),
+            tags: vec!(),
+            level: Level::Info,
+            limits: vec!((1, 3)).into_iter().collect(),
+            timeout: None,
+        });
+
+        assert_eq!(super::from_str::<Vec<Level>>("[\"debug\", \"Debug\"]").unwrap(), vec!(Level::Debug, Level::Debug));
+        assert_eq!(super::from_str::<HashMap<String, u8>>("{\n  a: '1', // one\n  \"b\": 2,\n}").unwrap(), vec!((s!("a"), 1), (s!("b"), 2)).into_iter().collect());
+        assert!(super::from_value::<u8>(&json!(300)).is_err());
+        assert!(super::from_value::<u16>(&json!("x")).is_err());
+    }
+}
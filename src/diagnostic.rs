@@ -0,0 +1,108 @@
+//! Terminal friendly rendering of errors with an excerpt of the source text
+//!
+//! ```text
+//! error: expected `:`
+//!  --> config.json:2:12
+//!   |
+//! 1 | {
+//! 2 |     "port" 8080
+//!   |            ^
+//! ```
+
+use std::fmt;
+
+use crate::FromJsonError;
+
+/// Render `error` against the `source` text it was produced from, see [`Diagnostic`]
+pub fn diagnostic<'a>(error: &'a FromJsonError, source: &'a str) -> Diagnostic<'a> {
+    Diagnostic {
+        error,
+        source,
+        origin: None,
+        context: 1,
+    }
+}
+
+/// An error together with its source text, formatted with a caret-annotated excerpt
+/// when displayed. Errors without a position are displayed without an excerpt.
+pub struct Diagnostic<'a> {
+    error: &'a FromJsonError,
+    source: &'a str,
+    origin: Option<&'a str>,
+    context: usize,
+}
+
+impl <'a> Diagnostic<'a> {
+
+    /// Name of the input (usually a file name) shown with the position
+    pub fn origin(mut self, origin: &'a str) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Number of lines shown before the offending line, default 1
+    pub fn context(mut self, lines: usize) -> Self {
+        self.context = lines;
+        self
+    }
+}
+
+impl <'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "error: {}", self.error.message())?;
+
+        let (line, column) = match self.error.position() {
+            Some(p) => p,
+            None => {
+                if let Some(origin) = self.origin {
+                    writeln!(f, " --> {}", origin)?;
+                }
+                return Ok(());
+            }
+        };
+
+        let gutter = line.to_string().len();
+        match self.origin {
+            Some(origin) => writeln!(f, "{:w$}--> {}:{}:{}", "", origin, line, column, w = gutter)?,
+            None => writeln!(f, "{:w$}--> line {}, column {}", "", line, column, w = gutter)?,
+        }
+        writeln!(f, "{:w$} |", "", w = gutter)?;
+
+        let first = line.saturating_sub(self.context).max(1);
+        let mut offending = "";
+        for (n, text) in self.source.lines().enumerate().map(|(i, t)| (i + 1, t)).skip(first - 1).take(line + 1 - first) {
+            writeln!(f, "{:>w$} | {}", n, text, w = gutter)?;
+            offending = text;
+        }
+
+        // columns count bytes, keep tabs so the caret lines up
+        let upto = column.saturating_sub(1).min(offending.len());
+        let padding: String = offending.char_indices()
+            .take_while(|(i, _)| *i < upto)
+            .map(|(_, c)| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{:w$} | {}^", "", padding, w = gutter)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::read;
+
+    #[test]
+    fn render_parse_error() {
+        let source = "{\n    \"port\" 8080\n}";
+        let error = read::from_str(source).unwrap_err();
+        let rendered = super::diagnostic(&error, source).origin("config.json").to_string();
+
+        assert_eq!(rendered, concat!(
+            "error: expected `:`\n",
+            " --> config.json:2:12\n",
+            "  |\n",
+            "1 | {\n",
+            "2 |     \"port\" 8080\n",
+            "  |            ^\n",
+        ));
+    }
+}
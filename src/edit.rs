@@ -0,0 +1,569 @@
+//! Editing documents without losing their formatting
+//!
+//! A [`Document`] keeps the text it was parsed from. Values are updated,
+//! added and removed by JSON Pointer and only the text of the edited values
+//! changes, so comments, whitespace and key order of the rest are written back
+//! unchanged. Besides json the text may contain `//` and `/* */` comments,
+//! trailing commas, unquoted keys and single quoted strings, as in hand-written
+//! config files and the output of [`write::Json5`](crate::write::Json5):
+//!
+//! ```
+//! use json_relaxed::edit::Document;
+//! use serde_json::json;
+//!
+//! let text = "{\n  // where to listen\n  \"port\": 8080, // default\n  \"debug\": false,\n}\n";
+//! let mut doc = Document::parse(text).unwrap();
+//! doc.set("/port", 9090).unwrap();
+//! doc.set("/tls/enabled", true).unwrap();
+//! doc.remove("/debug").unwrap();
+//! assert_eq!(doc.to_string(), "{\n  // where to listen\n  \"port\": 9090, // default\n  \"tls\": {\n    \"enabled\": true\n  },\n}\n");
+//! assert_eq!(doc.value(), &json!({"port": 9090, "tls": {"enabled": true}}));
+//! ```
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::limits::Limits;
+use crate::{patch, pattern, FromJsonError};
+
+/// Where a value is found in the text
+#[derive(Debug, Clone)]
+struct Node {
+    /// Start of the member key, or of the value for elements
+    entry: usize,
+    /// End of the member key, `entry` for elements
+    key_end: usize,
+    start: usize,
+    end: usize,
+    /// Position of the comma following the value in its container
+    comma: Option<usize>,
+    /// Pointers of the members or elements in text order
+    children: Vec<String>,
+}
+
+/// A parsed document that is edited in place, see the [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    value: Value,
+    nodes: BTreeMap<String, Node>,
+}
+
+impl Document {
+
+    /// Parse `text`, which may contain comments and trailing commas
+    pub fn parse(text: &str) -> Result<Document, FromJsonError> {
+        let mut parser = Parser { text: text.as_bytes(), pos: 0, depth: 0, nodes: BTreeMap::new() };
+        let value = parser.value(String::new(), None)?;
+        parser.skip()?;
+        if parser.pos < text.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        let nodes = parser.nodes;
+        Ok(Document { text: s!(text), value, nodes })
+    }
+
+    /// The document as json
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn get(&self, pointer: &str) -> Option<&Value> {
+        self.value.pointer(pointer)
+    }
+
+    /// The text with all edits applied
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The comments on the lines before each member or element by pointer,
+    /// without the comment markers. A comment after a value on the same line
+    /// belongs to that line and is not included.
+    pub fn comments(&self) -> BTreeMap<String, String> {
+        let mut comments = BTreeMap::new();
+        for (pointer, node) in &self.nodes {
+            let from = match pointer.as_str() {
+                "" => 0,
+                p => {
+                    let container = &self.nodes[&parent_of(p)];
+                    let boundary = match container.children.iter().position(|c| c == p) {
+                        Some(0) | None => container.start + 1,
+                        Some(i) => {
+                            let previous = &self.nodes[&container.children[i - 1]];
+                            previous.comma.map_or(previous.end, |c| c + 1)
+                        }
+                    };
+                    self.text[boundary..node.entry].find('\n').map_or(boundary, |n| boundary + n + 1)
+                }
+            };
+            if let Some(comment) = comment_text(&self.text[from..node.entry]) {
+                comments.insert(pointer.clone(), comment);
+            }
+        }
+        comments
+    }
+
+    /// Replace the value at `pointer`. A missing member is added to its object,
+    /// along with missing parent objects, and an array is appended to with the
+    /// index of its length or `-`.
+    pub fn set(&mut self, pointer: &str, value: impl Into<Value>) -> Result<(), FromJsonError> {
+        let value = value.into();
+        if let Some(node) = self.nodes.get(pointer) {
+            let rendered = self.render(&value, &indent_of(&self.text, node.start));
+            let (start, end) = (node.start, node.end);
+            return self.replace(vec!((start, end, rendered)));
+        }
+        if !pointer.starts_with('/') {
+            return Err(FromJsonError::with_message(&format!("`{}` is no JSON Pointer", pointer)));
+        }
+
+        // the nearest existing parent gets the missing objects of the path below it
+        let tokens = pattern::tokens(pointer);
+        let mut depth = tokens.len() - 1;
+        let mut parent = parent_of(pointer);
+        while !self.nodes.contains_key(&parent) {
+            depth -= 1;
+            parent = parent_of(&parent);
+        }
+        let value = tokens[depth + 1..].iter().rev().fold(value, |v, key| {
+            let mut object = Map::new();
+            object.insert(key.clone(), v);
+            Value::Object(object)
+        });
+        let key = &tokens[depth];
+
+        match self.value.pointer(&parent) {
+            Some(Value::Object(_)) => self.insert(&parent, Some(key), &value),
+            Some(Value::Array(a)) if depth + 1 == tokens.len() && (key == "-" || *key == a.len().to_string()) => self.insert(&parent, None, &value),
+            Some(Value::Array(a)) => Err(FromJsonError::with_message(&format!("index `{}` is out of bounds for {} elements at `{}`", key, a.len(), parent))),
+            _ => Err(FromJsonError::with_message(&format!("no object or array at `{}`", parent)))
+        }
+    }
+
+    /// Remove the member or element at `pointer` with the rest of its line
+    /// and return it. Comments on the lines above are kept.
+    pub fn remove(&mut self, pointer: &str) -> Result<Value, FromJsonError> {
+        let removed = match (pointer, self.value.pointer(pointer)) {
+            ("", _) => return Err(FromJsonError::with_message("the root value can not be removed")),
+            (_, Some(v)) => v.clone(),
+            (_, None) => return Err(FromJsonError::with_message(&format!("no value at `{}`", pointer)))
+        };
+        let node = &self.nodes[pointer];
+        let container = &self.nodes[&parent_of(pointer)];
+        let index = container.children.iter().position(|c| c == pointer).unwrap_or(0);
+        let previous = index.checked_sub(1).map(|i| &self.nodes[&container.children[i]]);
+        let text = self.text.as_bytes();
+
+        let own_line = line_start(text, node.entry);
+        let after = node.comma.map_or(node.end, |c| c + 1);
+        let blank_end = skip_blanks(text, after);
+        let mut edits = Vec::new();
+        match own_line {
+            Some(start) => {
+                let end = match text.get(blank_end..) {
+                    Some([b'/', b'/', ..]) => line_end(text, blank_end) + 1,
+                    Some([b'\n', ..]) => blank_end + 1,
+                    _ => blank_end
+                };
+                edits.push((start, end.min(text.len()), s!("")));
+                // the previous member is now the last one
+                if let (None, Some(Node { comma: Some(comma), .. })) = (node.comma, previous) {
+                    edits.push((*comma, comma + 1, s!("")));
+                }
+            },
+            None => match (node.comma, previous) {
+                (None, Some(Node { comma: Some(comma), .. })) => edits.push((*comma, node.end, s!(""))),
+                (None, _) => edits.push((node.entry, node.end, s!(""))),
+                (Some(_), _) => edits.push((node.entry, blank_end, s!("")))
+            }
+        }
+
+        // a container left with whitespace only is closed up
+        let (inner_start, inner_end) = (container.start + 1, container.end - 1);
+        let left = self.text[inner_start..inner_end].char_indices()
+            .filter(|(i, _)| !edits.iter().any(|(s, e, _)| inner_start + i >= *s && inner_start + i < *e))
+            .all(|(_, c)| c.is_whitespace());
+        if left {
+            edits = vec!((inner_start, inner_end, s!("")));
+        }
+        self.replace(edits)?;
+        Ok(removed)
+    }
+
+    /// Add a member or element at the end of the container at `parent`
+    fn insert(&mut self, parent: &str, key: Option<&str>, value: &Value) -> Result<(), FromJsonError> {
+        let text = self.text.as_bytes();
+        let container = &self.nodes[parent];
+        let multiline = self.text.contains('\n');
+
+        let last = container.children.last().map(|c| &self.nodes[c]);
+        let colon = container.children.first()
+            .map(|c| &self.nodes[c])
+            .map_or(": ", |first| &self.text[first.key_end..first.start]);
+        let entry = |indent: &str| match key {
+            Some(key) => format!("{}{}{}", serde_json::to_string(key).unwrap_or_default(), colon, self.render(value, indent)),
+            None => self.render(value, indent)
+        };
+
+        let edits = match last {
+            None => {
+                let inner = container.start + 1..container.end - 1;
+                let replaced = match self.text[inner.clone()].trim().is_empty() {
+                    true => inner.end,
+                    false => inner.start
+                };
+                match multiline {
+                    true => {
+                        let outer = indent_of(&self.text, container.start);
+                        let indent = format!("{}{}", outer, indent_unit(&self.text));
+                        vec!((inner.start, replaced, format!("\n{}{}\n{}", indent, entry(&indent), outer)))
+                    },
+                    false => vec!((inner.start, replaced, entry("")))
+                }
+            },
+            Some(last) => {
+                let trailing = if last.comma.is_some() { "," } else { "" };
+                let mut edits = Vec::new();
+                if last.comma.is_none() {
+                    edits.push((last.end, last.end, s!(",")));
+                }
+                let after = last.comma.map_or(last.end, |c| c + 1);
+                match line_start(text, last.entry) {
+                    Some(start) => {
+                        let indent = &self.text[start..last.entry];
+                        let blank_end = skip_blanks(text, after);
+                        let at = match text.get(blank_end..) {
+                            Some([b'/', b'/', ..]) => line_end(text, blank_end),
+                            Some([b'\n', ..]) => blank_end,
+                            _ => after
+                        };
+                        edits.push((at, at, format!("\n{}{}{}", indent, entry(indent), trailing)));
+                    },
+                    None => {
+                        let separator = match container.children.len() {
+                            1 => " ",
+                            n => {
+                                let previous = &self.nodes[&container.children[n - 2]];
+                                &self.text[previous.comma.map_or(previous.end, |c| c + 1)..last.entry]
+                            }
+                        };
+                        let separator = if separator.trim().is_empty() { separator } else { " " };
+                        edits.push((after, after, format!("{}{}{}", separator, entry(""), trailing)));
+                    }
+                }
+                edits
+            }
+        };
+        self.replace(edits)
+    }
+
+    /// `value` as text for a position indented by `indent`. Multi-line
+    /// documents get pretty containers using the document's indentation.
+    fn render(&self, value: &Value, indent: &str) -> String {
+        match (self.text.contains('\n'), value) {
+            (true, Value::Object(o)) if !o.is_empty() => self.pretty(value, indent),
+            (true, Value::Array(a)) if !a.is_empty() => self.pretty(value, indent),
+            _ => serde_json::to_string(value).unwrap_or_default()
+        }
+    }
+
+    fn pretty(&self, value: &Value, indent: &str) -> String {
+        let unit = indent_unit(&self.text);
+        let mut out = Vec::new();
+        let mut ser = serde_json::Serializer::with_formatter(&mut out, serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes()));
+        let _ = value.serialize(&mut ser);
+        String::from_utf8_lossy(&out).replace('\n', &format!("\n{}", indent))
+    }
+
+    /// Apply non-overlapping `(start, end, replacement)` edits and parse the result
+    fn replace(&mut self, mut edits: Vec<(usize, usize, String)>) -> Result<(), FromJsonError> {
+        edits.sort_by_key(|(start, _, _)| *start);
+        let mut text = self.text.clone();
+        for (start, end, replacement) in edits.into_iter().rev() {
+            text.replace_range(start..end, &replacement);
+        }
+        *self = Document::parse(&text)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// The text of the comments in `text`, which holds nothing else, one line each
+fn comment_text(mut text: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    loop {
+        text = text.trim_start();
+        if let Some(rest) = text.strip_prefix("//") {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            lines.push(rest[..end].trim());
+            text = &rest[end..];
+        } else if let Some(rest) = text.strip_prefix("/*") {
+            let end = rest.find("*/").unwrap_or(rest.len());
+            lines.extend(rest[..end].lines().map(|l| l.trim().trim_start_matches('*').trim()).filter(|l| !l.is_empty()));
+            text = &rest[(end + 2).min(rest.len())..];
+        } else {
+            break;
+        }
+    }
+    match lines.is_empty() {
+        true => None,
+        false => Some(lines.join("\n"))
+    }
+}
+
+fn parent_of(pointer: &str) -> String {
+    s!(&pointer[..pointer.rfind('/').unwrap_or(0)])
+}
+
+/// Start of the line `pos` is on, if only blanks precede it there
+fn line_start(text: &[u8], pos: usize) -> Option<usize> {
+    let start = text[..pos].iter().rposition(|b| *b == b'\n').map_or(0, |p| p + 1);
+    match text[start..pos].iter().all(|b| *b == b' ' || *b == b'\t') {
+        true => Some(start),
+        false => None
+    }
+}
+
+fn line_end(text: &[u8], pos: usize) -> usize {
+    text[pos..].iter().position(|b| *b == b'\n').map_or(text.len(), |p| pos + p)
+}
+
+fn skip_blanks(text: &[u8], mut pos: usize) -> usize {
+    while matches!(text.get(pos), Some(b' ' | b'\t' | b'\r')) {
+        pos += 1;
+    }
+    pos
+}
+
+/// The leading whitespace of the line `pos` is on
+fn indent_of(text: &str, pos: usize) -> String {
+    let start = text[..pos].rfind('\n').map_or(0, |p| p + 1);
+    text[start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect()
+}
+
+/// The indentation of the first indented line, two spaces if there is none
+fn indent_unit(text: &str) -> String {
+    text.lines()
+        .map(|l| l.chars().take_while(|c| *c == ' ' || *c == '\t').collect::<String>())
+        .find(|indent| !indent.is_empty())
+        .unwrap_or_else(|| s!("  "))
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+    /// Containers entered, bounded by the default [`Limits`]
+    depth: usize,
+    nodes: BTreeMap<String, Node>,
+}
+
+impl <'a> Parser<'a> {
+
+    fn error(&self, message: &str) -> FromJsonError {
+        let before = &self.text[..self.pos.min(self.text.len())];
+        let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+        let column = before.len() - before.iter().rposition(|b| *b == b'\n').map_or(0, |p| p + 1) + 1;
+        FromJsonError::with_message(message).with_position(line, column)
+    }
+
+    /// Skip whitespace and comments
+    fn skip(&mut self) -> Result<(), FromJsonError> {
+        loop {
+            match &self.text[self.pos..] {
+                [b' ' | b'\t' | b'\r' | b'\n', ..] => self.pos += 1,
+                [b'/', b'/', ..] => self.pos = line_end(self.text, self.pos),
+                [b'/', b'*', rest @ ..] => match rest.windows(2).position(|w| w == b"*/") {
+                    Some(p) => self.pos += p + 4,
+                    None => return Err(self.error("unterminated comment"))
+                },
+                _ => return Ok(())
+            }
+        }
+    }
+
+    fn value(&mut self, pointer: String, entry: Option<(usize, usize)>) -> Result<Value, FromJsonError> {
+        self.skip()?;
+        let start = self.pos;
+        let mut children = Vec::new();
+        let container = matches!(self.text.get(start), Some(b'{' | b'['));
+        if container {
+            self.depth += 1;
+            Limits::default().check_depth(self.depth).map_err(|e| self.error(e.message()))?;
+        }
+        let value = match self.text.get(start) {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut object = Map::new();
+                loop {
+                    self.skip()?;
+                    if self.text.get(self.pos) == Some(&b'}') {
+                        break;
+                    }
+                    let key_start = self.pos;
+                    let key = match self.text.get(self.pos) {
+                        Some(b'"' | b'\'') => self.string()?,
+                        Some(c) if is_identifier(*c) => self.identifier(),
+                        _ => return Err(self.error("expected a key"))
+                    };
+                    let key_end = self.pos;
+                    self.skip()?;
+                    match self.text.get(self.pos) {
+                        Some(b':') => self.pos += 1,
+                        _ => return Err(self.error("expected `:`"))
+                    }
+                    let child = patch::child(&pointer, &key);
+                    object.insert(key, self.value(child.clone(), Some((key_start, key_end)))?);
+                    children.push(child);
+                    if !self.separator(&children, b'}')? {
+                        break;
+                    }
+                }
+                self.pos += 1;
+                Value::Object(object)
+            },
+            Some(b'[') => {
+                self.pos += 1;
+                let mut array = Vec::new();
+                loop {
+                    self.skip()?;
+                    if self.text.get(self.pos) == Some(&b']') {
+                        break;
+                    }
+                    let child = patch::child(&pointer, &array.len().to_string());
+                    array.push(self.value(child.clone(), None)?);
+                    children.push(child);
+                    if !self.separator(&children, b']')? {
+                        break;
+                    }
+                }
+                self.pos += 1;
+                Value::Array(array)
+            },
+            Some(b'"' | b'\'') => Value::String(self.string()?),
+            Some(_) => {
+                let end = self.text[start..].iter()
+                    .position(|b| matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b',' | b'}' | b']' | b'/'))
+                    .map_or(self.text.len(), |p| start + p);
+                let value = serde_json::from_slice::<Value>(&self.text[start..end]).map_err(|_| self.error("expected a value"))?;
+                self.pos = end;
+                value
+            },
+            None => return Err(self.error("unexpected end of input"))
+        };
+        if container {
+            self.depth -= 1;
+        }
+        let (entry, key_end) = entry.unwrap_or((start, start));
+        self.nodes.insert(pointer, Node { entry, key_end, start, end: self.pos, comma: None, children });
+        Ok(value)
+    }
+
+    /// Read the comma after the last of `children`, false at the `close` of the container
+    fn separator(&mut self, children: &[String], close: u8) -> Result<bool, FromJsonError> {
+        self.skip()?;
+        match self.text.get(self.pos) {
+            Some(b',') => {
+                let pos = self.pos;
+                if let Some(node) = children.last().and_then(|c| self.nodes.get_mut(c)) {
+                    node.comma = Some(pos);
+                }
+                self.pos += 1;
+                Ok(true)
+            },
+            Some(c) if *c == close => Ok(false),
+            _ => Err(self.error(&format!("expected `,` or `{}`", close as char)))
+        }
+    }
+
+    /// A string in double or single quotes
+    fn string(&mut self) -> Result<String, FromJsonError> {
+        let quote = self.text[self.pos];
+        let start = self.pos;
+        self.pos += 1;
+        while self.pos < self.text.len() && self.text[self.pos] != quote {
+            self.pos += if self.text[self.pos] == b'\\' { 2 } else { 1 };
+        }
+        if self.pos >= self.text.len() {
+            return Err(self.error("unterminated string"));
+        }
+        self.pos += 1;
+        let literal = &self.text[start..self.pos];
+        let parsed = match quote {
+            b'"' => serde_json::from_slice(literal),
+            _ => {
+                // the same literal in double quotes
+                let mut double = s!("\"");
+                let mut chars = std::str::from_utf8(&literal[1..literal.len() - 1]).unwrap_or_default().chars();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some('\'') => double.push('\''),
+                            Some(escaped) => double.extend(['\\', escaped]),
+                            None => {}
+                        },
+                        '"' => double.push_str("\\\""),
+                        c => double.push(c)
+                    }
+                }
+                double.push('"');
+                serde_json::from_str(&double)
+            }
+        };
+        parsed.map_err(|e| self.error(&e.to_string()))
+    }
+
+    /// An unquoted key
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self.pos < self.text.len() && is_identifier(self.text[self.pos]) {
+            self.pos += 1;
+        }
+        s!(String::from_utf8_lossy(&self.text[start..self.pos]))
+    }
+}
+
+fn is_identifier(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$'
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Document;
+
+    #[test]
+    fn edits_keep_formatting() {
+        let text = "/* app */\n{\n    \"name\": \"app\",   // shown in the title\n    \"tags\": [\"a\"],\n    \"limits\": {},\n    \"retries\": 3\n}\n";
+        let mut doc = Document::parse(text).unwrap();
+        doc.set("/name", "renamed").unwrap();
+        doc.set("/tags/1", "b").unwrap();
+        doc.set("/limits/max", json!({"cpu": 2})).unwrap();
+        assert_eq!(doc.remove("/retries").unwrap(), json!(3));
+        assert_eq!(doc.as_str(), "/* app */\n{\n    \"name\": \"renamed\",   // shown in the title\n    \"tags\": [\"a\", \"b\"],\n    \"limits\": {\n        \"max\": {\n            \"cpu\": 2\n        }\n    }\n}\n");
+
+        let mut compact = Document::parse(r#"{"a": 1, "b": [1, 2], "c": 3}"#).unwrap();
+        compact.remove("/b/0").unwrap();
+        compact.remove("/c").unwrap();
+        compact.set("/d", json!([true])).unwrap();
+        assert_eq!(compact.to_string(), r#"{"a": 1, "b": [2], "d": [true]}"#);
+        compact.remove("/b/0").unwrap();
+        assert_eq!(compact.value(), &json!({"a": 1, "b": [], "d": [true]}));
+
+        assert!(compact.set("/a/x", 1).is_err());
+        assert!(compact.set("/b/3", 1).is_err());
+        assert!(compact.set("port", 1).is_err());
+        assert!(compact.set("a/b", 1).is_err());
+        assert!(Document::parse(&"[".repeat(200_000)).unwrap_err().message().contains("depth"));
+        assert_eq!(Document::parse("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err().position(), Some((3, 7)));
+    }
+}
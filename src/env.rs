@@ -0,0 +1,132 @@
+//! Overriding a document with environment variables
+//!
+//! With prefix `APP` and separator `__`, `APP_SERVER__PORT=8080` sets
+//! `/server/port`. Names are lowercased unless disabled. Values are coerced to the
+//! type the base document has at their path with the relaxed rules, so a port
+//! stays a number and `APP_DEBUG=0` turns a boolean off. Arrays and objects are
+//! read from json text, paths without a typed value keep the string.
+
+use serde_json::{Map, Number, Value};
+
+use crate::{coerce, merge, patch, read, Maybe};
+
+/// Maps environment variables onto json paths
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    prefix: String,
+    separator: String,
+    lowercase: bool,
+    coerce: bool,
+}
+
+impl Resolver {
+
+    /// Variables starting with `prefix` followed by `_`
+    pub fn new(prefix: &str) -> Self {
+        Resolver { prefix: format!("{}_", prefix), separator: s!("__"), lowercase: true, coerce: true }
+    }
+
+    /// Separator between path segments, `__` by default
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = s!(separator);
+        self
+    }
+
+    /// Lowercase the path segments, enabled by default
+    pub fn lowercase(mut self, enable: bool) -> Self {
+        self.lowercase = enable;
+        self
+    }
+
+    /// Coerce values to the type in the base document, enabled by default
+    pub fn coerce(mut self, enable: bool) -> Self {
+        self.coerce = enable;
+        self
+    }
+
+    /// The overrides found in `vars` as a document, typed after `base`
+    pub fn layer(&self, base: &Value, vars: impl IntoIterator<Item = (String, String)>) -> Value {
+        let mut root = Value::Object(Map::new());
+        for (name, value) in vars {
+            let path = match name.strip_prefix(&self.prefix) {
+                Some(p) if !p.is_empty() => p,
+                _ => continue
+            };
+            let path = match self.lowercase {
+                true => path.to_lowercase(),
+                false => s!(path)
+            };
+            let segments: Vec<&str> = path.split(self.separator.as_str()).collect();
+            if segments.iter().any(|s| s.is_empty()) {
+                continue;
+            }
+            let typed = segments.iter().try_fold(base, |v, s| v.get(*s));
+            let value = match (self.coerce, typed) {
+                (true, Some(t)) => typed_as(t, value),
+                _ => Value::String(value)
+            };
+            patch::insert(&mut root, &segments, value);
+        }
+        root
+    }
+
+    /// Merge the overrides of the process environment over `base`
+    pub fn apply(&self, base: &Value) -> Value {
+        self.apply_vars(base, std::env::vars())
+    }
+
+    /// Merge the overrides found in `vars` over `base`
+    pub fn apply_vars(&self, base: &Value, vars: impl IntoIterator<Item = (String, String)>) -> Value {
+        merge::merge(base, &self.layer(base, vars))
+    }
+}
+
+/// `text` with the type of `like`, unchanged if it does not fit
+fn typed_as(like: &Value, text: String) -> Value {
+    let string = Value::String(text);
+    let typed = match like {
+        Value::Bool(_) => match coerce::to_bool(Some(&string)) {
+            Maybe::Strict(b) | Maybe::Relaxed(b) => Some(Value::Bool(b)),
+            _ => None
+        },
+        Value::Number(_) => match (coerce::to_int(Some(&string)), coerce::to_float(Some(&string))) {
+            (Maybe::Relaxed(i), _) => Some(Value::Number(i.into())),
+            (_, Maybe::Relaxed(f)) => Number::from_f64(f).map(Value::Number),
+            _ => None
+        },
+        Value::Array(_) | Value::Object(_) => read::from_str(string.as_str().unwrap_or_default()).ok()
+            .filter(|v| v.is_array() == like.is_array() && v.is_object() == like.is_object()),
+        Value::Null | Value::String(_) => None
+    };
+    typed.unwrap_or(string)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Resolver;
+
+    #[test]
+    fn env_overrides() {
+        let base = json!({"server": {"port": 8080, "host": "localhost", "tls": true}, "ratio": 0.5, "tags": ["a"]});
+        let vars = [
+            ("APP_SERVER__PORT", "9000"), ("APP_SERVER__TLS", "0"), ("APP_RATIO", "0.25"),
+            ("APP_TAGS", "[\"b\", \"c\"]"), ("APP_SERVER__NAME", "api"), ("APP_SERVER__HOST", "8.8.8.8"),
+            ("APP_", "x"), ("APP_A____B", "x"), ("OTHER_RATIO", "1")
+        ].iter().map(|(k, v)| (s!(*k), s!(*v)));
+
+        assert_eq!(Resolver::new("APP").apply_vars(&base, vars.clone()), json!({
+            "server": {"port": 9000, "host": "8.8.8.8", "tls": false, "name": "api"},
+            "ratio": 0.25,
+            "tags": ["b", "c"]
+        }));
+
+        let raw = Resolver::new("APP").coerce(false).layer(&base, vars);
+        assert_eq!(raw["server"]["port"], json!("9000"));
+
+        let dotted = Resolver::new("app").separator(".").lowercase(false).layer(&base, vec!((s!("app_Server.Port"), s!("1"))));
+        assert_eq!(dotted, json!({"Server": {"Port": "1"}}));
+    }
+}
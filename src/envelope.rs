@@ -0,0 +1,266 @@
+//! Unwrapping API response envelopes
+//!
+//! List endpoints wrap their items, as in `{"data": [...], "meta": {"next": ...}}`
+//! or `{"items": [...], "total": ...}`. An [`Envelope`] looks for the items,
+//! the next page and the total at the usual places, or at the JSON Pointers
+//! it is given, and returns them as a [`Page`]. A response in one of the
+//! common error shapes is reported as an error by [`check_error`] first,
+//! rather than read as an empty page:
+//!
+//! ```
+//! use json_relaxed::envelope::extract_page;
+//! use serde_json::{json, Value};
+//!
+//! let response = json!({"data": [{"id": 1}, {"id": 2}], "meta": {"next": "c2", "total": "40"}});
+//! let page = extract_page::<Value>(&response).unwrap();
+//! assert_eq!((page.items.len(), page.next.as_deref(), page.total), (2, Some("c2"), Some(40)));
+//!
+//! let failed = json!({"error": {"code": 429, "message": "slow down"}});
+//! assert_eq!(extract_page::<Value>(&failed).unwrap_err().message(), "error response 429: slow down");
+//! ```
+
+use serde_json::Value;
+use std::fmt;
+
+use crate::{array, coerce, FromJsonError, Maybe, MaybeValue, TryFromJson};
+
+/// Items of a response with the pagination info
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor, page number or URL of the next page
+    pub next: Option<String>,
+    pub total: Option<u64>,
+}
+
+impl <T> Page<T> {
+    pub fn has_more(&self) -> bool {
+        self.next.is_some()
+    }
+}
+
+/// Where the parts of a page are found, each by the first JSON Pointer that exists
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    items: Vec<String>,
+    next: Vec<String>,
+    total: Vec<String>,
+}
+
+fn pointers(p: &[&str]) -> Vec<String> {
+    p.iter().map(|p| s!(*p)).collect()
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            items: pointers(&["/data", "/items", "/results", "/records", "/entries"]),
+            next: pointers(&["/meta/next", "/meta/next_cursor", "/links/next", "/pagination/next", "/next", "/next_cursor", "/nextPageToken"]),
+            total: pointers(&["/meta/total", "/pagination/total", "/total", "/total_count", "/totalCount", "/count"]),
+        }
+    }
+}
+
+/// An error reported by a response, see [`error_response`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub code: Option<String>,
+    /// One per error of an `errors` array
+    pub messages: Vec<String>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "error response")?;
+        if let Some(code) = &self.code {
+            write!(f, " {}", code)?;
+        }
+        match self.messages.is_empty() {
+            true => Ok(()),
+            false => write!(f, ": {}", self.messages.join("; "))
+        }
+    }
+}
+
+impl From<ApiError> for FromJsonError {
+    fn from(e: ApiError) -> Self {
+        FromJsonError::with_message(&e.to_string())
+    }
+}
+
+/// Read `value` as an error response if it has one of the shapes
+/// `{"error": {...}}`, `{"error": "..."}`, `{"errors": [...]}` or
+/// `{"code": ..., "message": ...}` with a numeric code other than 0 or 2xx.
+/// A `status` like `"queued"` next to a message is no error.
+pub fn error_response(value: &Value) -> Option<ApiError> {
+    match (value.get("error"), value.get("errors")) {
+        (Some(Value::Object(_)), _) => Some(described(value.get("error"))),
+        (Some(Value::String(e)), _) => Some(match text(Some(value), &["error_description", "message"]) {
+            Some(message) => ApiError { code: Some(s!(e)), messages: vec!(message) },
+            None => ApiError { code: None, messages: vec!(s!(e)) }
+        }),
+        (Some(Value::Bool(true)), _) => Some(described(Some(value))),
+        (_, Some(Value::Array(errors))) if !errors.is_empty() => {
+            let code = errors.iter().find_map(|e| text(Some(e), &["code", "type"]));
+            let messages = errors.iter()
+                .filter_map(|e| match e {
+                    Value::String(s) => Some(s!(s)),
+                    e => text(Some(e), &["message", "detail", "title"])
+                })
+                .collect();
+            Some(ApiError { code, messages })
+        },
+        _ => {
+            let code = ["code", "status"].iter().find_map(|k| text(Some(value), &[k]).and_then(|c| c.parse::<u64>().ok()));
+            match (code, text(Some(value), &["message"])) {
+                (Some(code), Some(_)) if code != 0 && !(200..300).contains(&code) => Some(described(Some(value))),
+                _ => None
+            }
+        }
+    }
+}
+
+/// Fail with the error of an error response, see [`error_response`]
+pub fn check_error(value: &Value) -> Result<(), FromJsonError> {
+    match error_response(value) {
+        Some(e) => Err(e.into()),
+        None => Ok(())
+    }
+}
+
+fn described(error: Option<&Value>) -> ApiError {
+    ApiError {
+        code: text(error, &["code", "status", "type"]),
+        messages: text(error, &["message", "detail", "description", "title"]).into_iter().collect(),
+    }
+}
+
+/// The first of `keys` of `value` that reads as a string. Read without the
+/// accessors, so the probes are neither observed nor subject to a session policy.
+fn text(value: Option<&Value>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|k| match coerce::to_string(value.and_then(|v| v.get(*k))) {
+        Maybe::Strict(s) | Maybe::Relaxed(s) => Some(s),
+        _ => None
+    })
+}
+
+/// Extract a page from `value` with the default [`Envelope`]
+pub fn extract_page<T: TryFromJson>(value: &Value) -> Result<Page<T>, FromJsonError> {
+    Envelope::new().extract(value)
+}
+
+/// The parent of `pointer`, and its last token
+fn split(pointer: &str) -> (&str, String) {
+    let at = pointer.rfind('/').unwrap_or(0);
+    (&pointer[..at], pointer[at..].trim_start_matches('/').replace("~1", "/").replace("~0", "~"))
+}
+
+impl Envelope {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Read the items at `pointer` only
+    pub fn items(mut self, pointer: &str) -> Self {
+        self.items = vec!(s!(pointer));
+        self
+    }
+
+    /// Read the next page at `pointer` only
+    pub fn next(mut self, pointer: &str) -> Self {
+        self.next = vec!(s!(pointer));
+        self
+    }
+
+    /// Read the total at `pointer` only
+    pub fn total(mut self, pointer: &str) -> Self {
+        self.total = vec!(s!(pointer));
+        self
+    }
+
+    /// The items converted with `TryFromJson`, failing at the first item
+    /// that does not convert or with the error of an error response. A
+    /// response that is an array is the items itself.
+    pub fn extract<T: TryFromJson>(&self, value: &Value) -> Result<Page<T>, FromJsonError> {
+        check_error(value)?;
+        let found = self.items.iter().find(|p| value.pointer(p).is_some_and(|v| !v.is_null()));
+        let options = array::Options::new().fail_fast(true);
+        let items = match (value, found) {
+            (Value::Array(_), None) => coerce::to_array_with(Some(value), &options),
+            (_, Some(pointer)) => {
+                let (parent, key) = split(pointer);
+                value.pointer(parent).maybe_array_with(key, &options)
+            },
+            (_, None) => return Err(FromJsonError::with_message(&format!("no items at {}", self.items.join(", "))))
+        };
+        let items = match items {
+            Maybe::Strict(items) | Maybe::Relaxed(items) => items,
+            Maybe::Null => Vec::new(),
+            Maybe::Error(e) => return Err(e)
+        };
+
+        let next = self.first(value, &self.next, |parent, key| parent.maybe_string(key));
+        let total = self.first(value, &self.total, |parent, key| parent.maybe_uint(key));
+        Ok(Page { items, next, total })
+    }
+
+    /// The first of `pointers` that reads as a value
+    fn first<V>(&self, value: &Value, pointers: &[String], read: impl Fn(Option<&Value>, String) -> Maybe<V>) -> Option<V> {
+        pointers.iter()
+            .filter(|p| value.pointer(p).is_some())
+            .find_map(|p| {
+                let (parent, key) = split(p);
+                match read(value.pointer(parent), key) {
+                    Maybe::Strict(v) | Maybe::Relaxed(v) => Some(v),
+                    _ => None
+                }
+            })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{error_response, extract_page, ApiError, Envelope};
+    use crate::session::{Policy, Session};
+
+    #[test]
+    fn envelopes() {
+        let page = extract_page::<Value>(&json!({"items": ["a", "b"], "total": 2, "next": null})).unwrap();
+        assert_eq!((page.items, page.next, page.total), (vec!(json!("a"), json!("b")), None, Some(2)));
+
+        let page = extract_page::<Value>(&json!([1, 2, 3])).unwrap();
+        assert_eq!((page.items.len(), page.has_more()), (3, false));
+
+        let custom = Envelope::new().items("/payload/rows").next("/paging/after").total("/paging/of");
+        let page = custom.extract::<Value>(&json!({"payload": {"rows": [{}]}, "paging": {"after": 20, "of": "81"}})).unwrap();
+        assert_eq!((page.next.as_deref(), page.total), (Some("20"), Some(81)));
+
+        assert!(extract_page::<Value>(&json!({"results": null})).is_err());
+    }
+
+    #[test]
+    fn error_shapes() {
+        let api = |code: Option<&str>, messages: &[&str]| Some(ApiError { code: code.map(String::from), messages: messages.iter().map(|m| s!(*m)).collect() });
+        assert_eq!(error_response(&json!({"error": {"status": "NOT_FOUND", "detail": "no such user"}})), api(Some("NOT_FOUND"), &["no such user"]));
+        assert_eq!(error_response(&json!({"error": "invalid_grant", "error_description": "expired"})), api(Some("invalid_grant"), &["expired"]));
+        assert_eq!(error_response(&json!({"error": "down"})), api(None, &["down"]));
+        assert_eq!(error_response(&json!({"errors": [{"message": "a", "code": 7}, "b"], "data": null})), api(Some("7"), &["a", "b"]));
+        assert_eq!(error_response(&json!({"code": 500, "message": "boom"})), api(Some("500"), &["boom"]));
+
+        assert_eq!(error_response(&json!({"code": 0, "message": "ok", "data": []})), None);
+        assert_eq!(error_response(&json!({"status": "queued", "message": "accepted", "data": []})), None);
+        assert_eq!(error_response(&json!({"code": "E_RATE", "message": "slow down"})), None);
+        assert_eq!(error_response(&json!({"status": "503", "message": "later"})), api(Some("503"), &["later"]));
+        assert_eq!(error_response(&json!({"error": null, "errors": [], "items": []})), None);
+        let e = extract_page::<Value>(&json!({"errors": ["x", "y"], "data": []})).unwrap_err();
+        assert_eq!(e.message(), "error response: x; y");
+
+        let (error, trace) = Session::new().policy(Policy::Strict).run(|| error_response(&json!({"code": 500, "message": "boom"})));
+        assert_eq!(error, api(Some("500"), &["boom"]));
+        assert!(trace.is_clean());
+    }
+}
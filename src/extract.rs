@@ -0,0 +1,377 @@
+//! Extraction plans for reading the same fields from many values
+//!
+//! An [`Extractor`] declares the fields once, with their type, default and
+//! policy. The resulting [`Plan`] selects all fields of a value in one pass
+//! (see [`KeySet`]) and returns them as a row of [`Maybe`] cells, a row of
+//! resolved cells or a typed tuple:
+//!
+//! ```
+//! use json_relaxed::extract::{Extractor, Type};
+//! use serde_json::json;
+//!
+//! let plan = Extractor::new()
+//!     .field("id", Type::Uint)
+//!     .field("host", Type::String).default("localhost")
+//!     .field("debug", Type::Bool).strict().optional()
+//!     .build();
+//!
+//! let (id, host, debug): (u64, String, Option<bool>) = plan.extract(&json!({"id": "7"})).unwrap();
+//! assert_eq!((id, host.as_str(), debug), (7, "localhost", None));
+//! ```
+
+use serde_json::Value;
+
+use crate::keys::KeySet;
+use crate::{coerce, observe, FromJsonError, Maybe};
+
+/// Type a field is read as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    String,
+    /// The value as it is, never relaxed
+    Json,
+}
+
+/// A field read by a [`Plan`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    /// An optional field without value
+    Null,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    String(String),
+    Json(Value),
+}
+
+impl From<bool> for Cell {
+    fn from(b: bool) -> Self {
+        Cell::Bool(b)
+    }
+}
+
+impl From<i32> for Cell {
+    fn from(i: i32) -> Self {
+        Cell::Int(i.into())
+    }
+}
+
+impl From<i64> for Cell {
+    fn from(i: i64) -> Self {
+        Cell::Int(i)
+    }
+}
+
+impl From<u64> for Cell {
+    fn from(u: u64) -> Self {
+        Cell::Uint(u)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(f: f64) -> Self {
+        Cell::Float(f)
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(s: &str) -> Self {
+        Cell::String(s!(s))
+    }
+}
+
+impl From<String> for Cell {
+    fn from(s: String) -> Self {
+        Cell::String(s)
+    }
+}
+
+impl From<Value> for Cell {
+    fn from(v: Value) -> Self {
+        Cell::Json(v)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    key: String,
+    kind: Type,
+    default: Option<Cell>,
+    strict: bool,
+    optional: bool,
+}
+
+/// Builder for a [`Plan`], settings after a field apply to that field
+#[derive(Debug, Clone, Default)]
+pub struct Extractor {
+    fields: Vec<Field>,
+}
+
+impl Extractor {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Read member `key` as `kind`
+    pub fn field(mut self, key: &str, kind: Type) -> Self {
+        self.fields.push(Field { key: s!(key), kind, default: None, strict: false, optional: false });
+        self
+    }
+
+    /// Use `value` if the field is missing, null or can not be read
+    pub fn default(mut self, value: impl Into<Cell>) -> Self {
+        if let Some(f) = self.fields.last_mut() {
+            f.default = Some(value.into());
+        }
+        self
+    }
+
+    /// Fail instead of coercing, `"8080"` is no Uint
+    pub fn strict(mut self) -> Self {
+        if let Some(f) = self.fields.last_mut() {
+            f.strict = true;
+        }
+        self
+    }
+
+    /// A missing or null field without default is `Cell::Null` instead of an error
+    pub fn optional(mut self) -> Self {
+        if let Some(f) = self.fields.last_mut() {
+            f.optional = true;
+        }
+        self
+    }
+
+    pub fn build(self) -> Plan {
+        let keys: Vec<&str> = self.fields.iter().map(|f| f.key.as_str()).collect();
+        let keys = KeySet::new(&keys);
+        // fields reading the same member share its slot
+        let slots = self.fields.iter().enumerate().map(|(i, f)| keys.slot(&f.key).unwrap_or(i)).collect();
+        Plan { keys, slots, fields: self.fields }
+    }
+}
+
+/// Fields compiled by an [`Extractor`], to be applied to many values
+#[derive(Debug, Clone)]
+pub struct Plan {
+    keys: KeySet,
+    /// Slot in `keys` of each field
+    slots: Vec<usize>,
+    fields: Vec<Field>,
+}
+
+impl Plan {
+
+    /// The outcome for every field, strict fields are `Error` instead of `Relaxed`.
+    /// Defaults are not applied.
+    pub fn row(&self, value: &Value) -> Vec<Maybe<Cell>> {
+        let selected = self.keys.select(value);
+        self.fields.iter().zip(&self.slots)
+            .map(|(field, slot)| {
+                let v = selected.get(*slot);
+                observe::outcome(field.key.as_str(), v, read(field, v))
+            })
+            .collect()
+    }
+
+    /// Every field with defaults applied, failing on the first field without value
+    pub fn values(&self, value: &Value) -> Result<Vec<Cell>, FromJsonError> {
+        self.row(value).into_iter().zip(&self.fields)
+            .map(|(maybe, field)| resolve(field, maybe))
+            .collect()
+    }
+
+    /// The fields as a tuple, like `(u64, String, Option<bool>)`
+    pub fn extract<T: FromRow>(&self, value: &Value) -> Result<T, FromJsonError> {
+        T::from_row(self.values(value)?)
+    }
+}
+
+fn read(field: &Field, value: Option<&Value>) -> Maybe<Cell> {
+    let maybe = match field.kind {
+        Type::Bool => cell(coerce::to_bool(value), Cell::Bool),
+        Type::Int => cell(coerce::to_int(value), Cell::Int),
+        Type::Uint => cell(coerce::to_uint(value), Cell::Uint),
+        Type::Float => cell(coerce::to_float(value), Cell::Float),
+        Type::String => cell(coerce::to_string(value), Cell::String),
+        Type::Json => match value {
+            Some(Value::Null) | None => Maybe::Null,
+            Some(v) => Maybe::Strict(Cell::Json(v.clone()))
+        }
+    };
+    match maybe {
+        Maybe::Relaxed(_) if field.strict => {
+            Maybe::Error(FromJsonError::with_message(&format!("`{}` is not strictly a {:?}", field.key, field.kind)))
+        },
+        m => m
+    }
+}
+
+fn cell<T>(maybe: Maybe<T>, f: impl FnOnce(T) -> Cell) -> Maybe<Cell> {
+    match maybe {
+        Maybe::Strict(v) => Maybe::Strict(f(v)),
+        Maybe::Relaxed(v) => Maybe::Relaxed(f(v)),
+        Maybe::Error(e) => Maybe::Error(e),
+        Maybe::Null => Maybe::Null
+    }
+}
+
+fn resolve(field: &Field, maybe: Maybe<Cell>) -> Result<Cell, FromJsonError> {
+    match (maybe, &field.default) {
+        (Maybe::Strict(c), _) | (Maybe::Relaxed(c), _) => Ok(c),
+        (m, Some(d)) => Ok(m.default(d.clone())),
+        (Maybe::Null, None) if field.optional => Ok(Cell::Null),
+        (Maybe::Null, None) => Err(FromJsonError::with_message(&format!("missing field `{}`", field.key))),
+        (Maybe::Error(e), None) => Err(FromJsonError::with_message(&format!("field `{}`: {}", field.key, e.message())))
+    }
+}
+
+/// Conversion of a resolved [`Cell`], numbers convert between types if they fit
+pub trait FromCell: Sized {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError>;
+}
+
+fn mismatch(cell: &Cell, expected: &str) -> FromJsonError {
+    FromJsonError::with_message(&format!("expected {}, found {:?}", expected, cell))
+}
+
+impl FromCell for bool {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::Bool(b) => Ok(b),
+            c => Err(mismatch(&c, "bool"))
+        }
+    }
+}
+
+impl FromCell for i64 {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::Int(i) => Ok(i),
+            Cell::Uint(u) if u <= i64::MAX as u64 => Ok(u as i64),
+            c => Err(mismatch(&c, "i64"))
+        }
+    }
+}
+
+impl FromCell for u64 {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::Uint(u) => Ok(u),
+            Cell::Int(i) if i >= 0 => Ok(i as u64),
+            c => Err(mismatch(&c, "u64"))
+        }
+    }
+}
+
+impl FromCell for f64 {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::Float(f) => Ok(f),
+            Cell::Int(i) => Ok(i as f64),
+            Cell::Uint(u) => Ok(u as f64),
+            c => Err(mismatch(&c, "f64"))
+        }
+    }
+}
+
+impl FromCell for String {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::String(s) => Ok(s),
+            c => Err(mismatch(&c, "string"))
+        }
+    }
+}
+
+impl FromCell for Value {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::Null => Ok(Value::Null),
+            Cell::Bool(b) => Ok(Value::Bool(b)),
+            Cell::Int(i) => Ok(i.into()),
+            Cell::Uint(u) => Ok(u.into()),
+            Cell::Float(f) => Ok(f.into()),
+            Cell::String(s) => Ok(Value::String(s)),
+            Cell::Json(v) => Ok(v)
+        }
+    }
+}
+
+impl <T: FromCell> FromCell for Option<T> {
+    fn from_cell(cell: Cell) -> Result<Self, FromJsonError> {
+        match cell {
+            Cell::Null => Ok(None),
+            c => T::from_cell(c).map(Some)
+        }
+    }
+}
+
+/// Conversion of all fields of a [`Plan`], implemented for tuples of [`FromCell`]
+pub trait FromRow: Sized {
+    fn from_row(cells: Vec<Cell>) -> Result<Self, FromJsonError>;
+}
+
+macro_rules! tuple_row {
+    ($n:expr; $($t:ident),+) => {
+        impl <$($t: FromCell),+> FromRow for ($($t,)+) {
+            fn from_row(cells: Vec<Cell>) -> Result<Self, FromJsonError> {
+                if cells.len() != $n {
+                    return Err(FromJsonError::with_message(&format!("expected {} fields, the plan has {}", $n, cells.len())));
+                }
+                let mut cells = cells.into_iter();
+                Ok(($($t::from_cell(cells.next().expect("length checked"))?,)+))
+            }
+        }
+    };
+}
+
+tuple_row!(1; A);
+tuple_row!(2; A, B);
+tuple_row!(3; A, B, C);
+tuple_row!(4; A, B, C, D);
+tuple_row!(5; A, B, C, D, E);
+tuple_row!(6; A, B, C, D, E, F);
+tuple_row!(7; A, B, C, D, E, F, G);
+tuple_row!(8; A, B, C, D, E, F, G, H);
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Cell, Extractor, Type};
+    use crate::Maybe;
+
+    #[test]
+    fn extraction_plans() {
+        let plan = Extractor::new()
+            .field("port", Type::Uint).default(8080)
+            .field("name", Type::String)
+            .field("ratio", Type::Float).strict()
+            .field("tags", Type::Json).optional()
+            .build();
+
+        let row = plan.row(&json!({"port": "80", "name": "api", "ratio": "0.5"}));
+        assert!(matches!(row[0], Maybe::Relaxed(Cell::Uint(80))));
+        assert!(matches!(row[2], Maybe::Error(_)));
+        assert!(matches!(row[3], Maybe::Null));
+
+        let records = [json!({"name": "a", "ratio": 1, "tags": [1]}), json!({"port": [80], "name": 2, "ratio": 0.5})];
+        let read: Vec<(u64, String, f64, Option<serde_json::Value>)> = records.iter().map(|r| plan.extract(r).unwrap()).collect();
+        assert_eq!(read, vec!((8080, s!("a"), 1.0, Some(json!([1]))), (8080, s!("2"), 0.5, None)));
+
+        let error = plan.values(&json!({"ratio": 1})).unwrap_err();
+        assert_eq!(error.message(), "missing field `name`");
+        assert!(plan.extract::<(u64, String)>(&records[0]).is_err());
+
+        let twice = Extractor::new().field("id", Type::Int).field("id", Type::String).build();
+        assert_eq!(twice.extract::<(i64, String)>(&json!({"id": 5})).unwrap(), (5, s!("5")));
+    }
+}
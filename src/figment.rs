@@ -0,0 +1,172 @@
+//! A figment provider reading json with this crate
+//!
+//! Input goes through [`read`](crate::read), so byte order marks, UTF-16 or Latin-1
+//! input and duplicate keys are handled per [`read::Options`]. Comments, trailing
+//! commas and the rest of the [lenient](read::Options::lenient) syntax are accepted
+//! unless the options given turn it off. [`normalize::Rules`] can be applied
+//! before figment sees the values, e.g. to turn `"8080"` into `8080`.
+
+use figment::providers::Serialized;
+use figment::value::{Dict, Map};
+use figment::{Error, Metadata, Profile, Provider};
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::{normalize, read, FromJsonError};
+
+enum Source {
+    File(PathBuf),
+    Text(String),
+}
+
+/// Provider of a json file or string, see the [module documentation](self)
+pub struct RelaxedJson {
+    source: Source,
+    options: read::Options,
+    rules: Option<normalize::Rules>,
+    profile: Profile,
+    nested: bool,
+}
+
+impl RelaxedJson {
+
+    /// Read the file at `path`, a missing file provides no values
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        RelaxedJson::new(Source::File(path.into()))
+    }
+
+    pub fn string(text: &str) -> Self {
+        RelaxedJson::new(Source::Text(s!(text)))
+    }
+
+    fn new(source: Source) -> Self {
+        RelaxedJson {
+            source,
+            options: read::Options::new().lenient(true),
+            rules: None,
+            profile: Profile::Default,
+            nested: false,
+        }
+    }
+
+    pub fn options(mut self, options: read::Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Normalize the document before handing it to figment
+    pub fn rules(mut self, rules: normalize::Rules) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Profile the values are provided for, `Profile::Default` by default
+    pub fn profile(mut self, profile: impl Into<Profile>) -> Self {
+        self.profile = profile.into();
+        self
+    }
+
+    /// Treat the top level keys as profiles
+    pub fn nested(mut self) -> Self {
+        self.nested = true;
+        self
+    }
+
+    fn read(&self) -> Result<Option<Value>, FromJsonError> {
+        let bytes = match &self.source {
+            Source::Text(text) => text.clone().into_bytes(),
+            Source::File(path) if !path.exists() => return Ok(None),
+            Source::File(path) => std::fs::read(path)?
+        };
+        let mut value = read::from_slice_with(&bytes, &self.options)?;
+        if let Some(rules) = &self.rules {
+            normalize::normalize(&mut value, rules);
+        }
+        Ok(Some(value))
+    }
+}
+
+/// serde_json writes numbers as a private struct under arbitrary_precision,
+/// figment is handed its own values instead
+#[cfg(feature = "arbitrary_precision")]
+fn plain(value: Value) -> figment::value::Value {
+    use figment::value::{Empty, Tag, Value as Figment};
+
+    match value {
+        Value::Null => Figment::Empty(Tag::Default, Empty::Unit),
+        Value::Bool(b) => Figment::from(b),
+        Value::Number(n) => {
+            match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Figment::from(i),
+                (_, Some(u)) => Figment::from(u),
+                _ => Figment::from(n.as_f64().unwrap_or(f64::NAN))
+            }
+        },
+        Value::String(s) => Figment::from(s),
+        Value::Array(a) => Figment::from(a.into_iter().map(plain).collect::<Vec<_>>()),
+        Value::Object(o) => Figment::from(o.into_iter().map(|(k, v)| (k, plain(v))).collect::<Dict>())
+    }
+}
+
+#[cfg(not(feature = "arbitrary_precision"))]
+fn plain(value: Value) -> Value {
+    value
+}
+
+impl Provider for RelaxedJson {
+    fn metadata(&self) -> Metadata {
+        match &self.source {
+            Source::File(path) => Metadata::from("relaxed JSON file", path.as_path()),
+            Source::Text(_) => Metadata::named("relaxed JSON source string")
+        }
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+        let value = match self.read() {
+            Ok(Some(v)) => v,
+            Ok(None) => return Ok(Map::new()),
+            Err(e) => return Err(Error::from(e.to_string()))
+        };
+        match (value, self.nested) {
+            (Value::Object(o), true) => {
+                let mut data = Map::new();
+                for (profile, v) in o {
+                    data.extend(Serialized::from(plain(v), profile).data()?);
+                }
+                Ok(data)
+            },
+            (v @ Value::Object(_), false) => Serialized::from(plain(v), self.profile.clone()).data(),
+            _ => Err(Error::from(s!("relaxed JSON source is not an object")))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use figment::Figment;
+    use serde_json::json;
+
+    use super::RelaxedJson;
+    use crate::normalize::{KeyCase, Rules};
+
+    #[test]
+    fn figment_provider() {
+        let source = "\u{feff}{\"Server\": {\"Port\": \"8080\", \"Debug\": \"yes\"}}";
+        let figment = Figment::new()
+            .merge(RelaxedJson::string(source).rules(Rules::new().numbers(true).key_case(KeyCase::Lower)))
+            .merge(RelaxedJson::file("does/not/exist.json"));
+
+        assert_eq!(figment.extract_inner::<u16>("server.port").unwrap(), 8080);
+        assert_eq!(figment.extract_inner::<String>("server.debug").unwrap(), "yes");
+
+        let nested = Figment::new().merge(RelaxedJson::string("{\"debug\": {\"a\": 1}, \"release\": {\"a\": 2}}").nested());
+        assert_eq!(nested.select("release").extract::<serde_json::Value>().unwrap(), json!({"a": 2}));
+        assert!(Figment::new().merge(RelaxedJson::string("[1]")).extract::<serde_json::Value>().is_err());
+
+        let commented = RelaxedJson::string("{\n  // seconds\n  \"timeout\": 30,\n  hosts: ['a', 'b',],\n}");
+        let figment = Figment::new().merge(commented);
+        assert_eq!(figment.extract_inner::<u32>("timeout").unwrap(), 30);
+        assert_eq!(figment.extract_inner::<Vec<String>>("hosts").unwrap(), vec!(s!("a"), s!("b")));
+    }
+}
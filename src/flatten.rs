@@ -0,0 +1,174 @@
+//! Converting between nested documents and flat objects with joined keys
+
+use serde_json::{Map, Value};
+
+/// How arrays are represented in flat keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arrays {
+    /// `items.0.id`
+    #[default]
+    Index,
+    /// `items[0].id`
+    Brackets,
+    /// arrays are kept as values and not flattened
+    Keep,
+}
+
+/// Flatten `value` into `{"a.b.c": 1, "items.0.id": 2}`, see [`Flatten`]
+pub fn flatten(value: &Value) -> Value {
+    Flatten::new().flatten(value)
+}
+
+/// Inverse of [`flatten`], see [`Flatten`]
+pub fn unflatten(value: &Value) -> Value {
+    Flatten::new().unflatten(value)
+}
+
+/// Configurable flattening, by default with `.` as separator and indices for arrays.
+/// Empty objects and arrays are kept as values. A scalar root is stored under `""`.
+#[derive(Debug, Clone)]
+pub struct Flatten {
+    separator: String,
+    arrays: Arrays,
+}
+
+impl Default for Flatten {
+    fn default() -> Self {
+        Flatten { separator: s!("."), arrays: Arrays::Index }
+    }
+}
+
+impl Flatten {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = s!(separator);
+        self
+    }
+
+    pub fn arrays(mut self, arrays: Arrays) -> Self {
+        self.arrays = arrays;
+        self
+    }
+
+    pub fn flatten(&self, value: &Value) -> Value {
+        let mut flat = Map::new();
+        self.flatten_into(&mut flat, String::new(), value);
+        Value::Object(flat)
+    }
+
+    fn flatten_into(&self, flat: &mut Map<String, Value>, prefix: String, value: &Value) {
+        match value {
+            Value::Object(o) if !o.is_empty() => {
+                for (k, v) in o.iter() {
+                    let key = match prefix.is_empty() {
+                        true => k.clone(),
+                        false => format!("{}{}{}", prefix, self.separator, k)
+                    };
+                    self.flatten_into(flat, key, v);
+                }
+            },
+            Value::Array(a) if !a.is_empty() && self.arrays != Arrays::Keep => {
+                for (i, v) in a.iter().enumerate() {
+                    let key = match (self.arrays, prefix.is_empty()) {
+                        (Arrays::Brackets, _) => format!("{}[{}]", prefix, i),
+                        (_, true) => i.to_string(),
+                        (_, false) => format!("{}{}{}", prefix, self.separator, i)
+                    };
+                    self.flatten_into(flat, key, v);
+                }
+            },
+            _ => {
+                flat.insert(prefix, value.clone());
+            }
+        }
+    }
+
+    /// Rebuild a nested document from a flat object.
+    /// Objects whose keys are exactly the indices `0..n` become arrays, unless arrays are kept.
+    pub fn unflatten(&self, value: &Value) -> Value {
+        let flat = match value {
+            Value::Object(o) => o,
+            _ => return value.clone()
+        };
+
+        let mut root = Value::Object(Map::new());
+        for (k, v) in flat.iter() {
+            if k.is_empty() {
+                return v.clone();
+            }
+            let mut target = &mut root;
+            for segment in self.segments(k) {
+                if !target.is_object() {
+                    *target = Value::Object(Map::new());
+                }
+                target = target.as_object_mut().expect("checked above")
+                    .entry(segment).or_insert(Value::Null);
+            }
+            *target = v.clone();
+        }
+
+        if self.arrays != Arrays::Keep {
+            to_arrays(&mut root);
+        }
+        root
+    }
+
+    fn segments(&self, key: &str) -> Vec<String> {
+        let parts = key.split(self.separator.as_str());
+        match self.arrays {
+            Arrays::Brackets => parts.flat_map(|p| p.split('[').map(|s| s.trim_end_matches(']').to_string()).collect::<Vec<_>>())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            _ => parts.map(String::from).collect()
+        }
+    }
+}
+
+fn to_arrays(value: &mut Value) {
+    match value {
+        Value::Object(o) => {
+            for (_, v) in o.iter_mut() {
+                to_arrays(v);
+            }
+            let is_sequence = !o.is_empty() && (0..o.len()).all(|i| o.contains_key(&i.to_string()));
+            if is_sequence {
+                let mut o = std::mem::take(o);
+                *value = Value::Array((0..o.len()).map(|i| o.remove(&i.to_string()).expect("checked above")).collect());
+            }
+        },
+        Value::Array(a) => {
+            for v in a.iter_mut() {
+                to_arrays(v);
+            }
+        },
+        _ => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Arrays, Flatten};
+
+    #[test]
+    fn flatten_roundtrip() {
+        let doc = json!({"a": {"b": {"c": 1}}, "items": [{"id": 2}, {"id": 3}], "empty": {}});
+        let flat = super::flatten(&doc);
+        assert_eq!(flat, json!({"a.b.c": 1, "items.0.id": 2, "items.1.id": 3, "empty": {}}));
+        assert_eq!(super::unflatten(&flat), doc);
+
+        let env = Flatten::new().separator("__").arrays(Arrays::Brackets);
+        let flat = env.flatten(&doc);
+        assert_eq!(flat, json!({"a__b__c": 1, "items[0]__id": 2, "items[1]__id": 3, "empty": {}}));
+        assert_eq!(env.unflatten(&flat), doc);
+
+        let keep = Flatten::new().arrays(Arrays::Keep);
+        assert_eq!(keep.flatten(&doc)["items"], doc["items"]);
+    }
+}
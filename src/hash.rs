@@ -0,0 +1,122 @@
+//! Deterministic hashing of documents
+//!
+//! The hash covers a canonical form of the value: object keys are sorted and numbers
+//! are compared by value, so `{"a": 1, "b": 2.0}` and `{"b": 2, "a": 1}` hash the same.
+
+use serde_json::{Number, Value};
+use std::hash::Hasher;
+
+/// Stable 64 bit FNV-1a digest of the canonical form of `value`.
+/// Stable across platforms and releases, but not cryptographically secure.
+pub fn canonical_hash(value: &Value) -> u64 {
+    let mut hasher = Fnv64::default();
+    hash_canonical(value, &mut hasher);
+    hasher.finish()
+}
+
+/// Feed the canonical form of `value` into `hasher`, for example a cryptographic digest
+/// wrapped as a `Hasher`
+pub fn hash_canonical<H: Hasher>(value: &Value, hasher: &mut H) {
+    match value {
+        Value::Null => hasher.write_u8(0),
+        Value::Bool(b) => {
+            hasher.write_u8(1);
+            hasher.write_u8(*b as u8);
+        },
+        Value::Number(n) => {
+            hasher.write_u8(2);
+            hash_number(n, hasher);
+        },
+        Value::String(s) => {
+            hasher.write_u8(3);
+            hash_str(s, hasher);
+        },
+        Value::Array(a) => {
+            hasher.write_u8(4);
+            hasher.write_u64(a.len() as u64);
+            for v in a.iter() {
+                hash_canonical(v, hasher);
+            }
+        },
+        Value::Object(o) => {
+            hasher.write_u8(5);
+            hasher.write_u64(o.len() as u64);
+            let mut entries: Vec<_> = o.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            for (k, v) in entries {
+                hash_str(k, hasher);
+                hash_canonical(v, hasher);
+            }
+        }
+    }
+}
+
+fn hash_str<H: Hasher>(s: &str, hasher: &mut H) {
+    hasher.write_u64(s.len() as u64);
+    hasher.write(s.as_bytes());
+}
+
+/// Integral numbers hash as integers, so 2 and 2.0 are the same
+fn hash_number<H: Hasher>(n: &Number, hasher: &mut H) {
+    let integral = match (n.as_i64(), n.as_u64(), n.as_f64()) {
+        (Some(i), _, _) => Some(i as i128),
+        (_, Some(u), _) => Some(u as i128),
+        (_, _, Some(f)) if f.fract() == 0.0 && f.abs() < 1e38 => Some(f as i128),
+        _ => None
+    };
+    match integral {
+        Some(i) => {
+            hasher.write_u8(0);
+            hasher.write(&i.to_le_bytes());
+        },
+        None => {
+            hasher.write_u8(1);
+            hasher.write_u64(n.as_f64().unwrap_or(0.0).to_bits());
+        }
+    }
+}
+
+/// 64 bit FNV-1a, with explicit little endian handling of integers
+struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Fnv64(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv64 {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::canonical_hash;
+
+    #[test]
+    fn canonical_hashing() {
+        let a = json!({"a": 1, "b": [true, null, 2.0], "c": {"x": "y"}});
+        let b = json!({"c": {"x": "y"}, "b": [true, null, 2], "a": 1.0});
+
+        assert_eq!(canonical_hash(&a), canonical_hash(&b));
+        assert_ne!(canonical_hash(&a), canonical_hash(&json!({"a": 1})));
+        assert_ne!(canonical_hash(&json!(["ab", "c"])), canonical_hash(&json!(["a", "bc"])));
+        assert_eq!(canonical_hash(&json!(null)), 0xaf63bd4c8601b7df);
+    }
+}
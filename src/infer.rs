@@ -0,0 +1,356 @@
+//! Inferring models from sample documents
+//!
+//! The samples are [profiled](crate::profile) and every path gets the type that
+//! reads all observed values: numbers that are sometimes strings stay numbers,
+//! members that are missing or null in some samples become `Option`, arrays
+//! become `Vec` and single values where arrays are expected are fine. The result
+//! is a Rust skeleton implementing [`TryFromJson`](crate::TryFromJson) with the
+//! relaxed accessors, or a schema document in the format of [`schema`](crate::schema):
+//!
+//! ```
+//! use json_relaxed::infer;
+//! use serde_json::json;
+//!
+//! let samples = [json!({"id": 1, "name": "a"}), json!({"id": "2"})];
+//! let inferred = infer::infer(&samples);
+//! let code = inferred.rust("User");
+//! assert!(code.contains("pub id: i64,"));
+//! assert!(code.contains("pub name: Option<String>,"));
+//! assert_eq!(inferred.schema()["required"], json!(["id"]));
+//! ```
+
+use serde_json::{json, Value};
+use std::fmt::Write;
+
+use crate::profile::{self, PathStats, Profile};
+use crate::schema;
+
+/// The type read at a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Bool,
+    Int,
+    Float,
+    String,
+    Array,
+    Object,
+    /// Different kinds of containers or containers and scalars
+    Any,
+    /// Only nulls were seen
+    Null,
+}
+
+fn classify(stats: &PathStats) -> Type {
+    let values = stats.count - stats.nulls;
+    let numeric = stats.numbers + stats.numeric_strings;
+    match stats {
+        _ if values == 0 => Type::Null,
+        s if s.objects == values => Type::Object,
+        s if s.objects > 0 => Type::Any,
+        s if s.arrays > 0 => Type::Array,
+        s if s.bools > 0 && s.bools + s.bool_strings == values => Type::Bool,
+        s if numeric == values && s.integers == values => Type::Int,
+        _ if numeric == values => Type::Float,
+        _ => Type::String
+    }
+}
+
+/// The other json types the values of `t` were given as
+fn relaxed_from(t: Type, stats: &PathStats) -> Vec<&'static str> {
+    let mut from = Vec::new();
+    if stats.bools > 0 && t != Type::Bool {
+        from.push("boolean");
+    }
+    if stats.numbers > 0 && !matches!(t, Type::Int | Type::Float) {
+        from.push("number");
+    }
+    if stats.strings > 0 && t != Type::String {
+        from.push("string");
+    }
+    from
+}
+
+/// The profile of sample documents, see the [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct Inference {
+    profile: Profile,
+}
+
+/// Infer the types of `samples`
+pub fn infer<'a>(samples: impl IntoIterator<Item = &'a Value>) -> Inference {
+    Inference { profile: profile::profile(samples) }
+}
+
+impl Inference {
+
+    /// The profile the types are inferred from
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    fn stats(&self, path: &str) -> PathStats {
+        self.profile.get(path).cloned().unwrap_or_default()
+    }
+
+    /// Members of the objects at `path` with their pointer, in lexical order
+    fn members(&self, path: &str) -> Vec<(String, String)> {
+        let prefix = format!("{}/", path);
+        self.profile.paths()
+            .filter_map(|(p, _)| p.strip_prefix(&prefix).map(|rest| (p, rest)))
+            .filter(|(_, rest)| !rest.contains('/') && *rest != "*")
+            .map(|(p, rest)| (s!(p), rest.replace("~1", "/").replace("~0", "~")))
+            .collect()
+    }
+
+    /// A schema document accepting all samples
+    pub fn schema(&self) -> Value {
+        self.schema_at("")
+    }
+
+    fn schema_at(&self, path: &str) -> Value {
+        let stats = self.stats(path);
+        let t = classify(&stats);
+        let schema = match t {
+            Type::Object => {
+                let mut required = Vec::new();
+                let mut optional = Vec::new();
+                for (pointer, key) in self.members(path) {
+                    let member = self.stats(&pointer);
+                    match member.count < stats.objects || member.nulls > 0 {
+                        true => optional.push((key, self.schema_at(&pointer))),
+                        false => required.push((key, self.schema_at(&pointer)))
+                    }
+                }
+                let required: Vec<(&str, Value)> = required.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+                let optional: Vec<(&str, Value)> = optional.iter().map(|(k, v)| (k.as_str(), v.clone())).collect();
+                schema::object(&required, &optional)
+            },
+            Type::Array => json!({"type": "array", "items": self.schema_at(&format!("{}/*", path))}),
+            Type::Any => json!({}),
+            Type::Null => return json!({"type": "null"}),
+            Type::Bool | Type::Int | Type::Float | Type::String => {
+                let name = match t {
+                    Type::Bool => "boolean",
+                    Type::Int => "integer",
+                    Type::Float => "number",
+                    _ => "string"
+                };
+                match relaxed_from(t, &stats).as_slice() {
+                    [] => json!({"type": name}),
+                    from => schema::relaxed(name, from)
+                }
+            }
+        };
+        match stats.nulls > 0 && t != Type::Any {
+            true => json!({"anyOf": [schema, {"type": "null"}]}),
+            false => schema
+        }
+    }
+
+    /// Rust structs for the samples, the root named `name`, with `TryFromJson`
+    /// implementations to start a model from
+    pub fn rust(&self, name: &str) -> String {
+        let mut out = String::from("use json_relaxed::{FromJsonError, Maybe, MaybeValue, TryFromJson};\nuse serde_json::Value;\n");
+        let mut names = Vec::new();
+        let mut float = false;
+        match classify(&self.stats("")) {
+            Type::Object => self.rust_struct("", name, &mut names, &mut out, &mut float),
+            _ => {
+                let (t, _) = self.rust_type("", "", name, &mut names, &mut out, &mut float);
+                let _ = write!(out, "\npub type {} = {};\n", name, t);
+            }
+        }
+        out.push_str(HELPERS);
+        if float {
+            out.push_str(FLOAT_HELPER);
+        }
+        out
+    }
+
+    fn rust_struct(&self, path: &str, name: &str, names: &mut Vec<String>, out: &mut String, float: &mut bool) {
+        let objects = self.stats(path).objects;
+        let mut fields = Vec::new();
+        let mut nested = String::new();
+        for (pointer, key) in self.members(path) {
+            let stats = self.stats(&pointer);
+            let (t, read) = self.rust_type(&pointer, &key, &type_name(&key), names, &mut nested, float);
+            let optional = stats.count < objects || stats.nulls > 0;
+            let (t, read) = match optional {
+                true => (format!("Option<{}>", t), format!("optional({})?", read)),
+                false => (t, format!("required({}, {:?})?", read, key))
+            };
+            let note = match relaxed_from(classify(&stats), &stats).as_slice() {
+                [] => String::new(),
+                from => format!(" // also given as {}", from.join(", "))
+            };
+            fields.push((field_name(&key), t, read, note));
+        }
+
+        let _ = write!(out, "\n#[derive(Debug, Clone)]\npub struct {} {{\n", name);
+        for (field, t, _, note) in &fields {
+            let _ = writeln!(out, "    pub {}: {},{}", field, t, note);
+        }
+        let _ = write!(out, "}}\n\nimpl TryFromJson for {} {{\n    fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {{\n        Ok({} {{\n", name, name);
+        for (field, _, read, _) in &fields {
+            let _ = writeln!(out, "            {}: {},", field, read);
+        }
+        out.push_str("        })\n    }\n}\n");
+        out.push_str(&nested);
+    }
+
+    /// The type at `path` and the read of member `key`, nested structs are written to `out`
+    fn rust_type(&self, path: &str, key: &str, hint: &str, names: &mut Vec<String>, out: &mut String, float: &mut bool) -> (String, String) {
+        let mut unique = |hint: &str| {
+            let mut name = s!(hint);
+            let mut n = 1;
+            while names.contains(&name) {
+                n += 1;
+                name = format!("{}{}", hint, n);
+            }
+            names.push(name.clone());
+            name
+        };
+        match classify(&self.stats(path)) {
+            Type::Bool => (s!("bool"), format!("value.maybe_bool({:?})", key)),
+            Type::Int => (s!("i64"), format!("value.maybe_int({:?})", key)),
+            Type::Float => {
+                *float = true;
+                (s!("f64"), format!("float(value.maybe_decimal({:?}))", key))
+            },
+            Type::String => (s!("String"), format!("value.maybe_string({:?})", key)),
+            Type::Object => {
+                let name = unique(hint);
+                self.rust_struct(path, &name, names, out, float);
+                (name.clone(), format!("value.maybe_object::<{}, _>({:?})", name, key))
+            },
+            Type::Array => {
+                let items = format!("{}/*", path);
+                match classify(&self.stats(&items)) {
+                    Type::Object => {
+                        let name = unique(hint.strip_suffix('s').filter(|n| !n.is_empty()).unwrap_or(hint));
+                        self.rust_struct(&items, &name, names, out, float);
+                        (format!("Vec<{}>", name), format!("value.maybe_array::<{}, _>({:?})", name, key))
+                    },
+                    _ => (s!("Vec<Value>"), format!("value.maybe_array::<Value, _>({:?})", key))
+                }
+            },
+            Type::Any | Type::Null => (s!("Value"), format!("value.maybe_object::<Value, _>({:?})", key))
+        }
+    }
+}
+
+const HELPERS: &str = "
+fn required<T>(maybe: Maybe<T>, key: &str) -> Result<T, FromJsonError> {
+    match maybe {
+        Maybe::Strict(v) | Maybe::Relaxed(v) => Ok(v),
+        Maybe::Null => Err(FromJsonError::with_message(&format!(\"missing field `{}`\", key))),
+        Maybe::Error(e) => Err(e)
+    }
+}
+
+fn optional<T>(maybe: Maybe<T>) -> Result<Option<T>, FromJsonError> {
+    match maybe {
+        Maybe::Strict(v) | Maybe::Relaxed(v) => Ok(Some(v)),
+        Maybe::Null => Ok(None),
+        Maybe::Error(e) => Err(e)
+    }
+}
+";
+
+const FLOAT_HELPER: &str = "
+fn float(decimal: Maybe<String>) -> Maybe<f64> {
+    match decimal {
+        Maybe::Strict(d) => d.parse().map_or_else(|_| Maybe::Error(FromJsonError::with_message(&d)), Maybe::Strict),
+        Maybe::Relaxed(d) => d.parse().map_or_else(|_| Maybe::Error(FromJsonError::with_message(&d)), Maybe::Relaxed),
+        Maybe::Null => Maybe::Null,
+        Maybe::Error(e) => Maybe::Error(e)
+    }
+}
+";
+
+/// `server_config` and `server-config` become `ServerConfig`
+fn type_name(key: &str) -> String {
+    let name: String = key.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect();
+    match name.chars().next() {
+        Some(c) if c.is_alphabetic() => name,
+        _ => format!("T{}", name)
+    }
+}
+
+/// `serverPort` and `server-port` become `server_port`, keywords are raw identifiers
+fn field_name(key: &str) -> String {
+    let mut name = String::new();
+    let mut previous_lower = false;
+    for c in key.chars() {
+        match c {
+            c if c.is_uppercase() => {
+                if previous_lower {
+                    name.push('_');
+                }
+                name.extend(c.to_lowercase());
+                previous_lower = false;
+            },
+            c if c.is_alphanumeric() => {
+                name.push(c);
+                previous_lower = true;
+            },
+            _ => {
+                if !name.is_empty() && !name.ends_with('_') {
+                    name.push('_');
+                }
+                previous_lower = false;
+            }
+        }
+    }
+    let name = s!(name.trim_end_matches('_'));
+    match name.as_str() {
+        "" => s!("field"),
+        n if n.starts_with(|c: char| c.is_numeric()) => format!("_{}", n),
+        "as" | "async" | "await" | "break" | "const" | "continue" | "crate" | "dyn" | "else" | "enum" | "extern" | "fn" | "for"
+        | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "static"
+        | "struct" | "trait" | "type" | "unsafe" | "use" | "where" | "while" | "yield" => format!("r#{}", name),
+        "self" | "Self" | "super" => format!("{}_", name),
+        _ => name
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    #[test]
+    fn inferred_models() {
+        let samples = [
+            json!({"id": 1, "active": "yes", "ratio": 0.5, "server": {"host": "a", "port": 80}, "items": [{"sku": "x", "qty": 1}], "type": null}),
+            json!({"id": "2", "active": true, "ratio": "1", "server": {"host": "b", "port": "81"}, "items": [], "tags": ["a"], "type": "t"}),
+        ];
+        let inferred = super::infer(&samples);
+
+        let code = inferred.rust("Order");
+        for line in [
+            "pub struct Order {", "pub active: bool, // also given as string", "pub id: i64, // also given as string",
+            "pub items: Vec<Item>,", "pub ratio: f64,", "pub server: Server,", "pub tags: Option<Vec<Value>>,",
+            "pub r#type: Option<String>,", "pub struct Item {", "pub qty: i64,", "pub struct Server {",
+            "id: required(value.maybe_int(\"id\"), \"id\")?,", "r#type: optional(value.maybe_string(\"type\"))?,",
+            "items: required(value.maybe_array::<Item, _>(\"items\"), \"items\")?,", "fn float(",
+        ] {
+            assert!(code.contains(line), "missing `{}` in\n{}", line, code);
+        }
+
+        let schema = inferred.schema();
+        assert_eq!(schema["required"], json!(["active", "id", "items", "ratio", "server"]));
+        assert_eq!(schema["properties"]["id"], json!({"type": "integer", "x-relaxed": ["string"]}));
+        assert_eq!(schema["properties"]["type"], json!({"anyOf": [{"type": "string"}, {"type": "null"}]}));
+        assert_eq!(schema["properties"]["items"]["items"]["required"], json!(["qty", "sku"]));
+        assert!(crate::schema::validate(&samples[1], &schema).is_valid());
+
+        assert_eq!((super::field_name("serverPort"), super::field_name("x-y"), super::type_name("line_items")), (s!("server_port"), s!("x_y"), s!("LineItems")));
+    }
+}
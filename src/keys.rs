@@ -0,0 +1,179 @@
+//! Reading the same members from many objects
+//!
+//! A [`KeySet`] is built once from the member names a record type needs.
+//! [`KeySet::select`] then finds all of them in one pass over an object, merging
+//! the sorted member names with the sorted key set instead of looking up every
+//! key on its own. The selected [`Fields`] are read with the usual accessors,
+//! by slot number or by name:
+//!
+//! ```
+//! use json_relaxed::keys::KeySet;
+//! use json_relaxed::MaybeValue;
+//! use serde_json::json;
+//!
+//! const ID: usize = 0;
+//! const NAME: usize = 1;
+//! let keys = KeySet::new(&["id", "name"]);
+//!
+//! for record in [json!({"id": "1", "name": "Ada"}), json!({"id": 2})] {
+//!     let fields = keys.select(&record);
+//!     assert!(fields.maybe_int(ID).relaxed() > 0);
+//!     fields.maybe_string(NAME).relaxed();
+//! }
+//! ```
+
+use serde_json::{Map, Value};
+
+use crate::backend::{JsonLike, Kind};
+
+/// Member names resolved once, see the [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct KeySet {
+    /// Names in sorted order with their slot
+    sorted: Vec<(String, usize)>,
+    len: usize,
+}
+
+impl KeySet {
+
+    /// Slots are given in the order of `keys`, a repeated key keeps its first slot
+    pub fn new(keys: &[&str]) -> Self {
+        let mut sorted: Vec<(String, usize)> = keys.iter().enumerate().map(|(i, k)| (s!(*k), i)).collect();
+        sorted.sort();
+        sorted.dedup_by(|a, b| a.0 == b.0);
+        KeySet { sorted, len: keys.len() }
+    }
+
+    /// Number of slots
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Slot of member `key`
+    pub fn slot(&self, key: &str) -> Option<usize> {
+        self.sorted.binary_search_by(|(k, _)| k.as_str().cmp(key)).ok().map(|i| self.sorted[i].1)
+    }
+
+    /// The members of `value` for all slots, empty for other types than objects
+    pub fn select<'a>(&'a self, value: &'a Value) -> Fields<'a> {
+        let mut values = vec!(None; self.len);
+        if let Value::Object(map) = value {
+            self.merge(map, &mut values);
+        }
+        Fields { keys: self, values }
+    }
+
+    /// serde_json's default map iterates in sorted order
+    #[cfg(not(feature = "preserve_order"))]
+    fn merge<'a>(&self, map: &'a Map<String, Value>, values: &mut [Option<&'a Value>]) {
+        let mut wanted = self.sorted.iter().peekable();
+        for (name, value) in map {
+            while let Some((key, slot)) = wanted.peek() {
+                match key.as_str().cmp(name) {
+                    std::cmp::Ordering::Less => {
+                        wanted.next();
+                    },
+                    std::cmp::Ordering::Equal => {
+                        values[*slot] = Some(value);
+                        wanted.next();
+                        break;
+                    },
+                    std::cmp::Ordering::Greater => break
+                }
+            }
+            if wanted.peek().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Members in insertion order can not be merged, they are hashed instead
+    #[cfg(feature = "preserve_order")]
+    fn merge<'a>(&self, map: &'a Map<String, Value>, values: &mut [Option<&'a Value>]) {
+        for (key, slot) in &self.sorted {
+            values[*slot] = map.get(key);
+        }
+    }
+}
+
+/// The members of one object selected by a [`KeySet`]
+///
+/// As a [`JsonLike`] it is an object that can also be indexed by slot.
+#[derive(Debug, Clone)]
+pub struct Fields<'a> {
+    keys: &'a KeySet,
+    values: Vec<Option<&'a Value>>,
+}
+
+impl <'a> Fields<'a> {
+
+    /// The member in `slot`
+    pub fn get(&self, slot: usize) -> Option<&'a Value> {
+        self.values.get(slot).copied().flatten()
+    }
+}
+
+impl <'a> JsonLike for Fields<'a> {
+    type Node = Value;
+
+    fn kind(&self) -> Kind {
+        Kind::Object
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Value> {
+        self.get(self.keys.slot(key)?)
+    }
+
+    fn get_index(&self, index: usize) -> Option<&Value> {
+        self.get(index)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        None
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        None
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        None
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        None
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::KeySet;
+    use crate::MaybeValue;
+
+    #[test]
+    fn selected_fields() {
+        let keys = KeySet::new(&["port", "host", "zone", "port", "debug"]);
+        assert_eq!((keys.len(), keys.slot("zone"), keys.slot("port"), keys.slot("other")), (5, Some(2), Some(0), None));
+
+        let record = json!({"a": 1, "debug": "yes", "host": "localhost", "port": "8080", "x": {"zone": 3}});
+        let fields = keys.select(&record);
+        assert_eq!(fields.maybe_int(0).relaxed(), 8080);
+        assert_eq!(fields.maybe_str("host").strict().as_deref(), Some("localhost"));
+        assert!(fields.maybe_bool(4).relaxed());
+        assert!(fields.get(2).is_none());
+        assert!(fields.get(3).is_none());
+
+        assert!(keys.select(&json!([1, 2])).get(0).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+//! Deferred extraction for expensive coercions
+//!
+//! A [`Lazy`], from [`maybe_lazy`](crate::MaybeValue::maybe_lazy) or wrapping any
+//! accessor with [`Lazy::new`], runs the conversion only when it is forced,
+//! so fields that end up unused, like a big array, an embedded json document
+//! or a parsed timestamp, cost nothing:
+//!
+//! ```
+//! use json_relaxed::{lazy, MaybeValue};
+//! use serde_json::json;
+//!
+//! let event = json!({"kind": "ping", "payload": "{\"id\": 7}"});
+//! let payload = lazy::embedded(&event, "payload");
+//! if event.maybe_string("kind").relaxed() != "ping" {
+//!     payload.force();
+//! }
+//! ```
+
+use serde_json::Value;
+
+use crate::backend::Key;
+use crate::{observe, read, Maybe};
+
+/// A [`Maybe`] computed on first use, see the [module documentation](self)
+pub struct Lazy<'a, T> {
+    eval: Box<dyn FnOnce() -> Maybe<T> + 'a>,
+}
+
+impl <'a, T: 'a> Lazy<'a, T> {
+
+    pub fn new(eval: impl FnOnce() -> Maybe<T> + 'a) -> Self {
+        Lazy { eval: Box::new(eval) }
+    }
+
+    /// Run the conversion
+    pub fn force(self) -> Maybe<T> {
+        (self.eval)()
+    }
+
+    /// Convert the value once it is forced, the outcome is kept
+    pub fn map<U: 'a>(self, f: impl FnOnce(T) -> U + 'a) -> Lazy<'a, U> {
+        Lazy::new(move || match self.force() {
+            Maybe::Strict(v) => Maybe::Strict(f(v)),
+            Maybe::Relaxed(v) => Maybe::Relaxed(f(v)),
+            Maybe::Error(e) => Maybe::Error(e),
+            Maybe::Null => Maybe::Null
+        })
+    }
+
+    pub fn strict(self) -> Option<T> {
+        self.force().strict()
+    }
+
+    pub fn relaxed(self) -> T
+        where T: Default
+    {
+        self.force().relaxed()
+    }
+
+    pub fn default(self, dflt: impl Into<T>) -> T {
+        self.force().default(dflt)
+    }
+}
+
+/// Member `key` of `value` as json embedded in a string, which is `Relaxed`.
+/// Other values are taken as they are.
+pub fn embedded<'a, I: Key + 'a>(value: &'a Value, key: I) -> Lazy<'a, Value> {
+    Lazy::new(move || {
+        let node = key.lookup(value);
+        let maybe = match node {
+            None | Some(Value::Null) => Maybe::Null,
+            Some(Value::String(s)) => match read::from_str(s) {
+                Ok(v) => Maybe::Relaxed(v),
+                Err(e) => Maybe::Error(e)
+            },
+            Some(v) => Maybe::Strict(v.clone())
+        };
+        observe::outcome(&key, node, maybe)
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use std::cell::Cell;
+
+    use super::Lazy;
+    use crate::{FromJsonError, Maybe, MaybeValue, TryFromJson};
+
+    thread_local! {
+        static CONVERSIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    struct Counted;
+
+    impl TryFromJson for Counted {
+        fn try_from_json(_: &Value) -> Result<Self, FromJsonError> {
+            CONVERSIONS.with(|c| c.set(c.get() + 1));
+            Ok(Counted)
+        }
+    }
+
+    #[test]
+    fn deferred_conversion() {
+        let json = json!({"items": [1, 2, 3], "doc": "{\"a\": [1]}", "bad": "{", "raw": {"a": 1}});
+
+        let items = Lazy::new(|| json.maybe_array::<Counted, _>("items"));
+        let unused = json.maybe_lazy::<Counted, _>("items");
+        assert_eq!(CONVERSIONS.with(Cell::get), 0);
+        assert_eq!(items.map(|v| v.len()).relaxed(), 3);
+        drop(unused);
+        assert_eq!(CONVERSIONS.with(Cell::get), 3);
+
+        assert!(matches!(super::embedded(&json, "doc").force(), Maybe::Relaxed(v) if v == json!({"a": [1]})));
+        assert!(matches!(super::embedded(&json, "raw").force(), Maybe::Strict(_)));
+        assert!(matches!(super::embedded(&json, "bad").force(), Maybe::Error(_)));
+        assert!(super::embedded(&json, "missing").strict().is_none());
+    }
+}
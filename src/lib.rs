@@ -2,8 +2,12 @@
 
 
 
-use serde_json::{json, Value, value::Index};
-use std::{error::Error, fmt};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::{error::Error, fmt, io};
+
+use backend::{JsonLike, Key, Kind};
+use lazy::Lazy;
 
 macro_rules! s {
     // use s! instead of String::from
@@ -12,23 +16,112 @@ macro_rules! s {
     };
 }
 
+#[cfg(feature = "actix")]
+pub mod actix;
+pub mod array;
+#[cfg(feature = "axum")]
+pub mod axum;
+pub mod backend;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+pub mod binary;
+pub mod coalesce;
+mod coerce;
+pub mod compare;
+pub mod config;
+pub mod constrain;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod de;
+pub mod diagnostic;
+pub mod edit;
+pub mod env;
+pub mod envelope;
+pub mod extract;
+#[cfg(feature = "figment")]
+pub mod figment;
+pub mod flatten;
+pub mod hash;
+pub mod infer;
+pub mod keys;
+pub mod lazy;
+pub mod limits;
+pub mod merge;
+pub mod metrics;
+pub mod migrate;
+pub mod normalize;
+mod observe;
+pub mod owned;
+pub mod patch;
+mod pattern;
+pub mod profile;
+pub mod query;
+pub mod read;
+pub mod redact;
+pub mod refs;
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
+pub mod sanitize;
+pub mod schema;
+pub mod secrets;
+pub mod ser;
+pub mod session;
+pub mod strip;
+pub mod substitute;
+pub mod table;
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "toml")]
+pub mod toml;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(any(feature = "axum", feature = "actix"))]
+mod web;
+pub mod words;
+pub mod write;
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+#[cfg(feature = "tokio")]
+pub mod async_read;
+
 #[derive(Debug)]
 pub struct FromJsonError {
-    msg: String
+    msg: String,
+    position: Option<(usize, usize)>
 }
 
 
 impl FromJsonError {
     pub fn with_message(message: &str) -> Self {
         FromJsonError {
-            msg: String::from(message)
+            msg: String::from(message),
+            position: None
         }
     }
-    fn unexpected() -> Self {
+    pub(crate) fn unexpected() -> Self {
         FromJsonError {
-            msg: String::from("unexpected error")
+            msg: String::from("unexpected error"),
+            position: None
         }
     }
+
+    /// Attach the (1-based) line and column in the source text
+    pub fn with_position(mut self, line: usize, column: usize) -> Self {
+        self.position = Some((line, column));
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    /// Line and column in the source text, if the error comes from parsing
+    pub fn position(&self) -> Option<(usize, usize)> {
+        self.position
+    }
 }
 
 impl Error for FromJsonError {
@@ -41,23 +134,126 @@ impl Error for FromJsonError {
 
 impl fmt::Display for FromJsonError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self.msg)
+        write!(f, "{:?}", self.msg)?;
+        match self.position {
+            Some((line, column)) => write!(f, " at line {} column {}", line, column),
+            None => Ok(())
+        }
+    }
+}
+
+impl From<serde_json::Error> for FromJsonError {
+    fn from(e: serde_json::Error) -> Self {
+        let msg = e.to_string();
+        match e.line() {
+            0 => FromJsonError::with_message(&msg),
+            line => {
+                // serde_json appends the position to its message, keep it separately
+                let suffix = format!(" at line {} column {}", line, e.column());
+                let msg = msg.strip_suffix(&suffix).unwrap_or(&msg);
+                FromJsonError::with_message(msg).with_position(line, e.column())
+            }
+        }
+    }
+}
+
+impl From<io::Error> for FromJsonError {
+    fn from(e: io::Error) -> Self {
+        FromJsonError::with_message(&e.to_string())
+    }
+}
+
+
+/// Conversion from a node of a value tree, `serde_json::Value` unless given otherwise
+pub trait TryFromJson<J: ?Sized = Value>: Sized {
+    fn try_from_json(value: &J) -> Result<Self,FromJsonError>;
+}
+
+/// The node itself, cloned
+impl TryFromJson for Value {
+    fn try_from_json(value: &Value) -> Result<Self,FromJsonError> {
+        Ok(value.clone())
     }
 }
 
+/// Conversion that may borrow strings and subtrees from the node, for read-only
+/// passes over large documents
+pub trait TryFromJsonRef<'a, J: ?Sized = Value>: Sized {
+    fn try_from_json_ref(value: &'a J) -> Result<Self,FromJsonError>;
+}
+
+/// A [`Maybe`] holding a reference into the source value
+pub type MaybeRef<'a, T> = Maybe<&'a T>;
 
-pub trait TryFromJson: Sized {
-    fn try_from_json(value: &Value) -> Result<Self,FromJsonError>;
+impl <'a, J: JsonLike + ?Sized> TryFromJsonRef<'a, J> for &'a J {
+    fn try_from_json_ref(value: &'a J) -> Result<Self,FromJsonError> {
+        Ok(value)
+    }
 }
 
-pub trait MaybeValue {
+/// Strings only, borrowing cannot convert other types
+impl <'a, J: JsonLike + ?Sized> TryFromJsonRef<'a, J> for &'a str {
+    fn try_from_json_ref(value: &'a J) -> Result<Self,FromJsonError> {
+        match value.as_str() {
+            Some(s) => Ok(s),
+            None => Err(coerce::mismatch(value))
+        }
+    }
+}
 
-    fn maybe_bool<I: Index>(&self, key: I) -> Maybe<bool>;
-    fn maybe_int<I: Index>(&self, key: I) -> Maybe<i64>;
-    fn maybe_uint<I: Index>(&self, key: I) -> Maybe<u64>;
-    fn maybe_string<I: Index>(&self, key: I) -> Maybe<String>;
-    fn maybe_array<T: TryFromJson, I: Index>(&self, key: I) -> Maybe<Vec<T>>;
-    fn maybe_object<T: TryFromJson, I: Index>(&self, key: I) -> Maybe<T>;
+impl <'a, J: JsonLike + ?Sized> TryFromJsonRef<'a, J> for Cow<'a, str> {
+    fn try_from_json_ref(value: &'a J) -> Result<Self,FromJsonError> {
+        match coerce::to_str(Some(value)) {
+            Maybe::Strict(s) | Maybe::Relaxed(s) => Ok(s),
+            Maybe::Error(e) => Err(e),
+            Maybe::Null => Err(coerce::mismatch(value))
+        }
+    }
+}
+
+impl <'a, J: JsonLike + ?Sized, T: TryFromJsonRef<'a, J>> TryFromJsonRef<'a, J> for Option<T> {
+    fn try_from_json_ref(value: &'a J) -> Result<Self,FromJsonError> {
+        match value.kind() {
+            Kind::Null => Ok(None),
+            _ => T::try_from_json_ref(value).map(Some)
+        }
+    }
+}
+
+/// Relaxed access to members and elements, implemented for every [`JsonLike`]
+pub trait MaybeValue: JsonLike {
+
+    fn maybe_bool<I: Key>(&self, key: I) -> Maybe<bool>;
+    /// Like `maybe_bool`, but a missing or null value is `Strict(None)`, so
+    /// unset stays apart from false when defaulting
+    fn maybe_bool_opt<I: Key>(&self, key: I) -> Maybe<Option<bool>>;
+    fn maybe_int<I: Key>(&self, key: I) -> Maybe<i64>;
+    fn maybe_uint<I: Key>(&self, key: I) -> Maybe<u64>;
+    fn maybe_string<I: Key>(&self, key: I) -> Maybe<String>;
+    /// Like `maybe_string`, but strings are borrowed instead of cloned
+    fn maybe_str<I: Key>(&self, key: I) -> Maybe<Cow<'_, str>>;
+    /// The exact digits of a number, with serde_json's arbitrary_precision even
+    /// beyond the range of f64
+    fn maybe_decimal<I: Key>(&self, key: I) -> Maybe<String>;
+    fn maybe_bigint<I: Key>(&self, key: I) -> Maybe<i128>;
+    /// A (lat, lon) pair, strict from `{"lat", "lon"}` or a GeoJSON `[lon, lat]`,
+    /// relaxed from `lng` and similar names, numbers as strings or `"52.1,13.4"`.
+    /// Coordinates out of range are an error.
+    fn maybe_geo<I: Key>(&self, key: I) -> Maybe<(f64, f64)>;
+    fn maybe_array<T: TryFromJson<Self::Node>, I: Key>(&self, key: I) -> Maybe<Vec<T>>;
+    fn maybe_array_with<T: TryFromJson<Self::Node>, I: Key>(&self, key: I, options: &array::Options) -> Maybe<Vec<T>>;
+    /// Like `maybe_array_with`, reading the elements in parallel on the rayon thread pool.
+    /// With `fail_fast` the error lists every failing element in order.
+    #[cfg(feature = "rayon")]
+    fn maybe_array_par<T: TryFromJson<Self::Node> + Send, I: Key>(&self, key: I, options: &array::Options) -> Maybe<Vec<T>>
+        where Self::Node: Sync;
+    fn maybe_object<T: TryFromJson<Self::Node>, I: Key>(&self, key: I) -> Maybe<T>;
+    /// Like `maybe_object`, converting only when the result is forced
+    fn maybe_lazy<'a, T: TryFromJson<Self::Node> + 'a, I: Key + 'a>(&'a self, key: I) -> Lazy<'a, T>;
+    /// The node itself without copying, `Null` if it is missing or null
+    fn maybe_node<I: Key>(&self, key: I) -> MaybeRef<'_, Self::Node>;
+    /// Like `maybe_object`, but the result may borrow from `self`
+    fn maybe_object_ref<'a, T: TryFromJsonRef<'a, Self::Node>, I: Key>(&'a self, key: I) -> Maybe<T>;
 
 }
 /// The result of getting a typed value from a json array or object
@@ -94,6 +290,7 @@ impl <T> Maybe<T>
     pub fn relaxed(self) -> T 
         where T: Default
     {
+        observe::defaulted(&self, false);
         match self {
             Maybe::Null => Default::default(),
             Maybe::Error(_) => Default::default(),
@@ -103,6 +300,7 @@ impl <T> Maybe<T>
     }
 
     pub fn default(self, dflt: impl Into<T>) -> T {
+        observe::defaulted(&self, true);
         match self {
             Maybe::Null => dflt.into(),
             Maybe::Error(_) => dflt.into(),
@@ -112,6 +310,9 @@ impl <T> Maybe<T>
     }
 
     pub fn default_for_null(self, dflt: impl Into<T>) -> Option<T> {
+        if let Maybe::Null = self {
+            observe::defaulted(&self, true);
+        }
         match self {
             Maybe::Strict(v) => Some(v),
             Maybe::Null => Some(dflt.into()),
@@ -126,146 +327,113 @@ impl <T> Maybe<T>
 
 
 
-impl MaybeValue for Value {
-
+impl <J: JsonLike + ?Sized> MaybeValue for J {
 
+    fn maybe_object<T: TryFromJson<J::Node>, I: Key>(&self, key: I) -> Maybe<T> {
+        let value = key.lookup(self);
+        let maybe = {
+            let _path = session::enter(&key);
+            coerce::to_object(value)
+        };
+        observe::outcome(&key, value, maybe)
+    }
 
-    fn maybe_object<T: TryFromJson, I: Index>(&self, key: I) -> Maybe<T> {
+    fn maybe_lazy<'a, T: TryFromJson<J::Node> + 'a, I: Key + 'a>(&'a self, key: I) -> Lazy<'a, T> {
+        Lazy::new(move || self.maybe_object(key))
+    }
 
-        match self.get(key) {
+    fn maybe_node<I: Key>(&self, key: I) -> MaybeRef<'_, J::Node> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_node(value))
+    }
 
-            Some(v) => {
-                let t: Result<T,_> = T::try_from_json(v);
-                match t {
-                    Ok(tv) => Maybe::Strict(tv),
-                    Err(e) => Maybe::Error(e)
-                }
-            },
+    fn maybe_object_ref<'a, T: TryFromJsonRef<'a, J::Node>, I: Key>(&'a self, key: I) -> Maybe<T> {
+        let value = key.lookup(self);
+        let maybe = {
+            let _path = session::enter(&key);
+            coerce::to_object_ref(value)
+        };
+        observe::outcome(&key, value, maybe)
+    }
 
-            None => Maybe::Null
+    fn maybe_array<T: TryFromJson<J::Node>, I: Key>(&self, key: I) -> Maybe<Vec<T>> {
+        let value = key.lookup(self);
+        let maybe = {
+            let _path = session::enter(&key);
+            coerce::to_array(value)
+        };
+        observe::outcome(&key, value, maybe)
+    }
 
-        }
+    fn maybe_array_with<T: TryFromJson<J::Node>, I: Key>(&self, key: I, options: &array::Options) -> Maybe<Vec<T>> {
+        let value = key.lookup(self);
+        let maybe = {
+            let _path = session::enter(&key);
+            coerce::to_array_with(value, options)
+        };
+        observe::outcome(&key, value, maybe)
     }
 
+    #[cfg(feature = "rayon")]
+    fn maybe_array_par<T: TryFromJson<J::Node> + Send, I: Key>(&self, key: I, options: &array::Options) -> Maybe<Vec<T>>
+        where J::Node: Sync
+    {
+        let value = key.lookup(self);
+        let maybe = {
+            let _path = session::enter(&key);
+            coerce::to_array_par(value, options)
+        };
+        observe::outcome(&key, value, maybe)
+    }
 
-    fn maybe_array<T: TryFromJson, I: Index>(&self, key: I) -> Maybe<Vec<T>> {
-        
-        match self.get(key) {
-            Some(Value::Array(a)) => {
-
-                // Maybe::Null
-                let mut collect: Vec<T> = Vec::new();
-                let mut clean = true;
-                for i in a.iter().map(|i| T::try_from_json(i)) {
-                    match i {
-                        Ok(v) => collect.push(v),
-                        Err(_) => {
-                            clean = false;
-                        }
-                    }
-                }
-
-                match clean {
-                    true => Maybe::Strict(collect),
-                    false => Maybe::Relaxed(collect)
-                }
-
-            },
-            Some(v) => {
-                match T::try_from_json(v) {
-                    Ok(t) => Maybe::Relaxed(vec!(t)),
-                    Err(e) => Maybe::Error(e)
-                }
-            },
-            None => {
-                Maybe::Null
-            }
-        }
+    fn maybe_string<I: Key>(&self, key: I) -> Maybe<String> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_string(value))
+    }
 
+    fn maybe_str<I: Key>(&self, key: I) -> Maybe<Cow<'_, str>> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_str(value))
+    }
 
+    fn maybe_decimal<I: Key>(&self, key: I) -> Maybe<String> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_decimal(value))
+    }
 
+    fn maybe_bigint<I: Key>(&self, key: I) -> Maybe<i128> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_bigint(value))
     }
 
-    fn maybe_string<I: Index>(&self, key: I) -> Maybe<String> {
+    fn maybe_geo<I: Key>(&self, key: I) -> Maybe<(f64, f64)> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_geo(value))
+    }
 
+    fn maybe_bool<I: Key>(&self, key: I) -> Maybe<bool> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_bool(value))
+    }
 
-        match self.get(key) {
-            Some(Value::Null) => Maybe::Null,
-            Some(Value::Bool(b)) => Maybe::Relaxed(b.to_string()),
-            Some(Value::Number(n)) => Maybe::Relaxed(n.to_string()),
-            Some(Value::String(s)) => Maybe::Strict(s!(s)),
-            Some(Value::Array(_)) => Maybe::Error(FromJsonError::with_message("type mismatch: array")),
-            Some(Value::Object(_)) => Maybe::Error(FromJsonError::with_message("type mismatch: object")),
-            None => Maybe::Null
-        }
-    
-    
-    }
-    fn maybe_bool<I: Index>(&self, key: I) -> Maybe<bool> {
-        match self.get(key) {
-            Some(Value::Null) => Maybe::Null,
-            Some(Value::Bool(b)) => Maybe::Strict(*b),
-            Some(Value::Number(n)) => {
-                if n.is_i64() {
-                    Maybe::Relaxed(n.as_i64().expect("checked above") != 0)
-                } else if  n.is_u64() {
-                    Maybe::Relaxed(n.as_u64().expect("checked above") > 0)
-                } else if n.is_f64() {
-                    Maybe::Relaxed(n.as_f64().expect("checked above") != 0.0)
-                } else {
-                    Maybe::Error(FromJsonError::unexpected())
-                }
-            },
-            Some(Value::String(s)) => {
-                Maybe::Relaxed( s != "" && s != "0" && s.to_lowercase() != "false" )
-            },
-            Some(Value::Array(_)) => Maybe::Error(FromJsonError::with_message("type mismatch: array")),
-            Some(Value::Object(_)) => Maybe::Error(FromJsonError::with_message("type mismatch: object")),
-            None => Maybe::Null
+    fn maybe_bool_opt<I: Key>(&self, key: I) -> Maybe<Option<bool>> {
+        let value = key.lookup(self);
+        match observe::outcome(&key, value, coerce::to_bool(value)) {
+            Maybe::Null => Maybe::Strict(None),
+            Maybe::Strict(b) => Maybe::Strict(Some(b)),
+            Maybe::Relaxed(b) => Maybe::Relaxed(Some(b)),
+            Maybe::Error(e) => Maybe::Error(e)
         }
     }
 
-
-    fn maybe_uint<I: Index>(&self, key: I) -> Maybe<u64> {
-        match self.maybe_int(key) {
-            Maybe::Strict(n) => Maybe::Strict(n as u64),
-            Maybe::Relaxed(n) => Maybe::Relaxed(n as u64),
-            Maybe::Error(e) => Maybe::Error(e),
-            Maybe::Null => Maybe::Null,
-        }
+    fn maybe_uint<I: Key>(&self, key: I) -> Maybe<u64> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_uint(value))
     }
 
-    fn maybe_int<I: Index>(&self, key: I) -> Maybe<i64> {
-        match self.get(key) {
-            Some(Value::Null) => Maybe::Null,
-            Some(Value::Bool(b)) => {
-                match b {
-                    true  => Maybe::Relaxed(1),
-                    false => Maybe::Relaxed(0)
-                }
-            },
-            Some(Value::Number(n)) => {
-                if n.is_i64() {
-                    Maybe::Strict(n.as_i64().expect("checked above"))
-                } else if  n.is_u64() {
-                    Maybe::Strict(n.as_u64().expect("checked above") as i64)
-                } else if n.is_f64() {
-                    Maybe::Relaxed(n.as_f64().expect("checked above") as i64)
-                } else {
-                    Maybe::Error(FromJsonError::unexpected())
-                }
-            },
-            Some(Value::String(s)) => {
-                let n = s.parse::<i64>();
-                match n {
-                    Ok(i) => Maybe::Relaxed(i),
-                    Err(_) => Maybe::Error(FromJsonError::with_message("parseIntError"))
-                }
-            },
-            Some(Value::Array(_)) => Maybe::Error(FromJsonError::with_message("type mismatch: array")),
-            Some(Value::Object(_)) => Maybe::Error(FromJsonError::with_message("type mismatch: object")),
-            None => Maybe::Null
-        }
+    fn maybe_int<I: Key>(&self, key: I) -> Maybe<i64> {
+        let value = key.lookup(self);
+        observe::outcome(&key, value, coerce::to_int(value))
     }
 }
 
@@ -292,8 +460,9 @@ impl MaybeValue for Value {
 #[cfg(test)]
 mod tests {
     use serde_json::json;
+    use std::borrow::Cow;
 
-    use crate::{FromJsonError, MaybeValue};
+    use crate::{FromJsonError, Maybe, MaybeValue};
 
     #[test]
     fn it_works() {
@@ -314,8 +483,105 @@ mod tests {
         assert_eq!(json.maybe_string("foo").relaxed(), "23");
         assert_eq!(json.maybe_int("bar").relaxed(), 42);
         assert_eq!(json.maybe_string("bar").relaxed(), "42");
+
+        assert!(matches!(json.maybe_str("bar"), Maybe::Strict(Cow::Borrowed("42"))));
+        assert!(matches!(json.maybe_str("foo"), Maybe::Relaxed(Cow::Owned(_))));
         Ok(())
     }
 
+    #[test]
+    fn tri_state_bool() {
+        let flags = json!({"beta": "false", "dark": null, "fast": true, "wide": []});
+        assert_eq!(flags.maybe_bool_opt("beta").relaxed(), Some(false));
+        assert_eq!(flags.maybe_bool_opt("dark").relaxed(), None);
+        assert_eq!(flags.maybe_bool_opt("missing").strict(), Some(None));
+        assert_eq!(flags.maybe_bool_opt("fast").strict(), Some(Some(true)));
+        assert!(matches!(flags.maybe_bool_opt("wide"), Maybe::Error(_)));
+    }
+
+    #[test]
+    fn geo_coordinates() {
+        let places = json!({
+            "office": {"lat": 52.5, "lon": 13.4},
+            "shop": {"latitude": "48.1", "lng": 11.6},
+            "pin": [13.4, 52.5, 34.0],
+            "point": {"type": "Point", "coordinates": [-0.1, 51.5]},
+            "typed": "52.5, 13.4",
+            "north": {"lat": 91, "lon": 0},
+            "east": [181, 0],
+            "lake": {"lat": 47.5},
+            "none": null
+        });
+        assert!(matches!(places.maybe_geo("office"), Maybe::Strict((52.5, 13.4))));
+        assert!(matches!(places.maybe_geo("shop"), Maybe::Relaxed((lat, 11.6)) if lat == 48.1));
+        assert!(matches!(places.maybe_geo("pin"), Maybe::Strict((52.5, 13.4))));
+        assert!(matches!(places.maybe_geo("point"), Maybe::Strict((51.5, lon)) if lon == -0.1));
+        assert!(matches!(places.maybe_geo("typed"), Maybe::Relaxed((52.5, 13.4))));
+        for invalid in ["north", "east", "lake"] {
+            assert!(matches!(places.maybe_geo(invalid), Maybe::Error(_)), "{}", invalid);
+        }
+        assert!(matches!(places.maybe_geo("none"), Maybe::Null));
+    }
+
+    #[test]
+    fn borrowed_objects() {
+        use serde_json::Value;
+
+        use crate::TryFromJsonRef;
+
+        struct Summary<'a> {
+            name: &'a str,
+            tags: &'a Value,
+            note: Option<Cow<'a, str>>
+        }
+
+        impl <'a> TryFromJsonRef<'a> for Summary<'a> {
+            fn try_from_json_ref(value: &'a Value) -> Result<Self,FromJsonError> {
+                Ok(Summary {
+                    name: value.maybe_object_ref("name").strict_ok()?,
+                    tags: value.maybe_node("tags").strict_ok()?,
+                    note: value.maybe_object_ref("note").strict_ok()?
+                })
+            }
+        }
+
+        let json = json!({"item": {"name": "crate", "tags": ["a", "b"], "note": null}, "count": 3});
+        let summary: Summary = json.maybe_object_ref("item").strict().unwrap();
+        assert_eq!(summary.name, "crate");
+        assert!(std::ptr::eq(summary.tags, &json["item"]["tags"]));
+        assert!(summary.note.is_none());
+
+        assert!(json.maybe_node("missing").strict().is_none());
+        assert!(matches!(json.maybe_object_ref::<&str, _>("count"), Maybe::Error(_)));
+        assert!(matches!(json.maybe_object_ref::<Cow<str>, _>("count"), Maybe::Strict(Cow::Owned(_))));
+    }
+
+    #[test]
+    fn wide_numbers() {
+        let json = json!({"price": 12.50, "text": " 1e-9 ", "wide": "170141183460469231731687303715884105727", "flag": true});
+
+        assert_eq!(json.maybe_decimal("price").strict(), Some(s!("12.5")));
+        assert_eq!(json.maybe_decimal("text").relaxed(), "1e-9");
+        assert_eq!(json.maybe_decimal("flag").relaxed(), "1");
+        assert_eq!(json.maybe_bigint("wide").relaxed(), i128::MAX);
+        assert_eq!(json.maybe_bigint("price").relaxed(), 12);
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn arbitrary_precision() {
+        let text = "{\"amount\": 123456789012345678901234567890.000000001, \"id\": 1267650600228229401496703205376}";
+        let json = crate::read::from_str(text).unwrap();
+
+        assert_eq!(json.maybe_decimal("amount").strict(), Some(s!("123456789012345678901234567890.000000001")));
+        assert_eq!(json.maybe_string("amount").relaxed(), "123456789012345678901234567890.000000001");
+        assert_eq!(json.maybe_bigint("id").strict(), Some(1 << 100));
+
+        let written = crate::ser::to_value_relaxed(&json).unwrap();
+        assert_eq!(written.to_string(), text.replace(": ", ":").replace(", ", ","));
+        let exact = crate::ser::Options::new().wide_as_number(true);
+        assert_eq!(crate::ser::to_value_with(&(1i128 << 100), &exact).unwrap(), json["id"]);
+    }
+
 
 }
@@ -0,0 +1,286 @@
+//! Limits guarding parsing and extraction against hostile input
+//!
+//! [`estimated_size`] approximates the memory a parsed value holds, for
+//! budgets on what is cached:
+//!
+//! ```
+//! use json_relaxed::limits::estimated_size;
+//! use serde_json::json;
+//!
+//! let size = estimated_size(&json!({"tags": ["a", "b"], "name": "x".repeat(1000)}));
+//! assert_eq!(size.nodes, 5);
+//! assert!(size.strings >= 1000 && size.total() > size.heap());
+//! ```
+
+use serde_json::Value;
+use std::cell::Cell;
+use std::mem::size_of;
+
+use crate::FromJsonError;
+
+/// Default maximum nesting depth, matches the recursion limit of serde_json
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Limits on the shape of a json document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum nesting depth of arrays and objects
+    pub max_depth: usize,
+    /// Maximum length in bytes of a single string (values and keys)
+    pub max_string_len: usize,
+    /// Maximum number of values in the whole document
+    pub max_nodes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_string_len: usize::MAX,
+            max_nodes: usize::MAX,
+        }
+    }
+}
+
+impl Limits {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = len;
+        self
+    }
+
+    pub fn max_nodes(mut self, nodes: usize) -> Self {
+        self.max_nodes = nodes;
+        self
+    }
+
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<(), FromJsonError> {
+        match depth > self.max_depth {
+            true => Err(FromJsonError::with_message(&format!("nesting depth exceeds limit of {}", self.max_depth))),
+            false => Ok(())
+        }
+    }
+
+    pub(crate) fn check_string(&self, s: &str) -> Result<(), FromJsonError> {
+        match s.len() > self.max_string_len {
+            true => Err(FromJsonError::with_message(&format!("string length exceeds limit of {}", self.max_string_len))),
+            false => Ok(())
+        }
+    }
+
+    pub(crate) fn check_nodes(&self, nodes: usize) -> Result<(), FromJsonError> {
+        match nodes > self.max_nodes {
+            true => Err(FromJsonError::with_message(&format!("node count exceeds limit of {}", self.max_nodes))),
+            false => Ok(())
+        }
+    }
+
+    /// Check an already parsed value against the limits.
+    /// The walk is iterative, so even very deep values can be checked safely.
+    pub fn check(&self, value: &Value) -> Result<(), FromJsonError> {
+        let mut nodes = 0;
+        let mut stack = vec!((value, 0));
+        while let Some((v, depth)) = stack.pop() {
+            nodes += 1;
+            self.check_nodes(nodes)?;
+            match v {
+                Value::String(s) => self.check_string(s)?,
+                Value::Array(a) => {
+                    self.check_depth(depth + 1)?;
+                    stack.extend(a.iter().map(|i| (i, depth + 1)));
+                },
+                Value::Object(o) => {
+                    self.check_depth(depth + 1)?;
+                    for (k, i) in o.iter() {
+                        self.check_string(k)?;
+                        stack.push((i, depth + 1));
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Run `f` with the nesting depth of recursive extraction (maybe_object and
+    /// maybe_array calling into TryFromJson) limited to `max_depth` on this thread.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        let previous = EXTRACTION_LIMIT.with(|l| l.replace(self.max_depth));
+        let _restore = Restore(previous);
+        f()
+    }
+}
+
+/// Approximate memory held by a value, see [`estimated_size`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Size {
+    /// Number of values, the root included
+    pub nodes: usize,
+    /// Heap bytes of strings and keys
+    pub strings: usize,
+    /// Heap bytes of array buffers and object entries
+    pub containers: usize,
+}
+
+impl Size {
+
+    pub fn heap(&self) -> usize {
+        self.strings + self.containers
+    }
+
+    /// Heap bytes and the root value itself
+    pub fn total(&self) -> usize {
+        self.heap() + size_of::<Value>()
+    }
+}
+
+/// Bookkeeping per object entry besides key and value, the slack and links of
+/// btree nodes, or the hash and index of an indexmap with preserve_order
+#[cfg(not(feature = "preserve_order"))]
+const ENTRY_OVERHEAD: usize = 16;
+#[cfg(feature = "preserve_order")]
+const ENTRY_OVERHEAD: usize = 24;
+
+/// Walk `value` and estimate the memory it holds. Allocator overhead is not
+/// counted, the estimate is for comparing with a budget rather than exact.
+pub fn estimated_size(value: &Value) -> Size {
+    let mut size = Size::default();
+    let mut stack = vec!(value);
+    while let Some(v) = stack.pop() {
+        size.nodes += 1;
+        match v {
+            Value::String(s) => size.strings += s.capacity(),
+            #[cfg(feature = "arbitrary_precision")]
+            Value::Number(n) => size.strings += n.to_string().len(),
+            Value::Array(a) => {
+                size.containers += a.capacity() * size_of::<Value>();
+                stack.extend(a.iter());
+            },
+            Value::Object(o) => {
+                size.containers += o.len() * (size_of::<String>() + size_of::<Value>() + ENTRY_OVERHEAD);
+                for (k, i) in o.iter() {
+                    size.strings += k.capacity();
+                    stack.push(i);
+                }
+            },
+            _ => {}
+        }
+    }
+    size
+}
+
+thread_local! {
+    static EXTRACTION_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static EXTRACTION_LIMIT: Cell<usize> = const { Cell::new(DEFAULT_MAX_DEPTH) };
+}
+
+struct Restore(usize);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        EXTRACTION_LIMIT.with(|l| l.set(self.0));
+    }
+}
+
+/// Marks one level of recursive extraction, released on drop
+pub(crate) struct DepthGuard;
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        EXTRACTION_DEPTH.with(|d| d.set(d.get() - 1));
+    }
+}
+
+/// Depth and limit of the current thread, to continue extraction on another one
+#[cfg(feature = "rayon")]
+pub(crate) fn current() -> (usize, usize) {
+    (EXTRACTION_DEPTH.with(|d| d.get()), EXTRACTION_LIMIT.with(|l| l.get()))
+}
+
+/// Run `f` with the depth and limit taken by [`current`] on another thread
+#[cfg(feature = "rayon")]
+pub(crate) fn resume<R>(state: (usize, usize), f: impl FnOnce() -> R) -> R {
+    let depth = EXTRACTION_DEPTH.with(|d| d.replace(state.0));
+    let _restore = Restore(EXTRACTION_LIMIT.with(|l| l.replace(state.1)));
+    let r = f();
+    EXTRACTION_DEPTH.with(|d| d.set(depth));
+    r
+}
+
+pub(crate) fn enter() -> Result<DepthGuard, FromJsonError> {
+    let limit = EXTRACTION_LIMIT.with(|l| l.get());
+    let depth = EXTRACTION_DEPTH.with(|d| d.get()) + 1;
+    match depth > limit {
+        true => Err(FromJsonError::with_message(&format!("extraction depth exceeds limit of {}", limit))),
+        false => {
+            EXTRACTION_DEPTH.with(|d| d.set(depth));
+            Ok(DepthGuard)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use std::mem::size_of;
+
+    use super::{estimated_size, Limits, Size};
+    use crate::{read, FromJsonError, Maybe, MaybeValue, TryFromJson};
+
+    #[test]
+    fn parse_limits() {
+        let opts = read::Options::new().limits(Limits::new().max_depth(2).max_string_len(3).max_nodes(5));
+
+        assert!(read::from_str_with("[[1, 2]]", &opts).is_ok());
+        assert!(read::from_str_with("[[[1]]]", &opts).is_err());
+        assert!(read::from_str_with("[\"abcd\"]", &opts).is_err());
+        assert!(read::from_str_with("[1, 2, 3, 4, 5]", &opts).is_err());
+
+        assert!(Limits::new().max_depth(1).check(&json!({"a": {"b": 1}})).is_err());
+    }
+
+    #[test]
+    fn size_estimate() {
+        assert_eq!(estimated_size(&json!(null)), Size { nodes: 1, ..Size::default() });
+        assert_eq!(estimated_size(&json!(true)).total(), size_of::<Value>());
+
+        let array = estimated_size(&Value::Array(Vec::with_capacity(10)));
+        assert_eq!((array.nodes, array.containers), (1, 10 * size_of::<Value>()));
+
+        let small = estimated_size(&json!({"k": "v"}));
+        let large = estimated_size(&json!({"k": "v".repeat(100), "more": [1, 2, {"x": null}]}));
+        assert_eq!((small.nodes, large.nodes), (2, 7));
+        assert!(large.strings >= 106 && large.heap() > small.heap());
+    }
+
+    struct Nested;
+
+    impl TryFromJson for Nested {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            match value.maybe_object::<Nested, _>("next") {
+                Maybe::Error(e) => Err(e),
+                _ => Ok(Nested)
+            }
+        }
+    }
+
+    #[test]
+    fn extraction_limit() {
+        let doc = json!({"next": {"next": {"next": {}}}});
+        let limits = Limits::new().max_depth(2);
+
+        assert!(limits.scope(|| doc.maybe_object::<Nested, _>("next").strict_ok()).is_err());
+        assert!(doc.maybe_object::<Nested, _>("next").strict_ok().is_ok());
+    }
+}
@@ -0,0 +1,291 @@
+//! Merging json documents
+
+use serde_json::{Map, Value};
+
+use crate::patch;
+use crate::pattern::PathPattern;
+
+/// Apply a JSON Merge Patch (RFC 7386) to `target`.
+/// Members of `patch` that are null delete the key from `target`.
+pub fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(p) => p,
+        _ => {
+            *target = patch.clone();
+            return;
+        }
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let target = target.as_object_mut().expect("checked above");
+
+    for (k, v) in patch.iter() {
+        match v {
+            Value::Null => {
+                patch::remove_key(target, k);
+            },
+            _ => apply_merge_patch(target.entry(k.as_str()).or_insert(Value::Null), v)
+        }
+    }
+}
+
+/// Create a JSON Merge Patch (RFC 7386) that turns `from` into `to`.
+/// Null members of objects in `to` can not be expressed in a merge patch and are
+/// removed when the patch is applied.
+pub fn merge_patch(from: &Value, to: &Value) -> Value {
+    let (from, to) = match (from, to) {
+        (Value::Object(f), Value::Object(t)) => (f, t),
+        _ => return to.clone()
+    };
+
+    let mut patch = Map::new();
+    for k in from.keys().filter(|k| !to.contains_key(*k)) {
+        patch.insert(k.clone(), Value::Null);
+    }
+    for (k, v) in to.iter() {
+        match from.get(k) {
+            Some(f) if f == v => {},
+            Some(f) => {
+                patch.insert(k.clone(), merge_patch(f, v));
+            },
+            None => {
+                patch.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    Value::Object(patch)
+}
+
+/// Fill members of `value` that are missing or null from `defaults`, recursing into
+/// objects. Values present in `value` are never overwritten, arrays are not merged.
+/// Null defaults add no members, at any depth.
+pub fn fill_defaults(value: &mut Value, defaults: &Value) {
+    match (value, defaults) {
+        (value @ Value::Null, Value::Object(_)) => {
+            *value = Value::Object(Map::new());
+            fill_defaults(value, defaults);
+        },
+        (value @ Value::Null, _) => *value = defaults.clone(),
+        (Value::Object(v), Value::Object(d)) => {
+            for (k, dv) in d.iter() {
+                match v.get_mut(k) {
+                    Some(member) => fill_defaults(member, dv),
+                    None if dv.is_null() => {},
+                    None => {
+                        let mut member = Value::Null;
+                        fill_defaults(&mut member, dv);
+                        // an object of only null defaults adds nothing
+                        if member.as_object().is_none_or(|m| !m.is_empty()) || dv.as_object().is_some_and(Map::is_empty) {
+                            v.insert(k.clone(), member);
+                        }
+                    }
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Resolves a conflict between a left and a right value
+type Resolve = dyn Fn(&Value, &Value) -> Value;
+
+/// How [`Merger`] resolves a path present on both sides
+pub enum Strategy {
+    /// Merge objects key by key, anything else is taken from the right
+    Deep,
+    /// Keep the left value
+    PreferLeft,
+    /// Take the right value as a whole
+    PreferRight,
+    /// Append the right array to the left one
+    Concat,
+    /// Keep the larger number
+    Max,
+    /// Keep the smaller number
+    Min,
+    /// Compute the result from both values
+    Custom(Box<Resolve>),
+}
+
+/// Deep merge of `right` over `left`, see [`Merger`]
+pub fn merge(left: &Value, right: &Value) -> Value {
+    Merger::new().merge(left, right)
+}
+
+/// Merges documents with conflict strategies registered by path pattern.
+///
+/// Patterns are matched against the path of the conflicting value: `"servers"` matches
+/// that key at any depth, `"/limits/*"` is a JSON Pointer with glob segments.
+/// The first matching pattern wins, paths without a match use [`Strategy::Deep`].
+/// Concat, Max and Min fall back to the right value if the types do not fit.
+/// A null on the right is a value like any other and replaces the left one.
+pub struct Merger {
+    rules: Vec<(PathPattern, Strategy)>,
+    default: Strategy,
+}
+
+impl Default for Merger {
+    fn default() -> Self {
+        Merger { rules: Vec::new(), default: Strategy::Deep }
+    }
+}
+
+impl Merger {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Resolve conflicts at paths matching `pattern` with `strategy`
+    pub fn strategy(mut self, pattern: &str, strategy: Strategy) -> Self {
+        self.rules.push((PathPattern::new(pattern), strategy));
+        self
+    }
+
+    /// Resolve conflicts at paths matching `pattern` with `resolve(left, right)`
+    pub fn custom(self, pattern: &str, resolve: impl Fn(&Value, &Value) -> Value + 'static) -> Self {
+        self.strategy(pattern, Strategy::Custom(Box::new(resolve)))
+    }
+
+    /// Strategy for paths without a matching pattern
+    pub fn default_strategy(mut self, strategy: Strategy) -> Self {
+        self.default = strategy;
+        self
+    }
+
+    pub fn merge(&self, left: &Value, right: &Value) -> Value {
+        self.merge_at(&mut Vec::new(), left, right)
+    }
+
+    fn merge_at(&self, path: &mut Vec<String>, left: &Value, right: &Value) -> Value {
+        let strategy = self.rules.iter()
+            .find(|(p, _)| !path.is_empty() && p.matches(path))
+            .map(|(_, s)| s)
+            .unwrap_or(&self.default);
+
+        match (strategy, left, right) {
+            (Strategy::Deep, Value::Object(l), Value::Object(r)) => {
+                let mut merged = l.clone();
+                for (k, rv) in r.iter() {
+                    path.push(k.clone());
+                    let v = match l.get(k) {
+                        Some(lv) => self.merge_at(path, lv, rv),
+                        None => rv.clone()
+                    };
+                    path.pop();
+                    merged.insert(k.clone(), v);
+                }
+                Value::Object(merged)
+            },
+            (Strategy::PreferLeft, _, _) => left.clone(),
+            (Strategy::Concat, Value::Array(l), Value::Array(r)) => {
+                Value::Array(l.iter().chain(r.iter()).cloned().collect())
+            },
+            (Strategy::Max, Value::Number(l), Value::Number(r)) => {
+                match l.as_f64() >= r.as_f64() {
+                    true => left.clone(),
+                    false => right.clone()
+                }
+            },
+            (Strategy::Min, Value::Number(l), Value::Number(r)) => {
+                match l.as_f64() <= r.as_f64() {
+                    true => left.clone(),
+                    false => right.clone()
+                }
+            },
+            (Strategy::Custom(resolve), _, _) => resolve(left, right),
+            _ => right.clone()
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    #[test]
+    fn rfc_example() {
+        let mut target = json!({
+            "title": "Goodbye!",
+            "author": {"givenName": "John", "familyName": "Doe"},
+            "tags": ["example", "sample"],
+            "content": "This will be unchanged"
+        });
+        let patch = json!({
+            "title": "Hello!",
+            "phoneNumber": "+01-123-456-7890",
+            "author": {"familyName": null},
+            "tags": ["example"]
+        });
+        let expected = json!({
+            "title": "Hello!",
+            "author": {"givenName": "John"},
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890"
+        });
+
+        let original = target.clone();
+        super::apply_merge_patch(&mut target, &patch);
+        assert_eq!(target, expected);
+
+        let generated = super::merge_patch(&original, &expected);
+        assert_eq!(generated, patch);
+    }
+
+    #[test]
+    fn merge_strategies() {
+        use super::{Merger, Strategy};
+
+        let base = json!({"servers": ["a"], "limits": {"conns": 10, "rate": 5}, "name": "base", "tags": ["x"]});
+        let layer = json!({"servers": ["b"], "limits": {"conns": 4, "rate": 50}, "name": "layer", "tags": ["y"]});
+
+        assert_eq!(super::merge(&base, &layer), layer);
+
+        let merger = Merger::new()
+            .strategy("servers", Strategy::Concat)
+            .strategy("/limits/conns", Strategy::Max)
+            .strategy("/limits/*", Strategy::Min)
+            .strategy("name", Strategy::PreferLeft)
+            .custom("tags", |l, r| json!([l, r]));
+        assert_eq!(merger.merge(&base, &layer), json!({
+            "servers": ["a", "b"],
+            "limits": {"conns": 10, "rate": 5},
+            "name": "base",
+            "tags": [["x"], ["y"]]
+        }));
+    }
+
+    #[test]
+    fn defaults_layering() {
+        let defaults = json!({"port": 80, "tls": {"enabled": false, "cert": "/etc/cert"}, "hosts": ["a", "b"]});
+        let mut user = json!({"port": 8080, "tls": {"enabled": true, "cert": null}, "hosts": ["c"]});
+        super::fill_defaults(&mut user, &defaults);
+
+        assert_eq!(user, json!({"port": 8080, "tls": {"enabled": true, "cert": "/etc/cert"}, "hosts": ["c"]}));
+
+        let mut user = json!({"port": 8080});
+        super::fill_defaults(&mut user, &json!({"proxy": null, "tls": {"ca": null}, "log": {"level": "info", "file": null}, "extra": {}}));
+        assert_eq!(user, json!({"port": 8080, "log": {"level": "info"}, "extra": {}}));
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn key_order() {
+        use crate::normalize::{self, KeyCase, Rules};
+
+        let keys = |v: &serde_json::Value| v.as_object().unwrap().keys().cloned().collect::<Vec<_>>();
+        let mut config = crate::read::from_str("{\"zeta\": 1, \"Alpha\": 2, \"mid\": 3, \"last\": 4}").unwrap();
+        super::apply_merge_patch(&mut config, &json!({"Alpha": null, "new": 5}));
+        assert_eq!(keys(&config), vec!("zeta", "mid", "last", "new"));
+
+        normalize::normalize(&mut config, &Rules::new().key_case(KeyCase::Upper));
+        assert_eq!(keys(&config), vec!("ZETA", "MID", "LAST", "NEW"));
+
+        let merged = super::merge(&config, &json!({"MID": 0, "FIRST": 1}));
+        assert_eq!(keys(&merged), vec!("ZETA", "MID", "LAST", "NEW", "FIRST"));
+    }
+}
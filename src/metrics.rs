@@ -0,0 +1,161 @@
+//! Counting strict, relaxed, null and error outcomes of the maybe_* accessors per field
+//!
+//! A [`Recorder`] installed with [`set_recorder`] sees every lookup, so dashboards
+//! can show which fields of an upstream feed are degrading. [`Counters`] is a
+//! recorder keeping the counts in memory. Without a recorder a lookup costs a
+//! single atomic load. Fields are JSON Pointers below the value the outermost
+//! accessor was called on, or the session, with array indices as `*`, like
+//! `/servers/*/port`.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+use crate::backend::Key;
+use crate::{session, FromJsonError, Maybe};
+
+/// Outcome of a lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Strict,
+    Relaxed,
+    Null,
+    Error,
+}
+
+impl Outcome {
+
+    pub fn of<T>(maybe: &Maybe<T>) -> Self {
+        match maybe {
+            Maybe::Strict(_) => Outcome::Strict,
+            Maybe::Relaxed(_) => Outcome::Relaxed,
+            Maybe::Null => Outcome::Null,
+            Maybe::Error(_) => Outcome::Error
+        }
+    }
+}
+
+/// Receives the outcome of every lookup
+pub trait Recorder: Send + Sync {
+    /// `key` is the field looked up, like `/servers/*/port`
+    fn record(&self, key: &str, outcome: Outcome);
+}
+
+static RECORDER: OnceLock<&'static dyn Recorder> = OnceLock::new();
+
+/// Install the global recorder, this can only be done once
+pub fn set_recorder(recorder: &'static dyn Recorder) -> Result<(), FromJsonError> {
+    RECORDER.set(recorder).map_err(|_| FromJsonError::with_message("a metrics recorder is already set"))
+}
+
+pub(crate) fn record<T, I: Key + ?Sized>(key: &I, maybe: &Maybe<T>) {
+    if let Some(recorder) = RECORDER.get() {
+        recorder.record(&field(&session::pointer_to(key)), Outcome::of(maybe));
+    }
+}
+
+/// A recorder is installed
+pub(crate) fn enabled() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// `pointer` with the array indices as `*`
+fn field(pointer: &str) -> String {
+    pointer.split('/').skip(1)
+        .map(|t| match !t.is_empty() && t.bytes().all(|b| b.is_ascii_digit()) {
+            true => "*",
+            false => t
+        })
+        .fold(String::new(), |f, t| f + "/" + t)
+}
+
+/// Number of lookups per outcome
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counts {
+    pub strict: u64,
+    pub relaxed: u64,
+    pub null: u64,
+    pub error: u64,
+}
+
+/// A recorder counting outcomes per field in memory
+///
+/// ```
+/// use json_relaxed::metrics::{self, Counters};
+///
+/// static COUNTERS: Counters = Counters::new();
+/// metrics::set_recorder(&COUNTERS).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct Counters {
+    counts: Mutex<BTreeMap<String, Counts>>,
+}
+
+impl Counters {
+
+    pub const fn new() -> Self {
+        Counters { counts: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// The counts so far by field
+    pub fn counts(&self) -> BTreeMap<String, Counts> {
+        self.counts.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    /// The counts so far by field, starting over
+    pub fn take(&self) -> BTreeMap<String, Counts> {
+        self.counts.lock().map(|mut c| std::mem::take(&mut *c)).unwrap_or_default()
+    }
+}
+
+impl Recorder for Counters {
+    fn record(&self, key: &str, outcome: Outcome) {
+        if let Ok(mut counts) = self.counts.lock() {
+            let c = counts.entry(s!(key)).or_default();
+            match outcome {
+                Outcome::Strict => c.strict += 1,
+                Outcome::Relaxed => c.relaxed += 1,
+                Outcome::Null => c.null += 1,
+                Outcome::Error => c.error += 1
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use serde_json::Value;
+
+    use super::{Counters, Counts};
+    use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+    struct Row;
+
+    impl TryFromJson for Row {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            value.maybe_uint("id");
+            Ok(Row)
+        }
+    }
+
+    #[test]
+    fn counters() {
+        static COUNTERS: Counters = Counters::new();
+        super::set_recorder(&COUNTERS).unwrap();
+        assert!(super::set_recorder(&COUNTERS).is_err());
+
+        // other tests run concurrently, only keys unique to this test are checked
+        let feed = json!([{"metrics-id": 1}, {"metrics-id": "2"}, {"metrics-id": null}, {"metrics-id": "x"}]);
+        for item in feed.as_array().unwrap() {
+            item.maybe_int("metrics-id");
+        }
+        assert_eq!(COUNTERS.counts()["/metrics-id"], Counts { strict: 1, relaxed: 1, null: 1, error: 1 });
+
+        let nested = json!({"metrics-rows": [{"id": 1}, {"id": "2"}, {"id": 3}]});
+        nested.maybe_array::<Row, _>("metrics-rows");
+        assert_eq!(COUNTERS.counts()["/metrics-rows/*/id"], Counts { strict: 2, relaxed: 1, null: 0, error: 0 });
+        assert_eq!(COUNTERS.counts()["/metrics-rows"].strict, 1);
+    }
+}
@@ -0,0 +1,161 @@
+//! Migrating documents between versions before extraction
+//!
+//! Each migration upgrades a document from one version to the next. The version
+//! is read from a member, `version` by default, missing means version 0.
+//! Loading runs every migration from the document's version up to the latest
+//! and sets the member accordingly:
+//!
+//! ```
+//! use json_relaxed::migrate::Migrations;
+//! use serde_json::json;
+//!
+//! let migrations = Migrations::new()
+//!     .version_key("schema_version")
+//!     .step(1, |doc| {
+//!         // 1 → 2: `host` and `port` moved into `server`
+//!         let doc = doc.as_object_mut().unwrap();
+//!         let server = json!({"host": doc.remove("host"), "port": doc.remove("port")});
+//!         doc.insert("server".into(), server);
+//!         Ok(())
+//!     })
+//!     .step(2, |doc| {
+//!         doc["server"]["tls"] = json!(false);
+//!         Ok(())
+//!     });
+//!
+//! let migrated = migrations.migrate(json!({"schema_version": "1", "host": "a", "port": 80})).unwrap();
+//! assert_eq!(migrated.applied, vec!(1, 2));
+//! assert_eq!(migrated.value, json!({"schema_version": 3, "server": {"host": "a", "port": 80, "tls": false}}));
+//! ```
+
+use serde_json::{Map, Value};
+
+use crate::{read, FromJsonError, Maybe, MaybeValue};
+
+/// Upgrades a document in place by one version
+type Step = dyn Fn(&mut Value) -> Result<(), FromJsonError> + Send + Sync;
+
+/// Migrations by version, see the [module documentation](self)
+pub struct Migrations {
+    key: String,
+    steps: Vec<(u64, Box<Step>)>,
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Migrations { key: s!("version"), steps: Vec::new() }
+    }
+}
+
+/// A migrated document with the migrations that ran
+#[derive(Debug, Clone)]
+pub struct Migrated {
+    pub value: Value,
+    /// Version of the document as loaded
+    pub from: u64,
+    /// Version after migrating
+    pub to: u64,
+    /// The versions whose migration ran, oldest first
+    pub applied: Vec<u64>,
+}
+
+impl Migrations {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Member holding the version, `version` by default
+    pub fn version_key(mut self, key: &str) -> Self {
+        self.key = s!(key);
+        self
+    }
+
+    /// Migrate documents of `version` to `version + 1`, a later step for the
+    /// same version replaces the earlier one
+    pub fn step(mut self, version: u64, migrate: impl Fn(&mut Value) -> Result<(), FromJsonError> + Send + Sync + 'static) -> Self {
+        self.steps.retain(|(v, _)| *v != version);
+        self.steps.push((version, Box::new(migrate)));
+        self.steps.sort_by_key(|(v, _)| *v);
+        self
+    }
+
+    /// The version documents are migrated to, one after the last step
+    pub fn latest(&self) -> u64 {
+        self.steps.last().map_or(0, |(v, _)| v + 1)
+    }
+
+    /// Run the migrations from the document's version on. Documents newer than
+    /// [`latest`](Self::latest) are an error, a failing step names its version.
+    pub fn migrate(&self, mut value: Value) -> Result<Migrated, FromJsonError> {
+        let from = match value.maybe_uint(self.key.as_str()) {
+            Maybe::Strict(v) | Maybe::Relaxed(v) => v,
+            Maybe::Null => 0,
+            Maybe::Error(e) => return Err(FromJsonError::with_message(&format!("`{}`: {}", self.key, e.message())))
+        };
+        if from > self.latest() {
+            return Err(FromJsonError::with_message(&format!("version {} is newer than the supported {}", from, self.latest())));
+        }
+
+        let mut applied = Vec::new();
+        for (version, step) in self.steps.iter().filter(|(v, _)| *v >= from) {
+            step(&mut value).map_err(|e| FromJsonError::with_message(&format!("migration from version {}: {}", version, e.message())))?;
+            applied.push(*version);
+        }
+
+        let to = applied.last().map_or(from, |v| v + 1);
+        if !applied.is_empty() {
+            if !value.is_object() {
+                value = Value::Object(Map::new());
+            }
+            value[self.key.as_str()] = Value::from(to);
+        }
+        Ok(Migrated { value, from, to, applied })
+    }
+
+    /// Read a document with [`read::from_str`] and migrate it
+    pub fn load(&self, text: &str) -> Result<Migrated, FromJsonError> {
+        self.migrate(read::from_str(text)?)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Migrations;
+    use crate::FromJsonError;
+
+    #[test]
+    fn version_chain() {
+        let migrations = Migrations::new()
+            .step(2, |doc| {
+                let title = doc.as_object_mut().and_then(|o| o.remove("title"));
+                doc["name"] = title.into();
+                Ok(())
+            })
+            .step(0, |doc| match doc.get("title") {
+                Some(_) => Ok(()),
+                None => Err(FromJsonError::with_message("no title"))
+            })
+            .step(1, |doc| {
+                doc["tags"] = json!([]);
+                Ok(())
+            });
+        assert_eq!(migrations.latest(), 3);
+
+        let migrated = migrations.migrate(json!({"title": "a"})).unwrap();
+        assert_eq!((migrated.from, migrated.to, migrated.applied), (0, 3, vec!(0, 1, 2)));
+        assert_eq!(migrated.value, json!({"version": 3, "name": "a", "tags": []}));
+
+        let migrated = migrations.load(r#"{"version": "2", "title": "b"}"#).unwrap();
+        assert_eq!(migrated.applied, vec!(2));
+
+        let current = migrations.migrate(json!({"version": 3})).unwrap();
+        assert!(current.applied.is_empty());
+
+        assert_eq!(migrations.migrate(json!({})).unwrap_err().message(), "migration from version 0: no title");
+        assert!(migrations.migrate(json!({"version": 4})).is_err());
+    }
+}
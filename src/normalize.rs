@@ -0,0 +1,233 @@
+//! Applying coercions to a document in place, so strict consumers can handle it
+
+use serde_json::{Map, Number, Value};
+
+/// Case applied to object keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    Lower,
+    Upper,
+    /// `retryCount`
+    Camel,
+    /// `retry_count`
+    Snake,
+    /// `retry-count`
+    Kebab,
+}
+
+impl KeyCase {
+    pub fn apply(&self, key: &str) -> String {
+        // leading underscores often carry meaning (`_id`), keep them
+        let trimmed = key.trim_start_matches('_');
+        let prefix = &key[..key.len() - trimmed.len()];
+        let words = || words(trimmed).into_iter();
+
+        let converted = match self {
+            KeyCase::Lower => trimmed.to_lowercase(),
+            KeyCase::Upper => trimmed.to_uppercase(),
+            KeyCase::Camel => words().enumerate().map(|(i, w)| match i {
+                0 => w.to_lowercase(),
+                _ => capitalize(&w)
+            }).collect(),
+            KeyCase::Snake => words().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_"),
+            KeyCase::Kebab => words().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("-"),
+        };
+        format!("{}{}", prefix, converted)
+    }
+}
+
+/// Split a key into words at separators and case changes, `HTTPServer_id` is `HTTP`, `Server`, `id`
+fn words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ' | '.') {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false);
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.push(*c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(|c| c.to_lowercase())).collect(),
+        None => String::new()
+    }
+}
+
+/// Rename every object key in `value` to `case`
+pub fn rename_keys(value: &mut Value, case: KeyCase) {
+    rename_keys_with(value, &|k| case.apply(k))
+}
+
+/// Rename every object key in `value` with `mapper`, on collisions the last key wins
+pub fn rename_keys_with(value: &mut Value, mapper: &dyn Fn(&str) -> String) {
+    match value {
+        Value::Object(o) => {
+            let renamed: Map<String, Value> = std::mem::take(o).into_iter()
+                .map(|(k, mut v)| {
+                    rename_keys_with(&mut v, mapper);
+                    (mapper(&k), v)
+                })
+                .collect();
+            *o = renamed;
+        },
+        Value::Array(a) => {
+            for v in a.iter_mut() {
+                rename_keys_with(v, mapper);
+            }
+        },
+        _ => {}
+    }
+}
+
+/// The coercions [`normalize`] applies, all disabled by default
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    /// Strings that are valid json numbers become numbers, eg "23" or "-1.5e3"
+    pub numbers: bool,
+    /// "true"/"false", "yes"/"no" and "on"/"off" (any case) become booleans
+    pub booleans: bool,
+    /// Rename all object keys, on collisions the last key wins
+    pub key_case: Option<KeyCase>,
+    /// Remove empty strings from objects and arrays
+    pub drop_empty_strings: bool,
+}
+
+impl Rules {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn numbers(mut self, enable: bool) -> Self {
+        self.numbers = enable;
+        self
+    }
+
+    pub fn booleans(mut self, enable: bool) -> Self {
+        self.booleans = enable;
+        self
+    }
+
+    pub fn key_case(mut self, case: KeyCase) -> Self {
+        self.key_case = Some(case);
+        self
+    }
+
+    pub fn drop_empty_strings(mut self, enable: bool) -> Self {
+        self.drop_empty_strings = enable;
+        self
+    }
+}
+
+/// Apply `rules` to `value` and all of its children.
+/// An empty string at the root becomes null if empty strings are dropped.
+pub fn normalize(value: &mut Value, rules: &Rules) {
+    if rules.drop_empty_strings && is_empty_string(value) {
+        *value = Value::Null;
+    }
+    walk(value, rules);
+}
+
+fn is_empty_string(value: &Value) -> bool {
+    matches!(value, Value::String(s) if s.is_empty())
+}
+
+fn walk(value: &mut Value, rules: &Rules) {
+    match value {
+        Value::String(s) => {
+            if let Some(v) = coerce_string(s, rules) {
+                *value = v;
+            }
+        },
+        Value::Array(a) => {
+            if rules.drop_empty_strings {
+                a.retain(|i| !is_empty_string(i));
+            }
+            for i in a.iter_mut() {
+                walk(i, rules);
+            }
+        },
+        Value::Object(o) => {
+            if rules.drop_empty_strings {
+                o.retain(|_, i| !is_empty_string(i));
+            }
+            if let Some(case) = rules.key_case {
+                let renamed: Map<String, Value> = std::mem::take(o).into_iter()
+                    .map(|(k, v)| (case.apply(&k), v))
+                    .collect();
+                *o = renamed;
+            }
+            for (_, i) in o.iter_mut() {
+                walk(i, rules);
+            }
+        },
+        _ => {}
+    }
+}
+
+fn coerce_string(s: &str, rules: &Rules) -> Option<Value> {
+    if rules.numbers {
+        if let Ok(n) = s.parse::<Number>() {
+            return Some(Value::Number(n));
+        }
+    }
+    if rules.booleans {
+        match s.to_lowercase().as_str() {
+            "true" | "yes" | "on" => return Some(Value::Bool(true)),
+            "false" | "no" | "off" => return Some(Value::Bool(false)),
+            _ => {}
+        }
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{KeyCase, Rules};
+
+    #[test]
+    fn normalize_document() {
+        let mut doc = json!({"Port": "8080", "Debug": "Yes", "Name": "", "Zip": "01234", "Tags": ["", "off", "1.5"]});
+        let rules = Rules::new().numbers(true).booleans(true).key_case(KeyCase::Lower).drop_empty_strings(true);
+        super::normalize(&mut doc, &rules);
+
+        assert_eq!(doc, json!({"port": 8080, "debug": true, "zip": "01234", "tags": [false, 1.5]}));
+    }
+
+    #[test]
+    fn key_cases() {
+        assert_eq!(KeyCase::Snake.apply("retryCount"), "retry_count");
+        assert_eq!(KeyCase::Snake.apply("HTTPServer2Port"), "http_server2_port");
+        assert_eq!(KeyCase::Camel.apply("max-retry_count"), "maxRetryCount");
+        assert_eq!(KeyCase::Kebab.apply("_userID"), "_user-id");
+
+        let mut doc = json!({"serverConfig": {"maxConns": 1, "hosts": [{"hostName": "a"}]}});
+        super::rename_keys(&mut doc, KeyCase::Snake);
+        assert_eq!(doc, json!({"server_config": {"max_conns": 1, "hosts": [{"host_name": "a"}]}}));
+
+        super::rename_keys_with(&mut doc, &|k| k.replace('_', "."));
+        assert_eq!(doc, json!({"server.config": {"max.conns": 1, "hosts": [{"host.name": "a"}]}}));
+    }
+}
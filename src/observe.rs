@@ -0,0 +1,213 @@
+//! Metrics and events for relaxed coercions, defaults and errors
+//!
+//! Outcomes are counted by the [metrics recorder](crate::metrics). Events are
+//! emitted behind the `tracing` and `log` features with the `json_relaxed` target.
+//! Relaxed coercions and substituted defaults are emitted at debug level, errors at
+//! warn level, with the JSON Pointer of the value as `path`, the outcome as `kind`
+//! and the json type of the source value as `from`. The pointer starts at the value
+//! the outermost accessor was called on, or at the session.
+//! With both features events go through tracing only, its `log` feature forwards them.
+//! Within a [session](crate::session) outcomes are also added to its trace.
+
+use crate::backend::{JsonLike, Key};
+use crate::Maybe;
+
+#[cfg(any(feature = "tracing", feature = "log"))]
+thread_local! {
+    /// Path of the last outcome without value, for the default that may follow
+    static LAST: std::cell::RefCell<String> = const { std::cell::RefCell::new(String::new()) };
+}
+
+/// Report `maybe`, read from `value` at `key`, to the [metrics recorder](crate::metrics)
+/// and as an event, and pass it on
+pub(crate) fn outcome<T, I: Key + ?Sized, J: JsonLike + ?Sized>(key: &I, value: Option<&J>, maybe: Maybe<T>) -> Maybe<T> {
+    if let Some(v) = value {
+        crate::session::touch(v);
+    }
+    outcome_from(key, value.map_or("missing", crate::coerce::type_name), maybe)
+}
+
+/// Like [`outcome`] with the json type of the source value given as `from`, for
+/// values that have been moved out already
+pub(crate) fn outcome_from<T, I: Key + ?Sized>(key: &I, from: &str, maybe: Maybe<T>) -> Maybe<T> {
+    let maybe = crate::session::record(key, from, maybe);
+    crate::metrics::record(key, &maybe);
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    {
+        let path = match &maybe {
+            Maybe::Strict(_) => String::new(),
+            _ => crate::session::pointer_to(key)
+        };
+        match &maybe {
+            Maybe::Relaxed(_) => debug(&path, "relaxed", from),
+            Maybe::Error(e) => warn(&path, from, e.message()),
+            Maybe::Strict(_) | Maybe::Null => {}
+        }
+        LAST.with(|last| *last.borrow_mut() = match &maybe {
+            Maybe::Null | Maybe::Error(_) => path,
+            Maybe::Strict(_) | Maybe::Relaxed(_) => String::new()
+        });
+    }
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    let _ = from;
+    maybe
+}
+
+/// Report that a default replaced a null or erroneous value, at the path of the last
+/// outcome. `given` is false for `Default::default()`.
+pub(crate) fn defaulted<T>(maybe: &Maybe<T>, given: bool) {
+    if let Maybe::Null | Maybe::Error(_) = maybe {
+        crate::session::defaulted::<T>(given);
+    }
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    {
+        let path = LAST.with(|last| last.take());
+        match maybe {
+            Maybe::Null => debug(&path, "default", "null"),
+            Maybe::Error(_) => debug(&path, "default", "error"),
+            Maybe::Strict(_) | Maybe::Relaxed(_) => {}
+        }
+    }
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    let _ = maybe;
+}
+
+#[cfg(any(feature = "tracing", feature = "log"))]
+fn debug(path: &str, kind: &str, from: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "json_relaxed", path, kind, from);
+    #[cfg(all(feature = "log", not(feature = "tracing")))]
+    log::debug!(target: "json_relaxed", "path={:?} kind={} from={}", path, kind, from);
+}
+
+#[cfg(any(feature = "tracing", feature = "log"))]
+fn warn(path: &str, from: &str, message: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "json_relaxed", path, kind = "error", from, message);
+    #[cfg(all(feature = "log", not(feature = "tracing")))]
+    log::warn!(target: "json_relaxed", "path={:?} kind=error from={} message={}", path, from, message);
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    #[cfg(all(feature = "log", not(feature = "tracing")))]
+    #[test]
+    fn log_events() {
+        use serde_json::json;
+        use std::cell::RefCell;
+
+        use crate::MaybeValue;
+
+        // other tests read values concurrently, only this thread is recorded
+        thread_local! {
+            static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        }
+
+        struct Recorder;
+
+        impl log::Log for Recorder {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                if record.target() == "json_relaxed" {
+                    EVENTS.with(|events| events.borrow_mut().push(format!("{} {}", record.level(), record.args())));
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        log::set_logger(&Recorder).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let value = json!({"port": "8080", "debug": true, "name": [1]});
+        value.maybe_int("port");
+        value.maybe_bool("debug");
+        value.maybe_string("name");
+        value.maybe_string("missing").relaxed();
+
+        let events = EVENTS.with(|events| events.borrow().clone());
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], "DEBUG path=\"/port\" kind=relaxed from=string");
+        assert!(events[1].starts_with("WARN path=\"/name\" kind=error from=array"));
+        assert_eq!(events[2], "DEBUG path=\"/missing\" kind=default from=null");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_events() {
+        use serde_json::{json, Value};
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+        #[derive(Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                self.0 += &format!(" {}={:?}", field.name(), value);
+            }
+
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0 += &format!(" {}={}", field.name(), value);
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, metadata: &Metadata) -> bool {
+                metadata.target() == "json_relaxed"
+            }
+
+            fn new_span(&self, _: &Attributes) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, _: &Record) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event) {
+                let mut fields = Fields(event.metadata().level().to_string());
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        struct Server;
+
+        impl TryFromJson for Server {
+            fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+                value.maybe_uint("port").strict_ok().map(|_| Server)
+            }
+        }
+
+        let recorder = Recorder::default();
+        let events = recorder.0.clone();
+        let value = json!({"servers": [{"port": 80}, {"port": "x"}], "debug": "yes"});
+        tracing::subscriber::with_default(recorder, || {
+            value.maybe_bool("debug");
+            value.maybe_array::<Server, _>("servers").relaxed();
+            value.maybe_string("host").relaxed();
+        });
+
+        let events = events.lock().unwrap();
+        assert_eq!(events[0], "DEBUG path=/debug kind=relaxed from=string");
+        assert!(events[1].starts_with("WARN path=/servers/1/port kind=error from=string"));
+        assert_eq!(&events[2..], ["DEBUG path=/servers kind=relaxed from=array", "DEBUG path=/host kind=default from=null"]);
+    }
+}
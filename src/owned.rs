@@ -0,0 +1,130 @@
+//! Extraction that moves strings, arrays and objects out instead of cloning them
+//!
+//! [`IntoMaybe`] is implemented for `Value`, consuming it, and for `&mut Value`,
+//! taking the member and leaving null in its place. Both follow the rules of the
+//! [`MaybeValue`](crate::MaybeValue) accessors, null words and secret references
+//! included, which are read instead of moved.
+
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+use crate::backend::Key;
+use crate::{coerce, observe, FromJsonError, Maybe};
+
+/// Owned counterparts of the [`MaybeValue`](crate::MaybeValue) accessors
+pub trait IntoMaybe: Sized {
+
+    /// The member itself, `Null` if it is missing or null
+    fn into_maybe_value<I: Key>(self, key: I) -> Maybe<Value>;
+    fn into_maybe_string<I: Key>(self, key: I) -> Maybe<String>;
+    /// The elements of an array, a single value is a `Relaxed` array of one
+    fn into_maybe_array<I: Key>(self, key: I) -> Maybe<Vec<Value>>;
+    fn into_maybe_object<I: Key>(self, key: I) -> Maybe<Map<String, Value>>;
+}
+
+impl IntoMaybe for &mut Value {
+
+    fn into_maybe_value<I: Key>(self, key: I) -> Maybe<Value> {
+        let node = key.lookup_mut(self);
+        let from = node.as_deref().map_or("missing", coerce::type_name);
+        let maybe = match node {
+            None | Some(Value::Null) => Maybe::Null,
+            Some(v) => Maybe::Strict(v.take())
+        };
+        observe::outcome_from(&key, from, maybe)
+    }
+
+    fn into_maybe_string<I: Key>(self, key: I) -> Maybe<String> {
+        let node = key.lookup_mut(self);
+        let from = node.as_deref().map_or("missing", coerce::type_name);
+        // only a string read as it is can be moved, null words and secrets are not
+        let read = match coerce::to_str(node.as_deref()) {
+            Maybe::Strict(Cow::Borrowed(_)) => None,
+            Maybe::Strict(s) => Some(Maybe::Strict(s.into_owned())),
+            Maybe::Relaxed(s) => Some(Maybe::Relaxed(s.into_owned())),
+            Maybe::Null => Some(Maybe::Null),
+            Maybe::Error(e) => Some(Maybe::Error(e))
+        };
+        let maybe = match (read, node) {
+            (Some(maybe), _) => maybe,
+            (None, Some(Value::String(s))) => Maybe::Strict(std::mem::take(s)),
+            (None, _) => Maybe::Error(FromJsonError::unexpected())
+        };
+        observe::outcome_from(&key, from, maybe)
+    }
+
+    fn into_maybe_array<I: Key>(self, key: I) -> Maybe<Vec<Value>> {
+        let node = key.lookup_mut(self);
+        let from = node.as_deref().map_or("missing", coerce::type_name);
+        let maybe = match node {
+            None | Some(Value::Null) => Maybe::Null,
+            Some(Value::Array(a)) => Maybe::Strict(std::mem::take(a)),
+            Some(v) => Maybe::Relaxed(vec!(v.take()))
+        };
+        observe::outcome_from(&key, from, maybe)
+    }
+
+    fn into_maybe_object<I: Key>(self, key: I) -> Maybe<Map<String, Value>> {
+        let node = key.lookup_mut(self);
+        let from = node.as_deref().map_or("missing", coerce::type_name);
+        let maybe = match node {
+            None | Some(Value::Null) => Maybe::Null,
+            Some(Value::Object(o)) => Maybe::Strict(std::mem::take(o)),
+            Some(v) => Maybe::Error(coerce::mismatch(&*v))
+        };
+        observe::outcome_from(&key, from, maybe)
+    }
+}
+
+impl IntoMaybe for Value {
+
+    fn into_maybe_value<I: Key>(mut self, key: I) -> Maybe<Value> {
+        (&mut self).into_maybe_value(key)
+    }
+
+    fn into_maybe_string<I: Key>(mut self, key: I) -> Maybe<String> {
+        (&mut self).into_maybe_string(key)
+    }
+
+    fn into_maybe_array<I: Key>(mut self, key: I) -> Maybe<Vec<Value>> {
+        (&mut self).into_maybe_array(key)
+    }
+
+    fn into_maybe_object<I: Key>(mut self, key: I) -> Maybe<Map<String, Value>> {
+        (&mut self).into_maybe_object(key)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::IntoMaybe;
+    use crate::words::WordPack;
+    use crate::Maybe;
+
+    #[test]
+    fn moved_members() {
+        let mut json = json!({"name": "crate", "port": 8080, "tags": ["a", "b"], "tag": "c", "meta": {"a": 1}, "list": [1, {"x": "y"}]});
+
+        assert_eq!((&mut json).into_maybe_string("name").strict(), Some(s!("crate")));
+        assert_eq!(json["name"], json!(""));
+        assert!(matches!((&mut json).into_maybe_string("port"), Maybe::Relaxed(p) if p == "8080"));
+        assert_eq!((&mut json).into_maybe_array("tags").strict(), Some(vec!(json!("a"), json!("b"))));
+        assert!(matches!((&mut json).into_maybe_array("tag"), Maybe::Relaxed(v) if v == vec!(json!("c"))));
+        assert!(matches!((&mut json).into_maybe_object("port"), Maybe::Error(_)));
+        assert!(json["list"].take().into_maybe_object(1).strict().is_some());
+
+        assert_eq!(json.into_maybe_object("meta").strict().map(|m| m.len()), Some(1));
+    }
+
+    #[test]
+    fn null_words() {
+        let mut json = json!({"m": "k. A.", "n": "Nein"});
+        assert!(matches!(WordPack::german().scope(|| (&mut json).into_maybe_string("m")), Maybe::Null));
+        assert_eq!(json["m"], json!("k. A."));
+        assert_eq!((&mut json).into_maybe_string("m").strict(), Some(s!("k. A.")));
+        assert_eq!(WordPack::german().scope(|| (&mut json).into_maybe_string("n")).strict(), Some(s!("Nein")));
+    }
+}
@@ -0,0 +1,260 @@
+//! JSON Patch (RFC 6902)
+
+use serde_json::{json, Map, Value};
+
+use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+/// A single JSON Patch operation, paths are JSON Pointers
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+impl TryFromJson for Operation {
+    fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+        let path = value.maybe_string("path").strict_ok()?;
+        let operand = || value.get("value").cloned()
+            .ok_or_else(|| FromJsonError::with_message("missing value"));
+        let from = || value.maybe_string("from").strict_ok();
+
+        match value.maybe_string("op").strict_ok()?.as_str() {
+            "add" => Ok(Operation::Add { path, value: operand()? }),
+            "remove" => Ok(Operation::Remove { path }),
+            "replace" => Ok(Operation::Replace { path, value: operand()? }),
+            "move" => Ok(Operation::Move { from: from()?, path }),
+            "copy" => Ok(Operation::Copy { from: from()?, path }),
+            "test" => Ok(Operation::Test { path, value: operand()? }),
+            op => Err(FromJsonError::with_message(&format!("unknown patch operation `{}`", op)))
+        }
+    }
+}
+
+impl From<&Operation> for Value {
+    fn from(op: &Operation) -> Self {
+        match op {
+            Operation::Add { path, value } => json!({"op": "add", "path": path, "value": value}),
+            Operation::Remove { path } => json!({"op": "remove", "path": path}),
+            Operation::Replace { path, value } => json!({"op": "replace", "path": path, "value": value}),
+            Operation::Move { from, path } => json!({"op": "move", "from": from, "path": path}),
+            Operation::Copy { from, path } => json!({"op": "copy", "from": from, "path": path}),
+            Operation::Test { path, value } => json!({"op": "test", "path": path, "value": value}),
+        }
+    }
+}
+
+/// Apply `patch` to `doc`. If any operation fails, `doc` is left unchanged.
+pub fn apply_patch(doc: &mut Value, patch: &[Operation]) -> Result<(), FromJsonError> {
+    apply(doc, patch, false)
+}
+
+/// Like [`apply_patch`], but array indices past the end are tolerated
+/// by appending for add, replace, move and copy
+pub fn apply_patch_relaxed(doc: &mut Value, patch: &[Operation]) -> Result<(), FromJsonError> {
+    apply(doc, patch, true)
+}
+
+fn apply(doc: &mut Value, patch: &[Operation], relaxed: bool) -> Result<(), FromJsonError> {
+    let mut work = doc.clone();
+    for op in patch {
+        match op {
+            Operation::Add { path, value } => add(&mut work, path, value.clone(), relaxed)?,
+            Operation::Remove { path } => {
+                remove(&mut work, path)?;
+            },
+            Operation::Replace { path, value } => {
+                match work.pointer_mut(path) {
+                    Some(target) => *target = value.clone(),
+                    None if relaxed => add(&mut work, path, value.clone(), relaxed)?,
+                    None => return Err(not_found(path))
+                }
+            },
+            Operation::Move { from, path } => {
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(FromJsonError::with_message(&format!("can not move {} into itself", from)));
+                }
+                let value = remove(&mut work, from)?;
+                add(&mut work, path, value, relaxed)?;
+            },
+            Operation::Copy { from, path } => {
+                let value = work.pointer(from).cloned().ok_or_else(|| not_found(from))?;
+                add(&mut work, path, value, relaxed)?;
+            },
+            Operation::Test { path, value } => {
+                if work.pointer(path) != Some(value) {
+                    return Err(FromJsonError::with_message(&format!("test failed at {}", path)));
+                }
+            }
+        }
+    }
+    *doc = work;
+    Ok(())
+}
+
+fn not_found(path: &str) -> FromJsonError {
+    FromJsonError::with_message(&format!("path not found: {}", path))
+}
+
+/// Split a pointer into its parent and the unescaped last token
+fn split(path: &str) -> Result<(&str, String), FromJsonError> {
+    match path.rfind('/') {
+        Some(p) => Ok((&path[..p], path[p + 1..].replace("~1", "/").replace("~0", "~"))),
+        None => Err(FromJsonError::with_message(&format!("invalid path: {}", path)))
+    }
+}
+
+fn add(doc: &mut Value, path: &str, value: Value, relaxed: bool) -> Result<(), FromJsonError> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+
+    let (parent, token) = split(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(o)) => {
+            o.insert(token, value);
+            Ok(())
+        },
+        Some(Value::Array(a)) => {
+            match (token.as_str(), token.parse::<usize>()) {
+                ("-", _) => a.push(value),
+                (_, Ok(i)) if i <= a.len() => a.insert(i, value),
+                (_, Ok(_)) if relaxed => a.push(value),
+                _ => return Err(FromJsonError::with_message(&format!("invalid array index: {}", path)))
+            }
+            Ok(())
+        },
+        _ => Err(not_found(parent))
+    }
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, FromJsonError> {
+    let (parent, token) = split(path)?;
+    let removed = match doc.pointer_mut(parent) {
+        Some(Value::Object(o)) => remove_key(o, &token),
+        Some(Value::Array(a)) => {
+            match token.parse::<usize>() {
+                Ok(i) if i < a.len() => Some(a.remove(i)),
+                _ => None
+            }
+        },
+        _ => None
+    };
+    removed.ok_or_else(|| not_found(path))
+}
+
+/// Create a patch that turns `from` into `to`
+pub fn diff(from: &Value, to: &Value) -> Vec<Operation> {
+    let mut ops = Vec::new();
+    diff_into(&mut ops, String::new(), from, to);
+    ops
+}
+
+fn diff_into(ops: &mut Vec<Operation>, path: String, from: &Value, to: &Value) {
+    match (from, to) {
+        _ if from == to => {},
+        (Value::Object(f), Value::Object(t)) => {
+            for k in f.keys().filter(|k| !t.contains_key(*k)) {
+                ops.push(Operation::Remove { path: child(&path, k) });
+            }
+            for (k, v) in t.iter() {
+                match f.get(k) {
+                    Some(fv) => diff_into(ops, child(&path, k), fv, v),
+                    None => ops.push(Operation::Add { path: child(&path, k), value: v.clone() })
+                }
+            }
+        },
+        (Value::Array(f), Value::Array(t)) => {
+            for (i, (fv, tv)) in f.iter().zip(t.iter()).enumerate() {
+                diff_into(ops, format!("{}/{}", path, i), fv, tv);
+            }
+            for (i, tv) in t.iter().enumerate().skip(f.len()) {
+                ops.push(Operation::Add { path: format!("{}/{}", path, i), value: tv.clone() });
+            }
+            for i in (t.len()..f.len()).rev() {
+                ops.push(Operation::Remove { path: format!("{}/{}", path, i) });
+            }
+        },
+        _ => ops.push(Operation::Replace { path, value: to.clone() })
+    }
+}
+
+pub(crate) fn child(path: &str, key: &str) -> String {
+    format!("{}/{}", path, key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Set the member at `segments`, replacing values that are in the way by objects.
+/// An empty segment appends an element to an array instead.
+pub(crate) fn insert(target: &mut Value, segments: &[&str], value: Value) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => return *target = value
+    };
+    if segment.is_empty() {
+        if !target.is_array() {
+            *target = Value::Array(Vec::new());
+        }
+        let array = target.as_array_mut().expect("checked above");
+        array.push(Value::Null);
+        return insert(array.last_mut().expect("pushed above"), rest, value);
+    }
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let child = target.as_object_mut().expect("checked above").entry(*segment).or_insert(Value::Null);
+    insert(child, rest, value);
+}
+
+/// Remove `key` without moving the other keys when the order is preserved
+pub(crate) fn remove_key(map: &mut Map<String, Value>, key: &str) -> Option<Value> {
+    #[cfg(feature = "preserve_order")]
+    return map.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    return map.remove(key);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::MaybeValue;
+    use super::Operation;
+
+    #[test]
+    fn apply_operations() {
+        let patch = json!({"ops": [
+            {"op": "add", "path": "/baz", "value": "qux"},
+            {"op": "remove", "path": "/foo/1"},
+            {"op": "replace", "path": "/foo/0", "value": "a"},
+            {"op": "move", "from": "/baz", "path": "/moved"},
+            {"op": "copy", "from": "/foo", "path": "/foo~1copy"},
+            {"op": "test", "path": "/moved", "value": "qux"}
+        ]});
+        let ops: Vec<Operation> = patch.maybe_array("ops").strict().unwrap();
+
+        let mut doc = json!({"foo": ["bar", "baz"]});
+        super::apply_patch(&mut doc, &ops).unwrap();
+        assert_eq!(doc, json!({"foo": ["a"], "moved": "qux", "foo/copy": ["a"]}));
+
+        let failing = vec!(Operation::Add { path: "/foo/5".into(), value: json!(1) });
+        assert!(super::apply_patch(&mut doc, &failing).is_err());
+        assert_eq!(doc["foo"], json!(["a"]));
+        super::apply_patch_relaxed(&mut doc, &failing).unwrap();
+        assert_eq!(doc["foo"], json!(["a", 1]));
+    }
+
+    #[test]
+    fn diff_roundtrip() {
+        let from = json!({"a": [1, 2, 3], "b": {"c": true}, "d": "x"});
+        let to = json!({"a": [1, 5], "b": {"c": true, "e/f": null}, "g": 1});
+
+        let mut doc = from.clone();
+        super::apply_patch(&mut doc, &super::diff(&from, &to)).unwrap();
+        assert_eq!(doc, to);
+    }
+}
@@ -0,0 +1,94 @@
+//! Glob patterns for keys and JSON Pointer paths
+
+/// Match `text` against a glob where `*` matches any run of characters and `?` one character
+pub(crate) fn glob(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let t: Vec<char> = text.chars().collect();
+
+    // classic backtracking over the last star
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        match p.get(pi) {
+            Some('*') => {
+                star = Some((pi, ti));
+                pi += 1;
+            },
+            Some(c) if *c == '?' || *c == t[ti] => {
+                pi += 1;
+                ti += 1;
+            },
+            _ => {
+                match star {
+                    Some((sp, st)) => {
+                        pi = sp + 1;
+                        ti = st + 1;
+                        star = Some((sp, st + 1));
+                    },
+                    None => return false
+                }
+            }
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// Unescaped tokens of a JSON Pointer
+pub(crate) fn tokens(pointer: &str) -> Vec<String> {
+    pointer.split('/').skip(1).map(|t| t.replace("~1", "/").replace("~0", "~")).collect()
+}
+
+/// A pattern over a whole path. Patterns starting with `/` are JSON Pointers whose
+/// segments are globs, where `**` matches any number of segments. Any other pattern
+/// is a glob matched against the last key only, at any depth.
+#[derive(Debug, Clone)]
+pub(crate) enum PathPattern {
+    Key(String),
+    Path(Vec<String>),
+}
+
+impl PathPattern {
+
+    pub(crate) fn new(pattern: &str) -> Self {
+        match pattern.starts_with('/') {
+            true => PathPattern::Path(tokens(pattern)),
+            false => PathPattern::Key(s!(pattern)),
+        }
+    }
+
+    pub(crate) fn matches(&self, path: &[String]) -> bool {
+        match self {
+            PathPattern::Key(p) => path.last().map(|k| glob(p, k)).unwrap_or(false),
+            PathPattern::Path(segments) => segments_match(segments, path)
+        }
+    }
+}
+
+fn segments_match(pattern: &[String], path: &[String]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(p), _) if p == "**" => {
+            (0..=path.len()).any(|skip| segments_match(&pattern[1..], &path[skip..]))
+        },
+        (Some(p), Some(k)) => glob(p, k) && segments_match(&pattern[1..], &path[1..]),
+        _ => false
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{glob, tokens, PathPattern};
+
+    #[test]
+    fn globs_and_paths() {
+        assert!(glob("*token*", "access_token_v2"));
+        assert!(glob("pass?ord", "password"));
+        assert!(!glob("pass", "password"));
+
+        assert!(PathPattern::new("secret").matches(&tokens("/a/b/secret")));
+        assert!(PathPattern::new("/servers/*/key").matches(&tokens("/servers/0/key")));
+        assert!(!PathPattern::new("/servers/*/key").matches(&tokens("/servers/0/x/key")));
+        assert!(PathPattern::new("/servers/**/key").matches(&tokens("/servers/0/x/key")));
+    }
+}
@@ -0,0 +1,172 @@
+//! Statistics about the structure of documents, to decide which coercions a feed needs
+
+use serde_json::{Number, Value};
+use std::collections::BTreeMap;
+
+use crate::patch;
+
+/// Statistics collected for one path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathStats {
+    /// Number of values seen at this path
+    pub count: usize,
+    pub nulls: usize,
+    pub bools: usize,
+    pub numbers: usize,
+    pub strings: usize,
+    pub arrays: usize,
+    pub objects: usize,
+    /// Strings that are valid json numbers, candidates for number coercion
+    pub numeric_strings: usize,
+    /// Numbers and numeric strings that are integers
+    pub integers: usize,
+    /// Strings like "true", "no" or "0", candidates for bool coercion
+    pub bool_strings: usize,
+    /// Shortest string or array (in characters or elements)
+    pub min_len: Option<usize>,
+    /// Longest string or array (in characters or elements)
+    pub max_len: Option<usize>,
+}
+
+impl PathStats {
+
+    /// Fraction of values that were null
+    pub fn null_rate(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            n => self.nulls as f64 / n as f64
+        }
+    }
+
+    /// Number of different json types seen, more than one calls for relaxed extraction
+    pub fn type_count(&self) -> usize {
+        [self.nulls, self.bools, self.numbers, self.strings, self.arrays, self.objects]
+            .iter().filter(|n| **n > 0).count()
+    }
+
+    fn length(&mut self, len: usize) {
+        self.min_len = Some(self.min_len.map_or(len, |m| m.min(len)));
+        self.max_len = Some(self.max_len.map_or(len, |m| m.max(len)));
+    }
+}
+
+/// Statistics over one or more documents by JSON Pointer path.
+/// Array elements are collected under `*`, so `/items/*/id` covers the ids of all items.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    paths: BTreeMap<String, PathStats>,
+    documents: usize,
+    max_depth: usize,
+}
+
+/// Profile all documents of `values`
+pub fn profile<'a>(values: impl IntoIterator<Item = &'a Value>) -> Profile {
+    let mut profile = Profile::new();
+    for v in values {
+        profile.add(v);
+    }
+    profile
+}
+
+impl Profile {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add one document to the statistics
+    pub fn add(&mut self, value: &Value) {
+        self.documents += 1;
+        self.walk(value, String::new(), 0);
+    }
+
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// Deepest nesting of arrays and objects seen
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn get(&self, path: &str) -> Option<&PathStats> {
+        self.paths.get(path)
+    }
+
+    /// All paths in lexical order
+    pub fn paths(&self) -> impl Iterator<Item = (&str, &PathStats)> {
+        self.paths.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    fn walk(&mut self, value: &Value, path: String, depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+        let stats = self.paths.entry(path.clone()).or_default();
+        stats.count += 1;
+
+        match value {
+            Value::Null => stats.nulls += 1,
+            Value::Bool(_) => stats.bools += 1,
+            Value::Number(n) => {
+                stats.numbers += 1;
+                if n.is_i64() || n.is_u64() {
+                    stats.integers += 1;
+                }
+            },
+            Value::String(s) => {
+                stats.strings += 1;
+                stats.length(s.chars().count());
+                if let Ok(n) = s.parse::<Number>() {
+                    stats.numeric_strings += 1;
+                    if n.is_i64() || n.is_u64() {
+                        stats.integers += 1;
+                    }
+                }
+                if matches!(s.to_lowercase().as_str(), "true" | "false" | "yes" | "no" | "on" | "off" | "0" | "1") {
+                    stats.bool_strings += 1;
+                }
+            },
+            Value::Array(a) => {
+                stats.arrays += 1;
+                stats.length(a.len());
+                for v in a.iter() {
+                    self.walk(v, format!("{}/*", path), depth + 1);
+                }
+            },
+            Value::Object(o) => {
+                stats.objects += 1;
+                for (k, v) in o.iter() {
+                    self.walk(v, patch::child(&path, k), depth + 1);
+                }
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    #[test]
+    fn profile_feed() {
+        let docs = vec!(
+            json!({"id": 1, "name": "a", "tags": ["x", "yy"]}),
+            json!({"id": "2", "name": null, "tags": []}),
+            json!({"id": 3, "name": "ccc", "tags": [["deep"]]}),
+        );
+        let profile = super::profile(&docs);
+
+        assert_eq!(profile.documents(), 3);
+        assert_eq!(profile.max_depth(), 3);
+
+        let id = profile.get("/id").unwrap();
+        assert_eq!((id.numbers, id.strings, id.numeric_strings, id.integers, id.type_count()), (2, 1, 1, 3, 2));
+
+        let name = profile.get("/name").unwrap();
+        assert!((name.null_rate() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!((name.min_len, name.max_len), (Some(1), Some(3)));
+
+        assert_eq!(profile.get("/tags/*").unwrap().count, 3);
+        assert_eq!(profile.get("/tags/*/*").unwrap().strings, 1);
+    }
+}
@@ -0,0 +1,139 @@
+//! Reading URL query strings and form bodies into json values
+//!
+//! Keys use the PHP / Rails bracket conventions: `a[b]=1` nests objects, `a[]=1`
+//! appends to an array and objects keyed `0`, `1`, ... become arrays, so
+//! `a=1&b[]=x&b[]=y&c[d]=z` reads as `{"a": "1", "b": ["x", "y"], "c": {"d": "z"}}`.
+//! All values are strings, the relaxed accessors read them as numbers or booleans.
+//! A repeated key without brackets keeps the last value.
+
+use serde_json::{Map, Value};
+
+use crate::limits::Limits;
+use crate::{patch, FromJsonError};
+
+/// Parse a query string, a leading `?` is skipped
+pub fn from_query(text: &str) -> Result<Value, FromJsonError> {
+    from_query_with(text, &Limits::default())
+}
+
+/// Parse an `application/x-www-form-urlencoded` body
+pub fn from_form(bytes: &[u8]) -> Result<Value, FromJsonError> {
+    from_query_with(&String::from_utf8_lossy(bytes), &Limits::default())
+}
+
+/// Parse a query string, failing on keys nested deeper than `limits` allow
+pub fn from_query_with(text: &str, limits: &Limits) -> Result<Value, FromJsonError> {
+    let text = text.strip_prefix('?').unwrap_or(text);
+    let mut root = Value::Object(Map::new());
+    for pair in text.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = match pair.find('=') {
+            Some(i) => (decode(&pair[..i]), decode(&pair[i + 1..])),
+            None => (decode(pair), String::new())
+        };
+        let segments = segments(&key);
+        if segments[0].is_empty() {
+            continue;
+        }
+        limits.check_depth(segments.len())?;
+        limits.check_string(&value)?;
+        patch::insert(&mut root, &segments, Value::String(value));
+    }
+    arrays(&mut root);
+    Ok(root)
+}
+
+/// `a[b][]` is `["a", "b", ""]`, keys with unbalanced brackets are taken literally
+fn segments(key: &str) -> Vec<&str> {
+    let (first, mut rest) = match key.find('[') {
+        Some(i) if i > 0 && key.ends_with(']') => (&key[..i], &key[i..]),
+        _ => return vec!(key)
+    };
+    let mut segments = vec!(first);
+    while let Some(inner) = rest.strip_prefix('[') {
+        match inner.find(']') {
+            Some(end) => {
+                segments.push(&inner[..end]);
+                rest = &inner[end + 1..];
+            },
+            None => return vec!(key)
+        }
+    }
+    match rest.is_empty() {
+        true => segments,
+        false => vec!(key)
+    }
+}
+
+/// Objects keyed `0` to `n - 1` become arrays
+fn arrays(value: &mut Value) {
+    match value {
+        Value::Object(o) => {
+            for v in o.values_mut() {
+                arrays(v);
+            }
+            let indexed = !o.is_empty() && (0..o.len()).all(|i| o.contains_key(&i.to_string()));
+            if indexed {
+                let mut o = std::mem::take(o);
+                *value = Value::Array((0..o.len()).filter_map(|i| o.remove(&i.to_string())).collect());
+            }
+        },
+        Value::Array(a) => {
+            for v in a.iter_mut() {
+                arrays(v);
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Percent decoding with `+` as space, invalid escapes are kept as they are
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |b: u8| (b as char).to_digit(16);
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(h), Some(l)) => {
+                        out.push((h * 16 + l) as u8);
+                        i += 2;
+                    },
+                    _ => out.push(b'%')
+                }
+            },
+            b => out.push(b)
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::limits::Limits;
+    use crate::MaybeValue;
+
+    #[test]
+    fn bracket_conventions() {
+        let value = super::from_query("?a=1&b[]=x&b[]=y&c[d]=z&e[0][n]=p&e[1][n]=q&name=J%C3%BCrgen+M&flag&bad=%zz&a=2").unwrap();
+        assert_eq!(value, json!({
+            "a": "2",
+            "b": ["x", "y"],
+            "c": {"d": "z"},
+            "e": [{"n": "p"}, {"n": "q"}],
+            "name": "Jürgen M",
+            "flag": "",
+            "bad": "%zz"
+        }));
+        assert_eq!(value.maybe_int("a").relaxed(), 2);
+
+        assert_eq!(super::from_form(b"x[=1&y]=2&z[a]b=3").unwrap(), json!({"x[": "1", "y]": "2", "z[a]b": "3"}));
+        assert!(super::from_query_with("a[b][c]=1", &Limits::new().max_depth(2)).is_err());
+    }
+}
@@ -0,0 +1,1029 @@
+//! Reading json documents from text and byte input
+
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_json::de::{IoRead, SliceRead};
+use serde_json::{Map, Number, Value};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+
+use crate::{limits::Limits, patch, FromJsonError, Maybe, TryFromJson};
+
+/// Struct and field name serde_json represents numbers with under arbitrary_precision
+#[cfg(feature = "arbitrary_precision")]
+pub(crate) const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Options for reading json input
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub limits: Limits,
+    /// Transcode UTF-16 and Latin-1 input to UTF-8 instead of failing
+    pub transcode: bool,
+    pub duplicate_keys: DuplicateKeys,
+    /// Accept `//` and `/* */` comments, trailing commas, unquoted keys and
+    /// single quoted strings in complete documents
+    pub lenient: bool,
+}
+
+/// What to do when an object contains the same key more than once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// The last value silently replaces earlier ones
+    #[default]
+    LastWins,
+    /// Keep the first value, the document is read as Relaxed
+    FirstWins,
+    /// Keep the last value and report each duplicate with its position as a warning
+    Warn,
+    /// Collect all values of the key into an array, the document is read as Relaxed
+    Merge,
+    /// Fail with an error at the duplicate key
+    Error,
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Limits enforced while parsing. Note that serde_json stops at a nesting
+    /// depth of 128 regardless of a higher `max_depth`.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn transcode(mut self, transcode: bool) -> Self {
+        self.transcode = transcode;
+        self
+    }
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Read the relaxed syntax of hand-edited files, see [`Options::lenient`].
+    /// Not supported by the streaming readers.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+}
+
+/// A parsed document together with the warnings collected while reading it
+pub struct Parsed {
+    pub value: Maybe<Value>,
+    pub warnings: Vec<FromJsonError>,
+}
+
+/// Parse a single json document from a byte slice
+pub fn from_slice(bytes: &[u8]) -> Result<Value, FromJsonError> {
+    from_slice_with(bytes, &Options::default())
+}
+
+/// Parse a single json document from a string
+pub fn from_str(text: &str) -> Result<Value, FromJsonError> {
+    from_slice(text.as_bytes())
+}
+
+/// Parse a single json document from a byte slice using `options`
+pub fn from_slice_with(bytes: &[u8], options: &Options) -> Result<Value, FromJsonError> {
+    match maybe_from_slice(bytes, options) {
+        Maybe::Strict(v) | Maybe::Relaxed(v) => Ok(v),
+        Maybe::Error(e) => Err(e),
+        Maybe::Null => Err(FromJsonError::unexpected())
+    }
+}
+
+/// Parse a single json document from a byte slice using `options`.
+/// The result is `Relaxed` if the input had a byte order mark, was transcoded,
+/// used the lenient syntax or had duplicate keys resolved by the
+/// [`DuplicateKeys`] policy.
+pub fn maybe_from_slice(bytes: &[u8], options: &Options) -> Maybe<Value> {
+    parse(bytes, options).value
+}
+
+/// Like [`maybe_from_slice`], additionally returning warnings such as duplicate keys
+/// found with [`DuplicateKeys::Warn`]
+pub fn parse(bytes: &[u8], options: &Options) -> Parsed {
+    let (text, clean) = match decode(bytes, options) {
+        Ok(decoded) => decoded,
+        Err(e) => return Parsed { value: Maybe::Error(e), warnings: Vec::new() }
+    };
+    let strict = match options.lenient {
+        true => Some(to_strict(&text)),
+        false => None
+    };
+    let (text, clean) = match &strict {
+        Some(strict) => (strict.text.as_slice(), clean && !strict.changed),
+        None => (text.as_ref(), clean)
+    };
+    let position = |e: FromJsonError| match &strict {
+        Some(strict) => strict.position(e),
+        None => e
+    };
+
+    let mut de = serde_json::Deserializer::from_slice(text);
+    let state = State::default();
+    let parsed = ValueSeed { options, depth: 0, state: &state }.deserialize(&mut de)
+        .and_then(|v| de.end().map(|_| v));
+
+    let value = match (parsed, clean && !state.relaxed.get()) {
+        (Ok(v), true) => Maybe::Strict(v),
+        (Ok(v), false) => Maybe::Relaxed(v),
+        (Err(e), _) => Maybe::Error(position(e.into()))
+    };
+
+    let warnings = match (&value, options.duplicate_keys) {
+        (Maybe::Relaxed(_), DuplicateKeys::Warn) => find_duplicate_keys(text).into_iter().map(position).collect(),
+        _ => Vec::new()
+    };
+
+    Parsed { value, warnings }
+}
+
+/// Parse a single json document from a string using `options`
+pub fn from_str_with(text: &str, options: &Options) -> Result<Value, FromJsonError> {
+    from_slice_with(text.as_bytes(), options)
+}
+
+/// Parse a single json document with simd-json using `options`.
+/// simd-json parses in place, so `bytes` is overwritten.
+#[cfg(feature = "simd-json")]
+pub fn from_slice_simd(bytes: &mut [u8], options: &Options) -> Result<Value, FromJsonError> {
+    let (mut owned, skip) = match decode(bytes, options)? {
+        (Cow::Owned(v), _) => (Some(v), 0),
+        (Cow::Borrowed(b), _) => (None, bytes.len() - b.len())
+    };
+    let text = match owned.as_mut() {
+        Some(v) => v.as_mut_slice(),
+        None => &mut bytes[skip..]
+    };
+
+    let simd_error = |e: simd_json::Error| FromJsonError::with_message(&e.to_string());
+    let mut de = simd_json::Deserializer::from_slice(text).map_err(simd_error)?;
+    let state = State::default();
+    ValueSeed { options, depth: 0, state: &state }.deserialize(&mut de).map_err(simd_error)
+}
+
+/// Strip a byte order mark and transcode to UTF-8 if enabled.
+/// The flag is false if anything had to be changed.
+fn decode<'a>(bytes: &'a [u8], options: &Options) -> Result<(Cow<'a, [u8]>, bool), FromJsonError> {
+    let utf16 = match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => return Ok((Cow::Borrowed(rest), false)),
+        [0xFF, 0xFE, rest @ ..] => Some((rest, false)),
+        [0xFE, 0xFF, rest @ ..] => Some((rest, true)),
+        // json starts with an ascii character, so a zero byte gives away UTF-16 without a BOM
+        [0, b, ..] if *b != 0 && options.transcode => Some((bytes, true)),
+        [b, 0, ..] if *b != 0 && options.transcode => Some((bytes, false)),
+        _ => None
+    };
+
+    match utf16 {
+        Some(_) if !options.transcode => Err(FromJsonError::with_message("UTF-16 input, transcoding is disabled")),
+        Some((rest, big_endian)) => {
+            let units = rest.chunks(2).map(|c| match (c, big_endian) {
+                ([hi, lo], true) => u16::from_be_bytes([*hi, *lo]),
+                ([lo, hi], false) => u16::from_le_bytes([*lo, *hi]),
+                _ => 0xFFFD
+            });
+            let text: String = char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect();
+            Ok((Cow::Owned(text.into_bytes()), false))
+        },
+        None => {
+            match std::str::from_utf8(bytes) {
+                Ok(_) => Ok((Cow::Borrowed(bytes), true)),
+                // Latin-1 maps every byte to the code point of the same value
+                Err(_) if options.transcode => Ok((Cow::Owned(bytes.iter().map(|b| *b as char).collect::<String>().into_bytes()), false)),
+                Err(_) => Ok((Cow::Borrowed(bytes), true))
+            }
+        }
+    }
+}
+
+/// Locate repeated keys in (valid) json text, already known to contain duplicates
+fn find_duplicate_keys(text: &[u8]) -> Vec<FromJsonError> {
+    let mut found = Vec::new();
+    // one entry per open container, None for arrays
+    let mut open: Vec<Option<HashSet<Cow<str>>>> = Vec::new();
+    let mut expect_key = false;
+
+    let mut i = 0;
+    while i < text.len() {
+        match text[i] {
+            b'{' => {
+                open.push(Some(HashSet::new()));
+                expect_key = true;
+            },
+            b'[' => {
+                open.push(None);
+                expect_key = false;
+            },
+            b'}' | b']' => {
+                open.pop();
+            },
+            b',' => {
+                expect_key = matches!(open.last(), Some(Some(_)));
+            },
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < text.len() && text[i] != b'"' {
+                    i += if text[i] == b'\\' { 2 } else { 1 };
+                }
+                if let (true, Some(Some(keys))) = (expect_key, open.last_mut()) {
+                    let literal = &text[start..=i.min(text.len() - 1)];
+                    let key = match literal.contains(&b'\\') {
+                        true => Cow::Owned(serde_json::from_slice::<String>(literal).unwrap_or_default()),
+                        false => String::from_utf8_lossy(&literal[1..literal.len() - 1])
+                    };
+                    if keys.contains(&key) {
+                        let line = text[..start].iter().filter(|b| **b == b'\n').count() + 1;
+                        let column = start - text[..start].iter().rposition(|b| *b == b'\n').map(|p| p + 1).unwrap_or(0) + 1;
+                        found.push(FromJsonError::with_message(&format!("duplicate key `{}`", key)).with_position(line, column));
+                    } else {
+                        keys.insert(key);
+                    }
+                    expect_key = false;
+                }
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    found
+}
+
+/// Text in the lenient syntax rewritten as strict json: comments and trailing
+/// commas are blanked out, unquoted keys and single quoted strings double
+/// quoted. Lines stay as they are, `shifts` records where columns moved.
+struct Strict {
+    text: Vec<u8>,
+    changed: bool,
+    /// Line, column in the rewritten text and the number of bytes inserted there
+    shifts: Vec<(usize, usize, isize)>,
+}
+
+impl Strict {
+    /// Move the position of an error in the rewritten text back to the original
+    fn position(&self, e: FromJsonError) -> FromJsonError {
+        match e.position() {
+            Some((line, column)) => {
+                let shift: isize = self.shifts.iter().filter(|s| s.0 == line && s.1 < column).map(|s| s.2).sum();
+                e.with_position(line, (column as isize - shift).max(1) as usize)
+            },
+            None => e
+        }
+    }
+}
+
+/// End of the whitespace and comments starting at `pos`
+fn blanks_end(text: &[u8], mut pos: usize) -> usize {
+    loop {
+        match &text[pos.min(text.len())..] {
+            [b' ' | b'\t' | b'\r' | b'\n', ..] => pos += 1,
+            [b'/', b'/' | b'*', ..] => pos = comment_end(text, pos),
+            _ => return pos
+        }
+    }
+}
+
+/// End of the comment starting at `pos`, the end of the text if it is unterminated
+fn comment_end(text: &[u8], pos: usize) -> usize {
+    let (close, skip): (&[u8], usize) = match text.get(pos + 1) {
+        Some(b'*') => (b"*/", 2),
+        _ => (b"\n", 0)
+    };
+    match text[pos + 2..].windows(close.len()).position(|w| w == close) {
+        Some(p) => pos + 2 + p + skip,
+        None => text.len()
+    }
+}
+
+fn to_strict(text: &[u8]) -> Strict {
+    let mut strict = Strict { text: Vec::with_capacity(text.len()), changed: false, shifts: Vec::new() };
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut i = 0;
+
+    while i < text.len() {
+        let column = strict.text.len() - line_start + 1;
+        match text[i] {
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < text.len() && text[i] != b'"' {
+                    i += if text[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(text.len());
+                strict.text.extend_from_slice(&text[start..i]);
+            },
+            b'\'' => {
+                strict.changed = true;
+                strict.text.push(b'"');
+                i += 1;
+                while i < text.len() && text[i] != b'\'' {
+                    match (text[i], text.get(i + 1)) {
+                        (b'\\', Some(b'\'')) => {
+                            strict.shifts.push((line, strict.text.len() - line_start, -1));
+                            strict.text.push(b'\'');
+                            i += 2;
+                        },
+                        (b'\\', Some(c)) => {
+                            strict.text.extend_from_slice(&[b'\\', *c]);
+                            i += 2;
+                        },
+                        (b'"', _) => {
+                            strict.shifts.push((line, strict.text.len() - line_start + 1, 1));
+                            strict.text.extend_from_slice(b"\\\"");
+                            i += 1;
+                        },
+                        (c, _) => {
+                            strict.text.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                if i < text.len() {
+                    strict.text.push(b'"');
+                    i += 1;
+                }
+            },
+            b'/' if matches!(text.get(i + 1), Some(b'/' | b'*')) => {
+                strict.changed = true;
+                let end = comment_end(text, i);
+                for b in &text[i..end] {
+                    strict.text.push(if *b == b'\n' { b'\n' } else { b' ' });
+                    if *b == b'\n' {
+                        line += 1;
+                        line_start = strict.text.len();
+                    }
+                }
+                i = end;
+            },
+            b',' if matches!(text.get(blanks_end(text, i + 1)), Some(b'}' | b']')) => {
+                strict.changed = true;
+                strict.text.push(b' ');
+                i += 1;
+            },
+            c if c.is_ascii_alphanumeric() || c == b'_' || c == b'$' => {
+                let start = i;
+                while i < text.len() && (text[i].is_ascii_alphanumeric() || matches!(text[i], b'_' | b'$' | b'.' | b'+' | b'-')) {
+                    i += 1;
+                }
+                let key = !c.is_ascii_digit() && text.get(blanks_end(text, i)) == Some(&b':');
+                if key {
+                    strict.changed = true;
+                    strict.shifts.push((line, column, 1));
+                    strict.shifts.push((line, column + i - start + 1, 1));
+                    strict.text.push(b'"');
+                }
+                strict.text.extend_from_slice(&text[start..i]);
+                if key {
+                    strict.text.push(b'"');
+                }
+            },
+            c => {
+                strict.text.push(c);
+                i += 1;
+                if c == b'\n' {
+                    line += 1;
+                    line_start = strict.text.len();
+                }
+            }
+        }
+    }
+    strict
+}
+
+/// The line each value of the json document `text` starts on by JSON Pointer,
+/// for members the line of the key. The text is scanned without being
+/// validated, for a document that does not parse the result is incomplete.
+/// The lenient syntax of [`Options::lenient`] is understood.
+pub fn value_lines(text: &str) -> BTreeMap<String, usize> {
+    struct Frame {
+        array: bool,
+        index: usize,
+        // the member or element being read has its segment on `path`
+        entered: bool,
+    }
+
+    let strict = to_strict(text.as_bytes());
+    let text = strict.text.as_slice();
+    let mut lines = BTreeMap::new();
+    let mut open: Vec<Frame> = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut expect_key = false;
+    let mut line = 1;
+
+    let pointer = |path: &[String]| path.iter().fold(String::new(), |p, s| patch::child(&p, s));
+    let start_value = |open: &mut Vec<Frame>, path: &mut Vec<String>, lines: &mut BTreeMap<String, usize>, line: usize| {
+        match open.last_mut() {
+            Some(top) if top.array && !top.entered => {
+                path.push(top.index.to_string());
+                top.entered = true;
+                lines.entry(pointer(path)).or_insert(line);
+            },
+            None => {
+                lines.entry(String::new()).or_insert(line);
+            },
+            Some(_) => {}
+        }
+    };
+
+    let mut i = 0;
+    while i < text.len() {
+        match text[i] {
+            b'\n' => line += 1,
+            b' ' | b'\t' | b'\r' | b':' => {},
+            c @ (b'{' | b'[') => {
+                start_value(&mut open, &mut path, &mut lines, line);
+                open.push(Frame { array: c == b'[', index: 0, entered: false });
+                expect_key = c == b'{';
+            },
+            b'}' | b']' => {
+                if let Some(Frame { entered: true, .. }) = open.pop() {
+                    path.pop();
+                }
+                expect_key = false;
+            },
+            b',' => {
+                if let Some(top) = open.last_mut() {
+                    if top.entered {
+                        path.pop();
+                        top.entered = false;
+                    }
+                    top.index += 1;
+                    expect_key = !top.array;
+                }
+            },
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < text.len() && text[i] != b'"' {
+                    i += if text[i] == b'\\' { 2 } else { 1 };
+                }
+                match open.last_mut() {
+                    Some(top) if expect_key && !top.array => {
+                        let literal = &text[start..=i.min(text.len() - 1)];
+                        let key = match literal.contains(&b'\\') {
+                            true => serde_json::from_slice::<String>(literal).unwrap_or_default(),
+                            false => String::from_utf8_lossy(&literal[1..literal.len() - 1]).into_owned()
+                        };
+                        path.push(key);
+                        top.entered = true;
+                        lines.entry(pointer(&path)).or_insert(line);
+                        expect_key = false;
+                    },
+                    _ => start_value(&mut open, &mut path, &mut lines, line)
+                }
+            },
+            _ => start_value(&mut open, &mut path, &mut lines, line)
+        }
+        i += 1;
+    }
+    lines
+}
+
+/// Read and parse a single json document from a reader
+pub fn from_reader<R: Read>(mut reader: R) -> Result<Value, FromJsonError> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    from_slice(&buf)
+}
+
+/// Read and parse a single json document from a reader using `options`
+pub fn from_reader_with<R: Read>(mut reader: R, options: &Options) -> Result<Value, FromJsonError> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    from_slice_with(&buf, options)
+}
+
+/// Iterate over newline delimited json (one document per line) read from `reader`.
+/// Blank lines are skipped.
+pub fn ndjson<R: BufRead>(reader: R) -> NdJson<R> {
+    NdJson {
+        lines: reader.lines()
+    }
+}
+
+/// Iterator over the documents of a newline delimited json input, see [`ndjson`]
+pub struct NdJson<R> {
+    lines: io::Lines<R>
+}
+
+impl <R: BufRead> Iterator for NdJson<R> {
+    type Item = Result<Value, FromJsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next()? {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => return Some(from_str(&line)),
+                Err(e) => return Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+
+/// Iterate over json documents that are concatenated or separated by whitespace,
+/// like `{"a": 1} {"a": 2}[3]`.
+pub fn documents(bytes: &[u8]) -> Documents<'_, SliceRead<'_>> {
+    Documents::new(serde_json::Deserializer::from_slice(bytes))
+}
+
+/// Iterate over concatenated json documents read from `reader`, see [`documents`]
+pub fn documents_from_reader<R: Read>(reader: R) -> Documents<'static, IoRead<R>> {
+    Documents::new(serde_json::Deserializer::from_reader(reader))
+}
+
+/// Iterator over concatenated json documents.
+/// Iteration stops after the first error, as the input can not be resynchronized.
+pub struct Documents<'de, R> {
+    de: serde_json::Deserializer<R>,
+    options: Options,
+    done: bool,
+    input: PhantomData<&'de ()>,
+}
+
+impl <'de, R: serde_json::de::Read<'de>> Documents<'de, R> {
+
+    fn new(de: serde_json::Deserializer<R>) -> Self {
+        Documents {
+            de,
+            options: Options::default(),
+            done: false,
+            input: PhantomData,
+        }
+    }
+
+    /// Parse each document with `options`
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl <'de, R: serde_json::de::Read<'de>> Iterator for Documents<'de, R> {
+    type Item = Result<Value, FromJsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // end() skips whitespace and only succeeds if nothing else is left
+        if self.done || self.de.end().is_ok() {
+            self.done = true;
+            return None;
+        }
+
+        let state = State::default();
+        match (ValueSeed { options: &self.options, depth: 0, state: &state }).deserialize(&mut self.de) {
+            Ok(v) => Some(Ok(v)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+
+/// Iterate over the elements of a top-level json array read from `reader`,
+/// converting each one to `T` as soon as it is complete. Only one element is
+/// held in memory at a time, so arbitrarily large dumps can be processed.
+pub fn array_elements<T: TryFromJson, R: Read>(reader: R) -> ArrayElements<T, R> {
+    ArrayElements {
+        bytes: io::BufReader::new(reader).bytes(),
+        options: Options::default(),
+        element: Vec::new(),
+        line: 1,
+        column: 0,
+        started: false,
+        separated: false,
+        done: false,
+        target: PhantomData,
+    }
+}
+
+/// Iterator over the elements of a json array, see [`array_elements`].
+/// Elements that can not be converted are reported and skipped, iteration
+/// stops after the first syntax error.
+pub struct ArrayElements<T, R> {
+    bytes: io::Bytes<io::BufReader<R>>,
+    options: Options,
+    element: Vec<u8>,
+    line: usize,
+    column: usize,
+    started: bool,
+    separated: bool,
+    done: bool,
+    target: PhantomData<fn() -> T>,
+}
+
+impl <T: TryFromJson, R: Read> ArrayElements<T, R> {
+
+    /// Parse each element with `options`, the limits apply per element
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn byte(&mut self) -> Result<Option<u8>, FromJsonError> {
+        match self.bytes.next() {
+            Some(Ok(b)) => {
+                match b {
+                    b'\n' => {
+                        self.line += 1;
+                        self.column = 0;
+                    },
+                    _ => self.column += 1
+                }
+                Ok(Some(b))
+            },
+            Some(Err(e)) => Err(e.into()),
+            None => Ok(None)
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, FromJsonError> {
+        loop {
+            match self.byte()? {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => continue,
+                other => return Ok(other)
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> FromJsonError {
+        FromJsonError::with_message(message).with_position(self.line, self.column)
+    }
+
+    /// Collect the bytes of the next element into `self.element` and return
+    /// its position, None at the end of the array
+    fn scan(&mut self) -> Result<Option<(usize, usize)>, FromJsonError> {
+        if !self.started {
+            self.started = true;
+            match self.skip_whitespace()? {
+                Some(b'[') => {},
+                _ => return Err(self.error("expected a json array"))
+            }
+        }
+
+        self.element.clear();
+        let mut first = self.skip_whitespace()?;
+        // after a comma another element must follow, `[1,]` is an error
+        if first == Some(b']') && !self.separated {
+            return self.end().map(|_| None);
+        }
+        let start = (self.line, self.column);
+        let (mut depth, mut string, mut escape) = (0usize, false, false);
+        loop {
+            let b = match first.take() {
+                Some(b) => b,
+                None => match self.byte()? {
+                    Some(b) => b,
+                    None => return Err(self.error("EOF while parsing a list"))
+                }
+            };
+            match (string, b) {
+                (true, _) if escape => escape = false,
+                (true, b'\\') => escape = true,
+                (true, b'"') => string = false,
+                (true, _) => {},
+                (false, b'"') => string = true,
+                (false, b'[' | b'{') => depth += 1,
+                (false, b']' | b'}') if depth > 0 => depth -= 1,
+                (false, b',' | b']') if depth == 0 => {
+                    if self.element.iter().all(|b| b.is_ascii_whitespace()) {
+                        return Err(self.error("expected value"));
+                    }
+                    self.separated = b == b',';
+                    if b == b']' {
+                        self.end()?;
+                    }
+                    return Ok(Some(start));
+                },
+                _ => {}
+            }
+            self.element.push(b);
+        }
+    }
+
+    /// Only whitespace may follow the closing bracket
+    fn end(&mut self) -> Result<(), FromJsonError> {
+        self.done = true;
+        match self.skip_whitespace()? {
+            None => Ok(()),
+            Some(_) => Err(self.error("trailing characters"))
+        }
+    }
+}
+
+impl <T: TryFromJson, R: Read> Iterator for ArrayElements<T, R> {
+    type Item = Result<T, FromJsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (line, column) = match self.scan() {
+            Ok(Some(start)) => start,
+            Ok(None) => return None,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+        let value = match from_slice_with(&self.element, &self.options) {
+            Ok(v) => v,
+            Err(e) => {
+                // positions are relative to the element
+                self.done = true;
+                let e = match e.position() {
+                    Some((1, c)) => e.with_position(line, column + c - 1),
+                    Some((l, c)) => e.with_position(line + l - 1, c),
+                    None => e
+                };
+                return Some(Err(e));
+            }
+        };
+        Some(T::try_from_json(&value))
+    }
+}
+
+
+/// Shared by all ValueSeeds of one document
+#[derive(Default)]
+struct State {
+    nodes: Cell<usize>,
+    relaxed: Cell<bool>,
+}
+
+/// Builds a Value while enforcing the configured options
+struct ValueSeed<'a> {
+    options: &'a Options,
+    depth: usize,
+    state: &'a State,
+}
+
+impl <'a> ValueSeed<'a> {
+
+    fn node<E: de::Error>(&self) -> Result<(), E> {
+        let nodes = &self.state.nodes;
+        nodes.set(nodes.get() + 1);
+        self.options.limits.check_nodes(nodes.get()).map_err(|e| E::custom(e.msg))
+    }
+
+    fn string<E: de::Error>(&self, s: &str) -> Result<(), E> {
+        self.options.limits.check_string(s).map_err(|e| E::custom(e.msg))
+    }
+
+    fn child<E: de::Error>(&self) -> Result<ValueSeed<'a>, E> {
+        self.options.limits.check_depth(self.depth + 1).map_err(|e| E::custom(e.msg))?;
+        Ok(ValueSeed { options: self.options, depth: self.depth + 1, state: self.state })
+    }
+}
+
+impl <'de, 'a> DeserializeSeed<'de> for ValueSeed<'a> {
+    type Value = Value;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl <'de, 'a> Visitor<'de> for ValueSeed<'a> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any json value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        self.node()?;
+        Ok(Value::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        self.node()?;
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Value, E> {
+        self.node()?;
+        Ok(Value::Number(n.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+        self.node()?;
+        Ok(Value::Number(n.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Value, E> {
+        self.node()?;
+        Ok(Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        self.node()?;
+        self.string(s)?;
+        Ok(Value::String(s!(s)))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        self.node()?;
+        self.string(&s)?;
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        self.node()?;
+        let mut collect = Vec::new();
+        while let Some(v) = seq.next_element_seed(self.child()?)? {
+            collect.push(v);
+        }
+        Ok(Value::Array(collect))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        self.node()?;
+        let mut collect = Map::new();
+        let mut merged = HashSet::new();
+        while let Some(k) = map.next_key::<String>()? {
+            #[cfg(feature = "arbitrary_precision")]
+            if k == NUMBER_TOKEN {
+                let digits: String = map.next_value()?;
+                return digits.parse::<Number>().map(Value::Number).map_err(de::Error::custom);
+            }
+            self.string(&k)?;
+            let v = map.next_value_seed(self.child()?)?;
+
+            if !collect.contains_key(&k) {
+                collect.insert(k, v);
+                continue;
+            }
+
+            match self.options.duplicate_keys {
+                DuplicateKeys::LastWins => {
+                    collect.insert(k, v);
+                },
+                DuplicateKeys::Warn => {
+                    self.state.relaxed.set(true);
+                    collect.insert(k, v);
+                },
+                DuplicateKeys::FirstWins => {
+                    self.state.relaxed.set(true);
+                },
+                DuplicateKeys::Merge => {
+                    self.state.relaxed.set(true);
+                    let existing = collect.get_mut(&k).expect("checked above");
+                    match (existing, merged.contains(&k)) {
+                        (Value::Array(a), true) => a.push(v),
+                        (existing, _) => {
+                            *existing = Value::Array(vec!(existing.take(), v));
+                            merged.insert(k);
+                        }
+                    }
+                },
+                DuplicateKeys::Error => {
+                    return Err(de::Error::custom(format!("duplicate key `{}`", k)));
+                }
+            }
+        }
+        Ok(Value::Object(collect))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::MaybeValue;
+
+    #[test]
+    fn value_lines() {
+        let text = "{\n  \"server\": {\n    \"port\": 80, \"a\\\"b\": \"x,]\"\n  },\n  \"tags\": [\n    1,\n    [2, {\"k\": null}]\n  ]\n}";
+        let lines = super::value_lines(text);
+        let found: Vec<(&str, usize)> = lines.iter().map(|(p, l)| (p.as_str(), *l)).collect();
+        assert_eq!(found, vec!(
+            ("", 1), ("/server", 2), ("/server/a\"b", 3), ("/server/port", 3),
+            ("/tags", 5), ("/tags/0", 6), ("/tags/1", 7), ("/tags/1/0", 7), ("/tags/1/1", 7), ("/tags/1/1/k", 7)
+        ));
+    }
+
+    #[test]
+    fn lenient_syntax() {
+        use super::Options;
+
+        let lenient = Options::new().lenient(true);
+        let text = "// settings\n{a: 1, /* b */ 'b': ['it\\'s', \"x\",],\n $c_1: 'say \"hi\"', // done\n}";
+        assert_eq!(super::maybe_from_slice(text.as_bytes(), &lenient).relaxed(), json!({"a": 1, "b": ["it's", "x"], "$c_1": "say \"hi\""}));
+        assert!(super::from_str(text).is_err());
+        let strict = b"{\"a\": [1e5, true]}";
+        assert_eq!(super::maybe_from_slice(strict, &lenient).strict(), super::from_slice(strict).ok());
+
+        // positions refer to the original text
+        let broken = super::from_str_with("{\n  key: 'a\"b' x}", &lenient).unwrap_err();
+        assert_eq!(broken.position(), Some((2, 14)));
+        assert_eq!(super::value_lines("{\n  // c\n  a: {\n    'b': 1,\n  },\n}").get("/a/b"), Some(&4));
+    }
+
+    #[test]
+    fn ndjson_lines() {
+        let input = "{\"id\": 1}\n\n{\"id\": \"2\"}\nnope\n";
+        let docs: Vec<_> = super::ndjson(input.as_bytes()).collect();
+
+        assert_eq!(docs.len(), 3);
+        assert_eq!(docs[0].as_ref().unwrap().maybe_int("id").strict(), Some(1));
+        assert_eq!(docs[1].as_ref().unwrap().maybe_int("id").relaxed(), 2);
+        assert!(docs[2].is_err());
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        use super::{DuplicateKeys, Options};
+
+        let input = "{\"a\": 1,\n \"b\": {\"a\": 0}, \"a\": 2, \"a\": [3]}";
+        let read = |policy| super::maybe_from_slice(input.as_bytes(), &Options::new().duplicate_keys(policy));
+
+        assert_eq!(read(DuplicateKeys::LastWins).strict(), Some(json!({"a": [3], "b": {"a": 0}})));
+        assert_eq!(read(DuplicateKeys::FirstWins).relaxed(), json!({"a": 1, "b": {"a": 0}}));
+        assert_eq!(read(DuplicateKeys::Merge).relaxed(), json!({"a": [1, 2, [3]], "b": {"a": 0}}));
+        assert_eq!(read(DuplicateKeys::Error).strict_ok().unwrap_err().position(), Some((2, 22)));
+
+        let parsed = super::parse(input.as_bytes(), &Options::new().duplicate_keys(DuplicateKeys::Warn));
+        let positions: Vec<_> = parsed.warnings.iter().map(|w| w.position()).collect();
+        assert_eq!(positions, vec!(Some((2, 17)), Some((2, 25))));
+        assert_eq!(parsed.value.relaxed(), json!({"a": [3], "b": {"a": 0}}));
+    }
+
+    #[test]
+    fn array_elements() {
+        use serde_json::Value;
+
+        use crate::{FromJsonError, TryFromJson};
+
+        struct Id(i64);
+
+        impl TryFromJson for Id {
+            fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+                value.maybe_int("id").strict_ok().map(Id)
+            }
+        }
+
+        let input = " [{\"id\": 1, \"tags\": [\"a]\", \"\\\"\"]}, {\"id\": \"2\"},\n {\"id\": 3}\n] ";
+        let ids: Vec<_> = super::array_elements::<Id, _>(input.as_bytes()).map(|r| r.map(|id| id.0).ok()).collect();
+        assert_eq!(ids, vec!(Some(1), None, Some(3)));
+        assert_eq!(super::array_elements::<Value, _>("[]".as_bytes()).count(), 0);
+
+        let read: Vec<_> = super::array_elements::<Value, _>("[1,\n  2x, 3]".as_bytes()).collect();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[1].as_ref().unwrap_err().position(), Some((2, 4)));
+        assert!(super::array_elements::<Value, _>("[1,]".as_bytes()).nth(1).unwrap().is_err());
+        assert!(super::array_elements::<Value, _>("{\"a\": 1}".as_bytes()).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn concatenated_documents() {
+        let input = " {\"a\": 1}{\"a\": 2}\n[3]  \"four\"\n";
+        let docs: Vec<_> = super::documents(input.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(docs, vec!(json!({"a": 1}), json!({"a": 2}), json!([3]), json!("four")));
+
+        let docs: Vec<_> = super::documents_from_reader("[1] [2 [3]".as_bytes()).collect();
+        assert_eq!(docs.len(), 2);
+        assert!(docs[1].is_err());
+    }
+
+    #[test]
+    fn bom_and_transcoding() {
+        let transcode = super::Options::new().transcode(true);
+
+        assert_eq!(super::from_slice(b"\xEF\xBB\xBF[1]").unwrap(), json!([1]));
+        assert!(super::maybe_from_slice(b"\xEF\xBB\xBF[1]", &transcode).strict().is_none());
+        assert!(super::maybe_from_slice(b"[1]", &transcode).strict().is_some());
+
+        let utf16: Vec<u8> = "\u{feff}[\"\u{e4}\"]".encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        assert!(super::from_slice(&utf16).is_err());
+        assert_eq!(super::from_slice_with(&utf16, &transcode).unwrap(), json!(["\u{e4}"]));
+
+        assert_eq!(super::from_slice_with(b"[\"\xE4\"]", &transcode).unwrap(), json!(["\u{e4}"]));
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn simd_parsing() {
+        use super::{DuplicateKeys, Options};
+
+        let mut input = b"\xEF\xBB\xBF{\"a\": [1, 2.5, \"x\"], \"a\": null}".to_vec();
+        assert_eq!(super::from_slice_simd(&mut input.clone(), &Options::new()).unwrap(), json!({"a": null}));
+        assert!(super::from_slice_simd(&mut input, &Options::new().duplicate_keys(DuplicateKeys::Error)).is_err());
+        assert!(super::from_slice_simd(&mut b"[1,".to_vec(), &Options::new()).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Redaction of sensitive values for safe logging
+
+use serde_json::Value;
+
+use crate::pattern::{self, PathPattern};
+
+/// Default replacement for redacted values
+pub const PLACEHOLDER: &str = "[REDACTED]";
+
+/// Replace all values matching one of `patterns` with [`PLACEHOLDER`], see [`Redactor`]
+pub fn redact(value: &mut Value, patterns: &[&str]) {
+    Redactor::new(patterns).apply(value)
+}
+
+/// Replaces values under configured keys or paths.
+///
+/// A pattern like `"password"` or `"*token*"` matches object keys at any depth
+/// (case-insensitive), a pattern starting with `/` like `"/servers/*/key"` is a
+/// JSON Pointer whose segments are globs, `**` spans any number of segments.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<PathPattern>,
+    placeholder: Value,
+}
+
+impl Redactor {
+
+    pub fn new(patterns: &[&str]) -> Self {
+        Redactor {
+            patterns: patterns.iter().map(|p| match p.starts_with('/') {
+                true => PathPattern::new(p),
+                false => PathPattern::new(&p.to_lowercase()),
+            }).collect(),
+            placeholder: Value::String(s!(PLACEHOLDER)),
+        }
+    }
+
+    /// Use `placeholder` instead of [`PLACEHOLDER`]
+    pub fn placeholder(mut self, placeholder: impl Into<Value>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+
+    /// Redact `value` in place
+    pub fn apply(&self, value: &mut Value) {
+        self.walk(value, &mut Vec::new());
+    }
+
+    /// A redacted copy of `value`
+    pub fn redacted(&self, value: &Value) -> Value {
+        let mut copy = value.clone();
+        self.apply(&mut copy);
+        copy
+    }
+
+    fn matches(&self, path: &[String]) -> bool {
+        let key = path.last().map(|k| k.to_lowercase());
+        self.patterns.iter().any(|p| match (p, &key) {
+            (PathPattern::Key(g), Some(k)) => pattern::glob(g, k),
+            (PathPattern::Key(_), None) => false,
+            (PathPattern::Path(_), _) => p.matches(path)
+        })
+    }
+
+    fn walk(&self, value: &mut Value, path: &mut Vec<String>) {
+        match value {
+            Value::Object(o) => {
+                for (k, v) in o.iter_mut() {
+                    path.push(k.clone());
+                    match self.matches(path) {
+                        true => *v = self.placeholder.clone(),
+                        false => self.walk(v, path)
+                    }
+                    path.pop();
+                }
+            },
+            Value::Array(a) => {
+                for (i, v) in a.iter_mut().enumerate() {
+                    path.push(i.to_string());
+                    // only path patterns can match array elements
+                    match self.patterns.iter().any(|p| matches!(p, PathPattern::Path(_)) && p.matches(path)) {
+                        true => *v = self.placeholder.clone(),
+                        false => self.walk(v, path)
+                    }
+                    path.pop();
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Redactor;
+
+    #[test]
+    fn redact_keys_and_paths() {
+        let mut doc = json!({
+            "user": "bob",
+            "Password": "hunter2",
+            "auth": {"access_token": "abc", "refresh_token": {"value": "def"}},
+            "servers": [{"host": "a", "key": "k1"}, {"host": "b", "key": "k2"}]
+        });
+        super::redact(&mut doc, &["password", "*token", "/servers/*/key"]);
+
+        assert_eq!(doc, json!({
+            "user": "bob",
+            "Password": "[REDACTED]",
+            "auth": {"access_token": "[REDACTED]", "refresh_token": "[REDACTED]"},
+            "servers": [{"host": "a", "key": "[REDACTED]"}, {"host": "b", "key": "[REDACTED]"}]
+        }));
+
+        let hidden = Redactor::new(&["/servers/1"]).placeholder(json!(null)).redacted(&doc);
+        assert_eq!(hidden["servers"][1], json!(null));
+    }
+}
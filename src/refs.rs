@@ -0,0 +1,219 @@
+//! Expanding `$ref` references
+//!
+//! An object `{"$ref": "#/definitions/x"}` is replaced by the value at that
+//! JSON Pointer of the same document, `"name#/pointer"` or `"name"` refer to a
+//! [registered](Refs::document) document. Other members next to `$ref`
+//! override those of the referenced object, so shared fragments can be
+//! adjusted where they are used. Pointers address documents as written, not
+//! through other references. References that can not be expanded, cycles
+//! included, are left in place and reported, as are those that would grow the
+//! document past the node limit of [`Refs::limits`]:
+//!
+//! ```
+//! use json_relaxed::refs::resolve_refs;
+//! use serde_json::json;
+//!
+//! let mut doc = json!({
+//!     "definitions": {"pool": {"size": 4, "timeout": 30}},
+//!     "db": {"$ref": "#/definitions/pool"},
+//!     "cache": {"$ref": "#/definitions/pool", "size": 16},
+//! });
+//! assert!(resolve_refs(&mut doc).is_empty());
+//! assert_eq!(doc["cache"], json!({"size": 16, "timeout": 30}));
+//! ```
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::limits::{estimated_size, Limits};
+use crate::patch;
+
+/// Nodes references may add by default, a guard against documents that
+/// reference the same fragment over and over
+pub const DEFAULT_MAX_NODES: usize = 1 << 20;
+
+/// A reference that could not be expanded and was left in place
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    /// JSON Pointer to the object containing `$ref`
+    pub path: String,
+    pub reference: String,
+    /// The reference is part of a cycle
+    pub cycle: bool,
+    /// Expanding the reference would exceed the node limit
+    pub limit: bool,
+}
+
+/// Expand references within `value`, see [`Refs`]
+pub fn resolve_refs(value: &mut Value) -> Vec<Unresolved> {
+    Refs::new().apply(value)
+}
+
+/// Reference expansion with other documents to refer to
+#[derive(Debug, Clone)]
+pub struct Refs {
+    documents: HashMap<String, Value>,
+    limits: Limits,
+}
+
+impl Default for Refs {
+    fn default() -> Self {
+        Refs { documents: HashMap::new(), limits: Limits::new().max_nodes(DEFAULT_MAX_NODES) }
+    }
+}
+
+impl Refs {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Make `value` available to references as `name`, like `"name#/a/b"`
+    pub fn document(mut self, name: &str, value: Value) -> Self {
+        self.documents.insert(s!(name), value);
+        self
+    }
+
+    /// Limit the number of nodes expanding references may add with `max_nodes`,
+    /// [`DEFAULT_MAX_NODES`] by default
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn apply(&self, value: &mut Value) -> Vec<Unresolved> {
+        let original = value.clone();
+        let mut unresolved = Vec::new();
+        let mut expansion = Expansion { stack: Vec::new(), nodes: 0, unresolved: &mut unresolved };
+        self.walk(value, (None, &original), String::new(), &mut expansion);
+        unresolved
+    }
+
+    /// Expand the references of `value`, found at `path` in document `doc`
+    fn walk(&self, value: &mut Value, doc: (Option<&str>, &Value), path: String, expansion: &mut Expansion) {
+        let reference = match value {
+            Value::Object(o) => match o.get("$ref") {
+                Some(Value::String(r)) => s!(r),
+                _ => {
+                    for (k, v) in o.iter_mut() {
+                        self.walk(v, doc, patch::child(&path, k), expansion);
+                    }
+                    return;
+                }
+            },
+            Value::Array(a) => {
+                for (i, v) in a.iter_mut().enumerate() {
+                    self.walk(v, doc, patch::child(&path, &i.to_string()), expansion);
+                }
+                return;
+            },
+            _ => return
+        };
+
+        let (name, pointer) = match reference.split_once('#') {
+            Some(("", pointer)) => (doc.0, pointer),
+            Some((name, pointer)) => (Some(name), pointer),
+            None => (Some(reference.as_str()), "")
+        };
+        let target_doc = match name {
+            None => Some(doc.1),
+            Some(name) if doc.0 == Some(name) => Some(doc.1),
+            Some(name) => self.documents.get(name)
+        };
+        let key = format!("{}#{}", name.unwrap_or_default(), pointer);
+        let unresolved = |cycle, limit| Unresolved { path: path.clone(), reference: reference.clone(), cycle, limit };
+        let target = match target_doc.and_then(|d| d.pointer(pointer)) {
+            _ if expansion.stack.contains(&key) => return expansion.unresolved.push(unresolved(true, false)),
+            Some(target) => target,
+            None => return expansion.unresolved.push(unresolved(false, false))
+        };
+        expansion.nodes += estimated_size(target).nodes;
+        if self.limits.check_nodes(expansion.nodes).is_err() {
+            return expansion.unresolved.push(unresolved(false, true));
+        }
+        let mut target = target.clone();
+
+        expansion.stack.push(key);
+        let target_doc = (name, target_doc.unwrap_or(doc.1));
+        self.walk(&mut target, target_doc, path.clone(), expansion);
+        expansion.stack.pop();
+
+        // the other members are read in the referring document
+        if let Value::Object(o) = value {
+            o.remove("$ref");
+        }
+        self.walk(value, doc, path.clone(), expansion);
+        match (target, std::mem::take(value)) {
+            (Value::Object(mut t), Value::Object(siblings)) => {
+                t.extend(siblings);
+                *value = Value::Object(t);
+            },
+            (target, Value::Object(siblings)) if siblings.is_empty() => *value = target,
+            (_, mut siblings) => {
+                siblings["$ref"] = Value::String(reference.clone());
+                *value = siblings;
+                expansion.unresolved.push(Unresolved { path, reference, cycle: false, limit: false });
+            }
+        }
+    }
+}
+
+/// State of [`Refs::apply`]
+struct Expansion<'a> {
+    /// The references being expanded
+    stack: Vec<String>,
+    /// Nodes added so far
+    nodes: usize,
+    unresolved: &'a mut Vec<Unresolved>,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Refs, Unresolved};
+    use crate::limits::Limits;
+
+    #[test]
+    fn shared_fragments() {
+        let common = json!({"retry": {"count": 3, "backoff": {"$ref": "#/backoff"}}, "backoff": "exponential"});
+        let mut doc = json!({
+            "a": {"$ref": "common#/retry"},
+            "b": [{"$ref": "common#/retry/count"}],
+            "ping": {"$ref": "#/pong"},
+            "pong": {"$ref": "#/ping"},
+            "gone": {"$ref": "other#/x"},
+            "config": {"$ref": "common"}
+        });
+        let mut unresolved = Refs::new().document("common", common).apply(&mut doc);
+        unresolved.sort_by(|a, b| a.path.cmp(&b.path));
+
+        assert_eq!(doc["a"], json!({"count": 3, "backoff": "exponential"}));
+        assert_eq!(doc["b"], json!([3]));
+        assert_eq!(doc["config"]["retry"]["backoff"], json!("exponential"));
+        assert_eq!(doc["gone"], json!({"$ref": "other#/x"}));
+        assert_eq!(unresolved, vec!(
+            Unresolved { path: s!("/gone"), reference: s!("other#/x"), cycle: false, limit: false },
+            Unresolved { path: s!("/ping"), reference: s!("#/pong"), cycle: true, limit: false },
+            Unresolved { path: s!("/pong"), reference: s!("#/ping"), cycle: true, limit: false },
+        ));
+    }
+
+    #[test]
+    fn fan_out_limit() {
+        // every level doubles the size of the one before
+        let mut doc = json!({"a0": [1, 2]});
+        for i in 1..40 {
+            let prev = format!("#/a{}", i - 1);
+            doc[format!("a{}", i)] = json!([{"$ref": prev}, {"$ref": prev}]);
+        }
+        let unresolved = Refs::new().limits(Limits::new().max_nodes(10_000)).apply(&mut doc);
+        assert!(!unresolved.is_empty() && unresolved.iter().all(|u| u.limit && !u.cycle));
+        assert!(crate::limits::estimated_size(&doc).nodes < 50_000);
+
+        let mut small = json!({"a": [1], "b": [{"$ref": "#/a"}, {"$ref": "#/a"}]});
+        assert!(Refs::new().limits(Limits::new().max_nodes(4)).apply(&mut small).is_empty());
+        assert_eq!(small["b"], json!([[1], [1]]));
+    }
+}
@@ -0,0 +1,92 @@
+//! Reading reqwest responses with this crate
+
+use std::future::Future;
+
+use crate::session::{self, Kind};
+use crate::{read, FromJsonError, TryFromJson};
+
+/// Relaxed json bodies for `reqwest::Response`
+pub trait RelaxedResponse {
+    /// Read the body with [`read`](crate::read) and convert it with [`TryFromJson`].
+    /// Conversion errors name the JSON Pointer of the first value that failed.
+    /// The status is not checked, use `error_for_status` first where needed.
+    fn relaxed_json<T: TryFromJson>(self) -> impl Future<Output = Result<T, FromJsonError>> + Send;
+}
+
+impl RelaxedResponse for reqwest::Response {
+    async fn relaxed_json<T: TryFromJson>(self) -> Result<T, FromJsonError> {
+        let url = self.url().clone();
+        let bytes = self.bytes().await
+            .map_err(|e| FromJsonError::with_message(&e.to_string()))?;
+        let value = read::from_slice(&bytes)
+            .map_err(|e| error_for(&url, e))?;
+        let ((converted, prefix), events) = session::scope(&[], || (T::try_from_json(&value), session::path()));
+        let field = events.iter()
+            .find(|e| e.kind == Kind::Error)
+            .map(|e| s!(e.path.strip_prefix(prefix.as_str()).unwrap_or(&e.path)));
+        converted.map_err(|e| match field {
+            Some(field) => error_for(&url, FromJsonError::with_message(&format!("{} at `{}`", e.message(), field))),
+            None => error_for(&url, e)
+        })
+    }
+}
+
+/// Name the url in the message, keeping the position in the body
+fn error_for(url: &reqwest::Url, e: FromJsonError) -> FromJsonError {
+    let error = FromJsonError::with_message(&format!("{} (body of {})", e.message(), url));
+    match e.position() {
+        Some((line, column)) => error.with_position(line, column),
+        None => error
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::RelaxedResponse;
+    use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+    struct Page {
+        total: i64,
+    }
+
+    impl TryFromJson for Page {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            Ok(Page { total: value.maybe_int("total").relaxed() })
+        }
+    }
+
+    struct Item;
+
+    impl TryFromJson for Item {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            value.maybe_int("price").strict_ok().map(|_| Item)
+        }
+    }
+
+    struct Items;
+
+    impl TryFromJson for Items {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            value.maybe_array_with::<Item, _>("items", &crate::array::Options::new().fail_fast(true)).strict_ok().map(|_| Items)
+        }
+    }
+
+    fn response(body: &'static str) -> reqwest::Response {
+        reqwest::Response::from(http::Response::new(body))
+    }
+
+    #[tokio::test]
+    async fn relaxed_json_body() {
+        assert_eq!(response("\u{feff}{\"total\": \"3\"}").relaxed_json::<Page>().await.unwrap().total, 3);
+
+        let error = response("{\"total\": ").relaxed_json::<Page>().await.err().unwrap();
+        assert_eq!(error.position(), Some((1, 10)));
+        assert!(error.message().contains("(body of http://no.url.provided.local/)"));
+
+        let error = response("{\"items\": [{\"price\": 1}, {\"price\": [2]}]}").relaxed_json::<Items>().await.err().unwrap();
+        assert!(error.message().contains(" at `/items/1/price` (body of "));
+    }
+}
@@ -0,0 +1,199 @@
+//! Replacing numbers that are not safe to hand to strict consumers
+//!
+//! `serde_json::Value` can not hold NaN or infinite numbers, they only show up as
+//! strings like `"NaN"` or `"-Infinity"` written by lenient producers. The
+//! [`Sanitizer`] treats these strings like the numbers they spell.
+
+use serde_json::{Number, Value};
+
+use crate::patch;
+
+/// Largest integer a f64 (and so a javascript number) represents exactly
+pub const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// What happens to an offending value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Replace with null
+    Null,
+    /// Remove the object member or array element
+    Remove,
+    /// Replace with the nearest bound, NaN becomes null
+    Clamp,
+    /// Replace with the given value
+    Replace(Value),
+}
+
+/// Bounds for numbers and the action for those breaking them,
+/// by default all finite numbers are accepted and offending values become null
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+    min: f64,
+    max: f64,
+    non_finite_strings: bool,
+    action: Action,
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Sanitizer {
+            min: f64::MIN,
+            max: f64::MAX,
+            non_finite_strings: true,
+            action: Action::Null,
+        }
+    }
+}
+
+impl Sanitizer {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn bounds(mut self, min: f64, max: f64) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Only accept numbers within the range where integers are exact in javascript
+    pub fn safe_integers(self) -> Self {
+        self.bounds(-MAX_SAFE_INTEGER, MAX_SAFE_INTEGER)
+    }
+
+    /// Handle strings like "NaN" or "Infinity", enabled by default
+    pub fn non_finite_strings(mut self, enable: bool) -> Self {
+        self.non_finite_strings = enable;
+        self
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Sanitize `value` in place, returning JSON Pointers to all values that were changed
+    pub fn apply(&self, value: &mut Value) -> Vec<String> {
+        let mut changed = Vec::new();
+        if let Some(f) = self.offending(value) {
+            changed.push(String::new());
+            *value = self.replacement(f).unwrap_or(Value::Null);
+        }
+        self.walk(value, String::new(), &mut changed);
+        changed
+    }
+
+    /// The numeric value of `value` if it breaks the rules
+    fn offending(&self, value: &Value) -> Option<f64> {
+        let f = match value {
+            Value::Number(n) => n.as_f64()?,
+            Value::String(s) if self.non_finite_strings => {
+                match s.trim().to_lowercase().as_str() {
+                    "nan" | "-nan" => f64::NAN,
+                    "infinity" | "+infinity" | "inf" | "+inf" => f64::INFINITY,
+                    "-infinity" | "-inf" => f64::NEG_INFINITY,
+                    _ => return None
+                }
+            },
+            _ => return None
+        };
+        match f.is_nan() || f < self.min || f > self.max {
+            true => Some(f),
+            false => None
+        }
+    }
+
+    /// What replaces an offending number, None if it is removed
+    fn replacement(&self, f: f64) -> Option<Value> {
+        match &self.action {
+            Action::Null => Some(Value::Null),
+            Action::Remove => None,
+            Action::Replace(v) => Some(v.clone()),
+            Action::Clamp if f.is_nan() => Some(Value::Null),
+            Action::Clamp => {
+                let bound = if f < self.min { self.min } else { self.max };
+                Some(match bound.fract() == 0.0 && bound.abs() <= MAX_SAFE_INTEGER {
+                    true => Value::Number((bound as i64).into()),
+                    false => Number::from_f64(bound).map(Value::Number).unwrap_or(Value::Null)
+                })
+            }
+        }
+    }
+
+    fn walk(&self, value: &mut Value, path: String, changed: &mut Vec<String>) {
+        match value {
+            Value::Array(a) => {
+                let mut i = 0;
+                let mut index = 0;
+                while i < a.len() {
+                    let child = format!("{}/{}", path, index);
+                    index += 1;
+                    if let Some(f) = self.offending(&a[i]) {
+                        changed.push(child);
+                        match self.replacement(f) {
+                            Some(v) => a[i] = v,
+                            None => {
+                                a.remove(i);
+                                continue;
+                            }
+                        }
+                    } else {
+                        self.walk(&mut a[i], child, changed);
+                    }
+                    i += 1;
+                }
+            },
+            Value::Object(o) => {
+                let mut removed = Vec::new();
+                for (k, v) in o.iter_mut() {
+                    let child = patch::child(&path, k);
+                    match self.offending(v) {
+                        Some(f) => {
+                            changed.push(child);
+                            match self.replacement(f) {
+                                Some(r) => *v = r,
+                                None => removed.push(k.clone())
+                            }
+                        },
+                        None => self.walk(v, child, changed)
+                    }
+                }
+                for k in removed {
+                    patch::remove_key(o, &k);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Replace non-finite number strings with null throughout `value`, see [`Sanitizer`]
+pub fn sanitize(value: &mut Value) -> Vec<String> {
+    Sanitizer::new().apply(value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Action, Sanitizer};
+
+    #[test]
+    fn sanitize_numbers() {
+        let mut doc = json!({"a": "NaN", "b": [1, "-Infinity", 2], "c": "nan bread"});
+        assert_eq!(super::sanitize(&mut doc), vec!("/a", "/b/1"));
+        assert_eq!(doc, json!({"a": null, "b": [1, null, 2], "c": "nan bread"}));
+
+        let mut ids = json!({"ids": [1, 12345678901234567890u64, -5, "Infinity"]});
+        let clamp = Sanitizer::new().bounds(0.0, 100.0).action(Action::Clamp);
+        clamp.apply(&mut ids);
+        assert_eq!(ids, json!({"ids": [1, 100, 0, 100]}));
+
+        let mut ids = json!({"big": 1e300, "ids": [1, 12345678901234567890u64, 2]});
+        let remove = Sanitizer::new().safe_integers().action(Action::Remove);
+        assert_eq!(remove.apply(&mut ids), vec!("/big", "/ids/1"));
+        assert_eq!(ids, json!({"ids": [1, 2]}));
+    }
+}
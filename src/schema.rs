@@ -0,0 +1,459 @@
+//! Validating json documents against a JSON Schema after applying the coercions
+//! of this crate
+//!
+//! A value of the wrong type is coerced like the maybe_* accessors do before the
+//! schema rejects it, so `"5"` passes `{"type": "integer"}` as a [`Coercion`] and
+//! the [`Report`] carries the coerced document. Supported keywords are `type`,
+//! `enum`, `const`, the numeric and length bounds, `multipleOf`, `items`,
+//! `uniqueItems`, `properties`, `required`, `additionalProperties`, the property
+//! counts, `allOf`, `anyOf`, `oneOf`, `not` and `$ref` to `#` pointers within the
+//! schema. `pattern`, `format` and other keywords are ignored.
+//!
+//! [`Describe`] goes the other way and publishes the schema a [`TryFromJson`](crate::TryFromJson)
+//! model accepts. The json types the accessors coerce from are listed under
+//! `x-relaxed`, which validators ignore.
+
+use serde_json::{json, Map, Value};
+
+use crate::{coerce, compare, patch, FromJsonError, Maybe};
+
+/// A value that only passed after being coerced
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coercion {
+    /// JSON Pointer to the value
+    pub path: String,
+    /// Type of the value in the input
+    pub from: &'static str,
+    /// Schema type or `enum` / `const` the value was coerced to
+    pub to: String,
+}
+
+/// A value the schema rejects even after coercion
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// JSON Pointer to the value
+    pub path: String,
+    pub message: String,
+}
+
+/// Outcome of a validation
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// The document with all coercions applied
+    pub value: Value,
+    pub coercions: Vec<Coercion>,
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// `Strict` without coercions, `Relaxed` with coercions only and
+    /// `Error` listing the violations otherwise
+    pub fn into_maybe(self) -> Maybe<Value> {
+        match (self.violations.is_empty(), self.coercions.is_empty()) {
+            (true, true) => Maybe::Strict(self.value),
+            (true, false) => Maybe::Relaxed(self.value),
+            (false, _) => {
+                let messages: Vec<String> = self.violations.iter()
+                    .map(|v| format!("{}: {}", if v.path.is_empty() { "/" } else { &v.path }, v.message))
+                    .collect();
+                Maybe::Error(FromJsonError::with_message(&messages.join("; ")))
+            }
+        }
+    }
+}
+
+/// Validate `value` against `schema`, see the [module documentation](self)
+pub fn validate(value: &Value, schema: &Value) -> Report {
+    let mut walk = Walk { root: schema, coercions: Vec::new(), violations: Vec::new() };
+    let mut value = value.clone();
+    walk.check(&mut value, schema, "");
+    Report { value, coercions: walk.coercions, violations: walk.violations }
+}
+
+struct Walk<'a> {
+    root: &'a Value,
+    coercions: Vec<Coercion>,
+    violations: Vec<Violation>,
+}
+
+impl <'a> Walk<'a> {
+
+    fn branch(&self) -> Walk<'a> {
+        Walk { root: self.root, coercions: Vec::new(), violations: Vec::new() }
+    }
+
+    fn violation(&mut self, path: &str, message: String) {
+        self.violations.push(Violation { path: s!(path), message });
+    }
+
+    fn coercion(&mut self, path: &str, from: &'static str, to: &str) {
+        self.coercions.push(Coercion { path: s!(path), from, to: s!(to) });
+    }
+
+    fn check(&mut self, value: &mut Value, schema: &'a Value, path: &str) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.violation(path, s!("no value is allowed")),
+            Value::Object(o) => o,
+            _ => return self.violation(path, s!("schema must be an object or a boolean"))
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match reference.strip_prefix('#').and_then(|p| self.root.pointer(p)) {
+                Some(target) => self.check(value, target, path),
+                None => self.violation(path, format!("unresolved $ref {}", reference))
+            }
+        }
+        if let Some(types) = schema.get("type") {
+            self.check_type(value, types, path);
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            self.check_enum(value, allowed, "enum", path);
+        }
+        if let Some(constant) = schema.get("const") {
+            self.check_enum(value, std::slice::from_ref(constant), "const", path);
+        }
+        match value {
+            Value::Number(_) => self.check_number(value, schema, path),
+            Value::String(s) => {
+                let length = s.chars().count() as u64;
+                self.check_count(length, schema, "minLength", "maxLength", "characters", path);
+            },
+            Value::Array(a) => self.check_array(a, schema, path),
+            Value::Object(o) => self.check_object(o, schema, path),
+            _ => {}
+        }
+        self.check_combinators(value, schema, path);
+    }
+
+    fn check_type(&mut self, value: &mut Value, types: &Value, path: &str) {
+        let types: Vec<&str> = match types {
+            Value::String(t) => vec!(t.as_str()),
+            Value::Array(a) => a.iter().filter_map(Value::as_str).collect(),
+            _ => return
+        };
+        if types.iter().any(|t| is_type(value, t)) {
+            return;
+        }
+        for t in types.iter() {
+            if let Some(coerced) = coerce_to(value, t) {
+                self.coercion(path, coerce::type_name(value), t);
+                *value = coerced;
+                return;
+            }
+        }
+        self.violation(path, format!("expected {}, found {}", types.join(" or "), coerce::type_name(value)));
+    }
+
+    fn check_enum(&mut self, value: &mut Value, allowed: &[Value], keyword: &str, path: &str) {
+        if allowed.contains(value) {
+            return;
+        }
+        match allowed.iter().find(|a| compare::relaxed_eq(value, a)) {
+            Some(a) => {
+                self.coercion(path, coerce::type_name(value), keyword);
+                *value = a.clone();
+            },
+            None => self.violation(path, format!("{} is not allowed by {}", value, keyword))
+        }
+    }
+
+    fn check_number(&mut self, value: &Value, schema: &Map<String, Value>, path: &str) {
+        let n = value.as_f64().unwrap_or_default();
+        let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+        if let Some(min) = bound("minimum").filter(|min| n < *min) {
+            self.violation(path, format!("{} is less than the minimum of {}", value, min));
+        }
+        if let Some(max) = bound("maximum").filter(|max| n > *max) {
+            self.violation(path, format!("{} is greater than the maximum of {}", value, max));
+        }
+        if let Some(min) = bound("exclusiveMinimum").filter(|min| n <= *min) {
+            self.violation(path, format!("{} is not greater than {}", value, min));
+        }
+        if let Some(max) = bound("exclusiveMaximum").filter(|max| n >= *max) {
+            self.violation(path, format!("{} is not less than {}", value, max));
+        }
+        if let Some(step) = bound("multipleOf").filter(|step| *step > 0.0 && (n / step).fract() != 0.0) {
+            self.violation(path, format!("{} is not a multiple of {}", value, step));
+        }
+    }
+
+    fn check_count(&mut self, count: u64, schema: &Map<String, Value>, min: &str, max: &str, unit: &str, path: &str) {
+        if let Some(min) = schema.get(min).and_then(Value::as_u64).filter(|min| count < *min) {
+            self.violation(path, format!("has {} {}, at least {} required", count, unit, min));
+        }
+        if let Some(max) = schema.get(max).and_then(Value::as_u64).filter(|max| count > *max) {
+            self.violation(path, format!("has {} {}, at most {} allowed", count, unit, max));
+        }
+    }
+
+    fn check_array(&mut self, array: &mut [Value], schema: &'a Map<String, Value>, path: &str) {
+        self.check_count(array.len() as u64, schema, "minItems", "maxItems", "items", path);
+        match schema.get("items") {
+            Some(Value::Array(tuple)) => {
+                for (i, (v, s)) in array.iter_mut().zip(tuple.iter()).enumerate() {
+                    self.check(v, s, &patch::child(path, &i.to_string()));
+                }
+            },
+            Some(items) => {
+                for (i, v) in array.iter_mut().enumerate() {
+                    self.check(v, items, &patch::child(path, &i.to_string()));
+                }
+            },
+            None => {}
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            for i in 1..array.len() {
+                if array[..i].contains(&array[i]) {
+                    self.violation(&patch::child(path, &i.to_string()), s!("duplicate item"));
+                }
+            }
+        }
+    }
+
+    fn check_object(&mut self, object: &mut Map<String, Value>, schema: &'a Map<String, Value>, path: &str) {
+        self.check_count(object.len() as u64, schema, "minProperties", "maxProperties", "properties", path);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    self.violation(&patch::child(path, key), s!("required property is missing"));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, v) in object.iter_mut() {
+            let child = patch::child(path, key);
+            match (properties.and_then(|p| p.get(key)), schema.get("additionalProperties")) {
+                (Some(s), _) => self.check(v, s, &child),
+                (None, Some(additional)) => self.check(v, additional, &child),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn check_combinators(&mut self, value: &mut Value, schema: &'a Map<String, Value>, path: &str) {
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for s in all.iter() {
+                self.check(value, s, path);
+            }
+        }
+        if let Some(Value::Array(any)) = schema.get("anyOf") {
+            // the branch needing the fewest coercions wins
+            let passed = self.branches(value, any, path);
+            match passed.into_iter().min_by_key(|(_, walk)| walk.coercions.len()) {
+                Some((v, walk)) => self.accept(value, v, walk),
+                None => self.violation(path, s!("no anyOf schema matches"))
+            }
+        }
+        if let Some(Value::Array(one)) = schema.get("oneOf") {
+            // a single strict match decides among several relaxed ones
+            let mut passed = self.branches(value, one, path);
+            if passed.len() > 1 {
+                passed.retain(|(_, walk)| walk.coercions.is_empty());
+            }
+            match passed.len() {
+                1 => {
+                    let (v, walk) = passed.remove(0);
+                    self.accept(value, v, walk);
+                },
+                0 => self.violation(path, s!("no oneOf schema matches")),
+                n => self.violation(path, format!("{} oneOf schemas match", n))
+            }
+        }
+        if let Some(not) = schema.get("not") {
+            let mut walk = self.branch();
+            walk.check(&mut value.clone(), not, path);
+            if walk.violations.is_empty() {
+                self.violation(path, s!("matches a schema given by not"));
+            }
+        }
+    }
+
+    fn branches(&self, value: &Value, schemas: &'a [Value], path: &str) -> Vec<(Value, Walk<'a>)> {
+        let mut passed = Vec::new();
+        for s in schemas.iter() {
+            let mut walk = self.branch();
+            let mut v = value.clone();
+            walk.check(&mut v, s, path);
+            if walk.violations.is_empty() {
+                passed.push((v, walk));
+            }
+        }
+        passed
+    }
+
+    fn accept(&mut self, value: &mut Value, branch_value: Value, walk: Walk<'a>) {
+        *value = branch_value;
+        self.coercions.extend(walk.coercions);
+    }
+}
+
+fn is_type(value: &Value, t: &str) -> bool {
+    match (t, value) {
+        ("null", Value::Null) | ("boolean", Value::Bool(_)) | ("number", Value::Number(_)) => true,
+        ("string", Value::String(_)) | ("array", Value::Array(_)) | ("object", Value::Object(_)) => true,
+        ("integer", Value::Number(n)) => n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => false
+    }
+}
+
+/// `value` coerced to the schema type `t`, None if the accessors would not read it
+fn coerce_to(value: &Value, t: &str) -> Option<Value> {
+    match t {
+        "boolean" => read(coerce::to_bool(Some(value))).map(Value::Bool),
+        "integer" if value.is_number() => None,
+        "integer" => read(coerce::to_int(Some(value))).map(|i| Value::Number(i.into())),
+        "number" => read(coerce::to_float(Some(value))).and_then(serde_json::Number::from_f64).map(Value::Number),
+        "string" => read(coerce::to_string(Some(value))).map(Value::String),
+        "array" if !value.is_null() && !value.is_object() => Some(Value::Array(vec!(value.clone()))),
+        _ => None
+    }
+}
+
+/// Types that can describe the json they are read from as a JSON Schema
+///
+/// There is no derive yet, models implement it next to `TryFromJson`:
+///
+/// ```
+/// use json_relaxed::schema::{self, Describe};
+/// # use serde_json::Value;
+///
+/// struct Order { id: u64, note: Option<String> }
+///
+/// impl Describe for Order {
+///     fn describe() -> Value {
+///         schema::object(&[("id", u64::describe())], &[("note", Option::<String>::describe())])
+///     }
+/// }
+/// ```
+pub trait Describe {
+    fn describe() -> Value;
+}
+
+/// Schema of a scalar `t` that is also read from the `relaxed` json types
+pub fn relaxed(t: &str, relaxed: &[&str]) -> Value {
+    json!({"type": t, "x-relaxed": relaxed})
+}
+
+/// Schema of an object with `required` and `optional` properties
+pub fn object(required: &[(&str, Value)], optional: &[(&str, Value)]) -> Value {
+    let properties: Map<String, Value> = required.iter().chain(optional.iter())
+        .map(|(k, v)| (s!(*k), v.clone()))
+        .collect();
+    let names: Vec<&str> = required.iter().map(|(k, _)| *k).collect();
+    json!({"type": "object", "properties": properties, "required": names})
+}
+
+impl Describe for bool {
+    fn describe() -> Value {
+        relaxed("boolean", &["number", "string"])
+    }
+}
+
+impl Describe for String {
+    fn describe() -> Value {
+        relaxed("string", &["boolean", "number"])
+    }
+}
+
+macro_rules! describe_number {
+    ($t:literal, $($ty:ty),*) => {
+        $(
+            impl Describe for $ty {
+                fn describe() -> Value {
+                    relaxed($t, &["boolean", "string"])
+                }
+            }
+        )*
+    };
+}
+
+describe_number!("integer", i8, i16, i32, i64, u8, u16, u32, u64, usize, isize);
+describe_number!("number", f32, f64);
+
+/// A single value is read as an array of one
+impl <T: Describe> Describe for Vec<T> {
+    fn describe() -> Value {
+        json!({"type": "array", "items": T::describe(), "x-relaxed": ["boolean", "number", "string"]})
+    }
+}
+
+impl <T: Describe> Describe for Option<T> {
+    fn describe() -> Value {
+        json!({"anyOf": [T::describe(), {"type": "null"}]})
+    }
+}
+
+fn read<T>(maybe: Maybe<T>) -> Option<T> {
+    match maybe {
+        Maybe::Strict(v) | Maybe::Relaxed(v) => Some(v),
+        Maybe::Null | Maybe::Error(_) => None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::Maybe;
+
+    #[test]
+    fn coercions_and_violations() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": {
+                "id": {"type": "integer", "minimum": 1},
+                "name": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "level": {"enum": ["low", "high"]},
+                "ratio": {"$ref": "#/definitions/ratio"}
+            },
+            "additionalProperties": false,
+            "definitions": {"ratio": {"type": "number", "maximum": 1}}
+        });
+
+        let report = super::validate(&json!({"id": "5", "tags": 7, "ratio": 0.5}), &schema);
+        assert_eq!(report.value, json!({"id": 5, "tags": ["7"], "ratio": 0.5}));
+        let paths: Vec<&str> = report.coercions.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!("/id", "/tags", "/tags/0"));
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].path, "/name");
+
+        let report = super::validate(&json!({"extra": 1, "id": "abc", "name": "x", "ratio": "2"}), &schema);
+        let paths: Vec<&str> = report.violations.iter().map(|v| v.path.as_str()).collect();
+        assert_eq!(paths, vec!("/extra", "/id", "/ratio"));
+        assert!(matches!(report.into_maybe(), Maybe::Error(_)));
+    }
+
+    #[test]
+    fn combinators() {
+        let schema = json!({"oneOf": [{"type": "string"}, {"type": "integer"}]});
+        match super::validate(&json!("5"), &schema).into_maybe() {
+            Maybe::Strict(v) => assert_eq!(v, json!("5")),
+            _ => panic!("expected a strict match")
+        }
+        let schema = json!({"anyOf": [{"type": "integer"}, {"type": "boolean"}], "not": {"const": 0}});
+        let report = super::validate(&json!("3"), &schema);
+        assert_eq!((report.value.clone(), report.is_valid()), (json!(3), true));
+        assert!(!super::validate(&json!(0), &schema).is_valid());
+    }
+
+    #[test]
+    fn describe() {
+        use super::Describe;
+
+        let schema = super::object(&[("id", u32::describe())], &[("tags", Option::<Vec<String>>::describe())]);
+        assert_eq!(schema["required"], json!(["id"]));
+        assert_eq!(schema["properties"]["id"]["x-relaxed"], json!(["boolean", "string"]));
+
+        let report = super::validate(&json!({"id": "7", "tags": 3}), &schema);
+        assert_eq!((report.value, report.violations.len()), (json!({"id": 7, "tags": ["3"]}), 0));
+    }
+}
@@ -0,0 +1,228 @@
+//! Resolving secret references in string values
+//!
+//! Resolvers are registered per scheme. A string that is a reference as a whole,
+//! like `"secret://vault/db/password"`, is replaced by the secret, references in
+//! `${scheme:...}` form, like `"${file:/run/secrets/db}"`, also within a longer
+//! string. Strings with unregistered schemes are left alone, `$${...}` is kept as
+//! the literal text `${...}`, as in [`substitute`](crate::substitute).
+//!
+//! Secrets are resolved for a whole document with [`Secrets::apply`], or while
+//! extracting within [`Secrets::scope`], where the string accessors return the
+//! resolved text and the document keeps the references:
+//!
+//! ```
+//! use json_relaxed::secrets::Secrets;
+//! use json_relaxed::MaybeValue;
+//! use serde_json::json;
+//!
+//! let secrets = Secrets::new().scheme("secret", |path| Ok(format!("<{}>", path)));
+//! let config = json!({"db": {"password": "secret://db/password"}});
+//! let password = secrets.scope(|| config["db"].maybe_string("password").relaxed());
+//! assert_eq!(password, "<db/password>");
+//! ```
+
+use serde_json::Value;
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use crate::{patch, FromJsonError};
+
+/// Looks up the secret for a reference without its scheme
+type Resolve = dyn Fn(&str) -> Result<String, FromJsonError> + Send + Sync;
+
+/// Resolvers by scheme, see the [module documentation](self)
+#[derive(Clone, Default)]
+pub struct Secrets {
+    schemes: Vec<(String, Arc<Resolve>)>,
+}
+
+/// A reference that could not be resolved by [`Secrets::apply`]
+#[derive(Debug)]
+pub struct Failure {
+    /// JSON Pointer to the string
+    pub path: String,
+    pub error: FromJsonError,
+}
+
+thread_local! {
+    static SCOPED: RefCell<Option<Secrets>> = const { RefCell::new(None) };
+}
+
+impl Secrets {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Resolve references of `scheme` with `resolve`, a later registration wins
+    pub fn scheme(mut self, scheme: &str, resolve: impl Fn(&str) -> Result<String, FromJsonError> + Send + Sync + 'static) -> Self {
+        self.schemes.insert(0, (s!(scheme), Arc::new(resolve)));
+        self
+    }
+
+    /// `file` references read the file, without a trailing newline
+    pub fn files(self) -> Self {
+        self.scheme("file", |path| match std::fs::read_to_string(path) {
+            Ok(text) => Ok(s!(text.strip_suffix('\n').unwrap_or(&text))),
+            Err(e) => Err(FromJsonError::with_message(&format!("secret file {}: {}", path, e)))
+        })
+    }
+
+    /// `env` references read an environment variable
+    pub fn env(self) -> Self {
+        self.scheme("env", |name| std::env::var(name)
+            .map_err(|e| FromJsonError::with_message(&format!("secret variable {}: {}", name, e))))
+    }
+
+    /// `text` with its references resolved and `$${` unescaped, None if it contains neither
+    pub fn resolve(&self, text: &str) -> Option<Result<String, FromJsonError>> {
+        if let Some((scheme, reference)) = text.split_once("://") {
+            if let Some(resolve) = self.resolver(scheme) {
+                return Some(resolve(reference));
+            }
+        }
+
+        let mut out = String::new();
+        let mut rest = text;
+        let mut found = false;
+        while let Some(start) = rest.find("${") {
+            let inner = &rest[start + 2..];
+            if rest[..start].ends_with('$') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("${");
+                rest = inner;
+                found = true;
+                continue;
+            }
+            let reference = inner.find('}').and_then(|end| inner[..end].split_once(':')
+                .and_then(|(scheme, reference)| self.resolver(scheme).map(|resolve| (resolve, reference, end))));
+            match reference {
+                Some((resolve, reference, end)) => {
+                    out.push_str(&rest[..start]);
+                    match resolve(reference) {
+                        Ok(secret) => out.push_str(&secret),
+                        Err(e) => return Some(Err(e))
+                    }
+                    rest = &inner[end + 1..];
+                    found = true;
+                },
+                None => {
+                    out.push_str(&rest[..start + 2]);
+                    rest = inner;
+                }
+            }
+        }
+        out.push_str(rest);
+        match found {
+            true => Some(Ok(out)),
+            false => None
+        }
+    }
+
+    fn resolver(&self, scheme: &str) -> Option<&Resolve> {
+        self.schemes.iter().find(|(s, _)| s == scheme).map(|(_, r)| r.as_ref())
+    }
+
+    /// Resolve the references in all strings of `value`, failures stay in place
+    pub fn apply(&self, value: &mut Value) -> Vec<Failure> {
+        let mut failures = Vec::new();
+        self.walk(value, String::new(), &mut failures);
+        failures
+    }
+
+    fn walk(&self, value: &mut Value, path: String, failures: &mut Vec<Failure>) {
+        match value {
+            Value::String(s) => match self.resolve(s) {
+                Some(Ok(secret)) => *s = secret,
+                Some(Err(error)) => failures.push(Failure { path, error }),
+                None => {}
+            },
+            Value::Array(a) => {
+                for (i, v) in a.iter_mut().enumerate() {
+                    self.walk(v, patch::child(&path, &i.to_string()), failures);
+                }
+            },
+            Value::Object(o) => {
+                for (k, v) in o.iter_mut() {
+                    self.walk(v, patch::child(&path, k), failures);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Run `f` with the string accessors resolving references on this thread
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _restore = replace(Some(self.clone()));
+        f()
+    }
+}
+
+pub(crate) struct Restore(Option<Secrets>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SCOPED.with(|s| *s.borrow_mut() = previous);
+    }
+}
+
+/// Use `secrets`, or none, on this thread until the guard is dropped
+pub(crate) fn replace(secrets: Option<Secrets>) -> Restore {
+    Restore(SCOPED.with(|s| s.replace(secrets)))
+}
+
+/// The secrets of this thread, to continue reading on another one
+#[cfg(feature = "rayon")]
+pub(crate) fn current() -> Option<Secrets> {
+    SCOPED.with(|s| s.borrow().clone())
+}
+
+/// Resolve `text` with the secrets of the current [`Secrets::scope`]
+pub(crate) fn resolve_scoped(text: &str) -> Option<Result<String, FromJsonError>> {
+    SCOPED.with(|s| s.borrow().as_ref().and_then(|secrets| secrets.resolve(text)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Secrets;
+    use crate::owned::IntoMaybe;
+    use crate::{FromJsonError, Maybe, MaybeValue};
+
+    fn vault() -> Secrets {
+        Secrets::new().env().scheme("secret", |path| match path {
+            "db/password" => Ok(s!("hunter2")),
+            _ => Err(FromJsonError::with_message("no such secret"))
+        })
+    }
+
+    #[test]
+    fn secret_references() {
+        let file = std::env::temp_dir().join(format!("json-relaxed-secret-{}", std::process::id()));
+        std::fs::write(&file, "s3cr3t\n").unwrap();
+        let secrets = vault().files();
+
+        let mut config = json!({
+            "db": {"password": "secret://db/password", "url": format!("postgres://app:${{file:{}}}@db", file.display())},
+            "other": ["secret://missing", "https://example.org", "$${file:/x} ${unknown:x}"]
+        });
+        assert_eq!(secrets.scope(|| config["db"].maybe_string("url").relaxed()), "postgres://app:s3cr3t@db");
+        assert!(matches!(secrets.scope(|| config["other"].maybe_str(0)), Maybe::Error(_)));
+        assert_eq!(secrets.scope(|| config["other"].maybe_string(2).relaxed()), "${file:/x} ${unknown:x}");
+        assert_eq!(config["db"].maybe_string("password").relaxed(), "secret://db/password");
+        let mut owned = config["db"].clone();
+        assert_eq!(secrets.scope(|| (&mut owned).into_maybe_string("password").strict()), Some(s!("hunter2")));
+        assert_eq!(owned["password"], json!("secret://db/password"));
+
+        let failures = secrets.apply(&mut config);
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].path, "/other/0");
+        assert_eq!(config["db"]["password"], json!("hunter2"));
+        assert_eq!(config["other"], json!(["secret://missing", "https://example.org", "${file:/x} ${unknown:x}"]));
+        assert_eq!(config["db"]["url"], json!("postgres://app:s3cr3t@db"));
+    }
+}
@@ -0,0 +1,589 @@
+//! A serde Serializer into Value with output conventions matching the relaxed [`de`](crate::de)
+//!
+//! By default
+//! - NaN and infinite floats become null
+//! - 128 bit integers outside the 64 bit range become strings
+//! - enum variant names are written in lower case
+//!
+//! With serde_json's arbitrary_precision wide numbers in a Value are written unchanged.
+
+use serde::ser::{self, Impossible, Serialize};
+use serde_json::{Map, Number, Value};
+use std::convert::TryFrom;
+
+use crate::normalize::KeyCase;
+use crate::FromJsonError;
+
+impl ser::Error for FromJsonError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        FromJsonError::with_message(&msg.to_string())
+    }
+}
+
+/// Representation of NaN and infinite floats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinite {
+    #[default]
+    Null,
+    /// `"NaN"`, `"Infinity"` and `"-Infinity"`
+    String,
+    Error,
+}
+
+/// Output conventions of the [`Serializer`]
+#[derive(Debug, Clone)]
+pub struct Options {
+    non_finite: NonFinite,
+    wide_as_string: bool,
+    #[cfg(feature = "arbitrary_precision")]
+    wide_as_number: bool,
+    variant_case: Option<KeyCase>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            non_finite: NonFinite::Null,
+            wide_as_string: true,
+            #[cfg(feature = "arbitrary_precision")]
+            wide_as_number: false,
+            variant_case: Some(KeyCase::Lower),
+        }
+    }
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn non_finite(mut self, non_finite: NonFinite) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    /// Write 128 bit integers outside the 64 bit range as strings, enabled by default.
+    /// Otherwise they are an error.
+    pub fn wide_as_string(mut self, enable: bool) -> Self {
+        self.wide_as_string = enable;
+        self
+    }
+
+    /// Write 128 bit integers outside the 64 bit range as exact numbers, this takes
+    /// precedence over [`wide_as_string`](Self::wide_as_string)
+    #[cfg(feature = "arbitrary_precision")]
+    pub fn wide_as_number(mut self, enable: bool) -> Self {
+        self.wide_as_number = enable;
+        self
+    }
+
+    /// Case of enum variant names, None keeps them as declared
+    pub fn variant_case(mut self, case: Option<KeyCase>) -> Self {
+        self.variant_case = case;
+        self
+    }
+
+    fn variant(&self, name: &str) -> String {
+        match self.variant_case {
+            Some(case) => case.apply(name),
+            None => s!(name)
+        }
+    }
+}
+
+/// Serialize `value` with the default [`Options`]
+pub fn to_value_relaxed<T: Serialize + ?Sized>(value: &T) -> Result<Value, FromJsonError> {
+    to_value_with(value, &Options::default())
+}
+
+pub fn to_value_with<T: Serialize + ?Sized>(value: &T, options: &Options) -> Result<Value, FromJsonError> {
+    value.serialize(Serializer::new(options))
+}
+
+/// Serializer into Value, see the [module documentation](self)
+#[derive(Clone, Copy)]
+pub struct Serializer<'a> {
+    options: &'a Options,
+}
+
+impl <'a> Serializer<'a> {
+    pub fn new(options: &'a Options) -> Self {
+        Serializer { options }
+    }
+
+    fn wide(&self, text: String, n: Option<Number>) -> Result<Value, FromJsonError> {
+        #[cfg(feature = "arbitrary_precision")]
+        let n = n.or_else(|| text.parse().ok().filter(|_| self.options.wide_as_number));
+        match (n, self.options.wide_as_string) {
+            (Some(n), _) => Ok(Value::Number(n)),
+            (None, true) => Ok(Value::String(text)),
+            _ => Err(FromJsonError::with_message(&format!("integer out of range: {}", text)))
+        }
+    }
+}
+
+impl <'a> ser::Serializer for Serializer<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    type SerializeSeq = SerializeVec<'a>;
+    type SerializeTuple = SerializeVec<'a>;
+    type SerializeTupleStruct = SerializeVec<'a>;
+    type SerializeTupleVariant = SerializeVec<'a>;
+    type SerializeMap = SerializeMap<'a>;
+    type SerializeStruct = SerializeMap<'a>;
+    type SerializeStructVariant = SerializeMap<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, FromJsonError> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, FromJsonError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, FromJsonError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, FromJsonError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, FromJsonError> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value, FromJsonError> {
+        let n = i64::try_from(v).ok().map(Number::from);
+        self.wide(v.to_string(), n)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, FromJsonError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, FromJsonError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, FromJsonError> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, FromJsonError> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value, FromJsonError> {
+        let n = u64::try_from(v).ok().map(Number::from);
+        self.wide(v.to_string(), n)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, FromJsonError> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, FromJsonError> {
+        match (Number::from_f64(v), self.options.non_finite) {
+            (Some(n), _) => Ok(Value::Number(n)),
+            (None, NonFinite::Null) => Ok(Value::Null),
+            (None, NonFinite::String) => Ok(Value::String(s!(match v {
+                v if v.is_nan() => "NaN",
+                v if v > 0.0 => "Infinity",
+                _ => "-Infinity"
+            }))),
+            (None, NonFinite::Error) => Err(FromJsonError::with_message(&format!("non-finite float: {}", v)))
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, FromJsonError> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, FromJsonError> {
+        Ok(Value::String(s!(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, FromJsonError> {
+        Ok(Value::Array(v.iter().map(|b| Value::Number((*b).into())).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Value, FromJsonError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, FromJsonError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, FromJsonError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, FromJsonError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value, FromJsonError> {
+        Ok(Value::String(self.options.variant(variant)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<Value, FromJsonError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Value, FromJsonError> {
+        let mut map = Map::new();
+        map.insert(self.options.variant(variant), value.serialize(self)?);
+        Ok(Value::Object(map))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec<'a>, FromJsonError> {
+        Ok(SerializeVec { ser: self, variant: None, vec: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec<'a>, FromJsonError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeVec<'a>, FromJsonError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<SerializeVec<'a>, FromJsonError> {
+        Ok(SerializeVec { ser: self, variant: Some(self.options.variant(variant)), vec: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeMap<'a>, FromJsonError> {
+        Ok(SerializeMap { ser: self, variant: None, map: Map::new(), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap<'a>, FromJsonError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, _len: usize) -> Result<SerializeMap<'a>, FromJsonError> {
+        Ok(SerializeMap { ser: self, variant: Some(self.options.variant(variant)), map: Map::new(), key: None })
+    }
+}
+
+/// Wrap `value` as `{variant: value}` for enum variants
+fn tagged(variant: Option<String>, value: Value) -> Value {
+    match variant {
+        Some(v) => {
+            let mut map = Map::new();
+            map.insert(v, value);
+            Value::Object(map)
+        },
+        None => value
+    }
+}
+
+pub struct SerializeVec<'a> {
+    ser: Serializer<'a>,
+    variant: Option<String>,
+    vec: Vec<Value>,
+}
+
+impl <'a> SerializeVec<'a> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        self.vec.push(value.serialize(self.ser)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, FromJsonError> {
+        Ok(tagged(self.variant, Value::Array(self.vec)))
+    }
+}
+
+impl <'a> ser::SerializeSeq for SerializeVec<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+impl <'a> ser::SerializeTuple for SerializeVec<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+impl <'a> ser::SerializeTupleStruct for SerializeVec<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+impl <'a> ser::SerializeTupleVariant for SerializeVec<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+pub struct SerializeMap<'a> {
+    ser: Serializer<'a>,
+    variant: Option<String>,
+    map: Map<String, Value>,
+    key: Option<String>,
+}
+
+impl <'a> SerializeMap<'a> {
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), FromJsonError> {
+        self.map.insert(s!(key), value.serialize(self.ser)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, FromJsonError> {
+        #[cfg(feature = "arbitrary_precision")]
+        if let (None, 1, Some(Value::String(digits))) = (&self.variant, self.map.len(), self.map.get(crate::read::NUMBER_TOKEN)) {
+            return digits.parse::<Number>().map(Value::Number).map_err(|e| FromJsonError::with_message(&e.to_string()));
+        }
+        Ok(tagged(self.variant, Value::Object(self.map)))
+    }
+}
+
+impl <'a> ser::SerializeMap for SerializeMap<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), FromJsonError> {
+        self.key = Some(key.serialize(KeySerializer { ser: self.ser })?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        match self.key.take() {
+            Some(k) => {
+                self.map.insert(k, value.serialize(self.ser)?);
+                Ok(())
+            },
+            None => Err(FromJsonError::with_message("value serialized before key"))
+        }
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+impl <'a> ser::SerializeStruct for SerializeMap<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), FromJsonError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+impl <'a> ser::SerializeStructVariant for SerializeMap<'a> {
+    type Ok = Value;
+    type Error = FromJsonError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), FromJsonError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value, FromJsonError> {
+        self.finish()
+    }
+}
+
+/// Object keys must be strings, scalars are written as their text
+struct KeySerializer<'a> {
+    ser: Serializer<'a>,
+}
+
+macro_rules! serialize_key {
+    ($($method:ident: $t:ty,)*) => {
+        $(
+            fn $method(self, v: $t) -> Result<String, FromJsonError> {
+                Ok(v.to_string())
+            }
+        )*
+    };
+}
+
+fn key_error() -> FromJsonError {
+    FromJsonError::with_message("key must be a string")
+}
+
+impl <'a> ser::Serializer for KeySerializer<'a> {
+    type Ok = String;
+    type Error = FromJsonError;
+
+    type SerializeSeq = Impossible<String, FromJsonError>;
+    type SerializeTuple = Impossible<String, FromJsonError>;
+    type SerializeTupleStruct = Impossible<String, FromJsonError>;
+    type SerializeTupleVariant = Impossible<String, FromJsonError>;
+    type SerializeMap = Impossible<String, FromJsonError>;
+    type SerializeStruct = Impossible<String, FromJsonError>;
+    type SerializeStructVariant = Impossible<String, FromJsonError>;
+
+    serialize_key! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<String, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, FromJsonError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String, FromJsonError> {
+        Ok(self.ser.options.variant(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<String, FromJsonError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<String, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, FromJsonError> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, FromJsonError> {
+        Err(key_error())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    use super::{NonFinite, Options};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Mode {
+        ReadOnly,
+        Limit(u32),
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Record {
+        id: u128,
+        small: i128,
+        ratio: f64,
+        mode: Mode,
+        limit: Mode,
+        counts: BTreeMap<u8, bool>,
+    }
+
+    #[test]
+    fn relaxed_round_trip() {
+        let record = Record {
+            id: u128::MAX,
+            small: -5,
+            ratio: f64::NAN,
+            mode: Mode::ReadOnly,
+            limit: Mode::Limit(3),
+            counts: vec!((1, true)).into_iter().collect(),
+        };
+        let value = super::to_value_relaxed(&record).unwrap();
+        assert_eq!(value, json!({
+            "id": "340282366920938463463374607431768211455",
+            "small": -5,
+            "ratio": null,
+            "mode": "readonly",
+            "limit": {"limit": 3},
+            "counts": {"1": true}
+        }));
+
+        let back: Record = crate::de::from_value(&value).unwrap();
+        assert_eq!((back.id, back.mode, back.limit), (u128::MAX, Mode::ReadOnly, Mode::Limit(3)));
+
+        let strict = Options::new().non_finite(NonFinite::Error).wide_as_string(false).variant_case(None);
+        assert!(super::to_value_with(&f64::INFINITY, &strict).is_err());
+        assert!(super::to_value_with(&u128::MAX, &strict).is_err());
+        assert_eq!(super::to_value_with(&Mode::ReadOnly, &strict).unwrap(), json!("ReadOnly"));
+        assert_eq!(super::to_value_with(&f64::NEG_INFINITY, &Options::new().non_finite(NonFinite::String)).unwrap(), json!("-Infinity"));
+    }
+}
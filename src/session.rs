@@ -0,0 +1,595 @@
+//! Extraction sessions collecting one trace for a whole document
+//!
+//! While a [`Session`] is active on a thread, every accessor reports into it
+//! with its full path, including the members and elements read by nested
+//! `TryFromJson` implementations. Those can add their own warnings with
+//! [`warn`] and ask for the [`policy`] of the session:
+//!
+//! ```
+//! use json_relaxed::session::{Kind, Session};
+//! use json_relaxed::{FromJsonError, MaybeValue, TryFromJson};
+//! use serde_json::{json, Value};
+//!
+//! struct Server { port: u64 }
+//!
+//! impl TryFromJson for Server {
+//!     fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+//!         Ok(Server { port: value.maybe_uint("port").relaxed() })
+//!     }
+//! }
+//!
+//! let config = json!({"servers": [{"port": 80}, {"port": "8080"}]});
+//! let (servers, trace) = Session::new().run(|| config.maybe_array::<Server, _>("servers").relaxed());
+//! assert_eq!(servers.len(), 2);
+//! assert_eq!(trace.events[0].path, "/servers/1/port");
+//! assert_eq!(trace.events[0].kind, Kind::Relaxed);
+//! ```
+//!
+//! Every `default()` or `relaxed()` that replaced a missing, null or erroneous
+//! value is listed by [`Trace::defaults`]. Sessions are per thread,
+//! `maybe_array_par` continues them on the rayon workers. [`Trace::unused`] lists
+//! the members of a document no accessor looked at.
+
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use crate::backend::Key;
+use crate::words::{self, WordPack};
+use crate::{patch, FromJsonError, Maybe};
+
+/// How the accessors treat relaxed coercions within a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Policy {
+    /// Coerce as everywhere else
+    #[default]
+    Relaxed,
+    /// Relaxed coercions are errors
+    Strict,
+}
+
+/// What happened at a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Relaxed,
+    Error,
+    Warning,
+    /// `default()` or `relaxed()` substituted a missing, null or erroneous value
+    Default,
+}
+
+/// An entry of a [`Trace`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// JSON Pointer below the value the outermost accessor was called on
+    pub path: String,
+    pub kind: Kind,
+    /// The json type of the source value, `missing` if there is none, empty for warnings
+    pub from: String,
+    /// The type that was read, like `u64` or `Vec<User>`, empty for warnings
+    pub to: String,
+    pub message: Option<String>,
+    /// The substituted default for `Default` events, like `u64::default()`
+    pub default: Option<String>,
+}
+
+/// A substituted default, see [`Trace::defaults`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Defaulted {
+    pub path: String,
+    pub default: String,
+    /// `missing`, `null` or the error message
+    pub reason: String,
+}
+
+/// Everything reported during a session, in order
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub events: Vec<Event>,
+    /// Addresses of the values the accessors found
+    read: HashSet<usize>,
+}
+
+impl Trace {
+
+    pub fn is_clean(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn of_kind(&self, kind: Kind) -> impl Iterator<Item = &Event> {
+        self.events.iter().filter(move |e| e.kind == kind)
+    }
+
+    /// Every place a default was used instead of a value
+    pub fn defaults(&self) -> Vec<Defaulted> {
+        self.of_kind(Kind::Default)
+            .map(|e| Defaulted {
+                path: e.path.clone(),
+                default: e.default.clone().unwrap_or_default(),
+                reason: e.message.clone().unwrap_or_else(|| e.from.clone())
+            })
+            .collect()
+    }
+
+    /// JSON Pointers to the members of `doc` that were not read, for reporting
+    /// typos like `retires` for `retries`. `doc` must be the unchanged document
+    /// the session read. Objects that were only read as a whole, like a `Value`,
+    /// are not looked into.
+    pub fn unused(&self, doc: &Value) -> Vec<String> {
+        let mut found = Vec::new();
+        self.walk_unused(doc, String::new(), &mut found);
+        found
+    }
+
+    fn walk_unused(&self, value: &Value, path: String, found: &mut Vec<String>) {
+        match value {
+            Value::Object(o) if o.values().any(|v| self.used(v)) => {
+                for (k, v) in o {
+                    match self.used(v) {
+                        true => self.walk_unused(v, patch::child(&path, k), found),
+                        false => found.push(patch::child(&path, k))
+                    }
+                }
+            },
+            Value::Array(a) => {
+                for (i, v) in a.iter().enumerate() {
+                    self.walk_unused(v, patch::child(&path, &i.to_string()), found);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// `value` or one of its members or elements was read
+    fn used(&self, value: &Value) -> bool {
+        self.read.contains(&address(value)) || match value {
+            Value::Object(o) => o.values().any(|v| self.used(v)),
+            Value::Array(a) => a.iter().any(|v| self.used(v)),
+            _ => false
+        }
+    }
+
+    /// The audit of this trace alone
+    pub fn audit(&self) -> Audit {
+        let mut audit = Audit::new();
+        audit.add(self);
+        audit
+    }
+}
+
+/// What would fail with [`Policy::Strict`], collected over many traces
+///
+/// Extraction runs leniently as usual, each relaxed coercion is a finding that
+/// strict parsing would turn into an error:
+///
+/// ```
+/// use json_relaxed::session::{Audit, Session};
+/// use json_relaxed::MaybeValue;
+/// use serde_json::json;
+///
+/// let mut audit = Audit::new();
+/// for payload in [json!({"id": 1}), json!({"id": "2"}), json!({"id": "3"})] {
+///     let (_, trace) = Session::new().run(|| payload.maybe_uint("id").relaxed());
+///     audit.add(&trace);
+/// }
+/// assert_eq!((audit.failing, audit.documents), (2, 3));
+/// assert_eq!(audit.findings[0].count, 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Audit {
+    /// Number of traces added
+    pub documents: usize,
+    /// Number of traces with at least one finding
+    pub failing: usize,
+    /// Findings by path and types, in the order they were first seen
+    pub findings: Vec<Finding>,
+}
+
+/// A relaxed coercion found by an [`Audit`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub path: String,
+    pub from: String,
+    pub to: String,
+    /// Number of times it was seen
+    pub count: usize,
+}
+
+impl Audit {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add the relaxed coercions of one extraction
+    pub fn add(&mut self, trace: &Trace) {
+        self.documents += 1;
+        let mut failing = false;
+        for e in trace.of_kind(Kind::Relaxed) {
+            failing = true;
+            match self.findings.iter_mut().find(|f| f.path == e.path && f.from == e.from && f.to == e.to) {
+                Some(f) => f.count += 1,
+                None => self.findings.push(Finding { path: e.path.clone(), from: e.from.clone(), to: e.to.clone(), count: 1 })
+            }
+        }
+        if failing {
+            self.failing += 1;
+        }
+    }
+
+    /// True if no extraction would have failed strictly
+    pub fn is_ready(&self) -> bool {
+        self.failing == 0
+    }
+}
+
+/// Settings of a session, see the [module documentation](self)
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    policy: Policy,
+    words: Option<WordPack>,
+}
+
+struct State {
+    policy: Policy,
+    events: Vec<Event>,
+    /// The last outcome without value, the default that may follow belongs to it
+    pending: Option<Event>,
+    read: HashSet<usize>,
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<State>> = const { RefCell::new(None) };
+    /// The members and elements being converted, also tracked without session
+    /// when events are emitted or metrics recorded
+    static PATH: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+impl Session {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Read booleans and null with `words` during the session, see [`words`](crate::words)
+    pub fn words(mut self, words: WordPack) -> Self {
+        self.words = Some(words);
+        self
+    }
+
+    /// Start the session on this thread, until the guard is finished or dropped
+    pub fn begin(&self) -> Guard {
+        let state = State { policy: self.policy, events: Vec::new(), pending: None, read: HashSet::new() };
+        let words = self.words.clone().map(words::install);
+        let path = PATH.with(|p| p.take());
+        Guard { previous: ACTIVE.with(|a| a.replace(Some(state))), path, _words: words }
+    }
+
+    /// Run `f` in the session and return its trace
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> (R, Trace) {
+        let guard = self.begin();
+        let r = f();
+        (r, guard.finish())
+    }
+}
+
+/// An active session, the previous one is restored on drop
+pub struct Guard {
+    previous: Option<State>,
+    path: Vec<String>,
+    _words: Option<words::Restore>,
+}
+
+impl Guard {
+
+    /// End the session and take its trace
+    pub fn finish(self) -> Trace {
+        let taken = ACTIVE.with(|a| a.borrow_mut().as_mut().map(|s| (std::mem::take(&mut s.events), std::mem::take(&mut s.read))));
+        let (events, read) = taken.unwrap_or_default();
+        Trace { events, read }
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|a| *a.borrow_mut() = previous);
+        let path = std::mem::take(&mut self.path);
+        PATH.with(|p| *p.borrow_mut() = path);
+    }
+}
+
+/// Add a warning at the current path, ignored without session
+pub fn warn(message: &str) {
+    ACTIVE.with(|a| if let Some(state) = a.borrow_mut().as_mut() {
+        let event = Event { path: current(), kind: Kind::Warning, from: String::new(), to: String::new(), message: Some(s!(message)), default: None };
+        state.events.push(event);
+    })
+}
+
+/// The policy of the current session, `Relaxed` without session
+pub fn policy() -> Policy {
+    ACTIVE.with(|a| a.borrow().as_ref().map_or(Policy::Relaxed, |s| s.policy))
+}
+
+/// JSON Pointer of the value being read, empty without session
+pub fn path() -> String {
+    match ACTIVE.with(|a| a.borrow().is_some()) {
+        true => current(),
+        false => String::new()
+    }
+}
+
+fn current() -> String {
+    PATH.with(|p| p.borrow().iter().fold(String::new(), |p, k| patch::child(&p, k)))
+}
+
+/// JSON Pointer of `key` below the value being read, also without session
+/// when events are emitted or metrics recorded
+pub(crate) fn pointer_to<I: Key + ?Sized>(key: &I) -> String {
+    patch::child(&current(), &key.name())
+}
+
+/// Run `f` below `segments` and return the events it added. They stay in the
+/// active session, a session is only started if there is none.
+pub(crate) fn scope<R>(segments: &[String], f: impl FnOnce() -> R) -> (R, Vec<Event>) {
+    let session = match ACTIVE.with(|a| a.borrow().is_some()) {
+        true => None,
+        false => Some(Session::new().begin())
+    };
+    let paths: Vec<PathGuard> = segments.iter().map(|s| enter_with(|| s.clone())).collect();
+    let before = ACTIVE.with(|a| a.borrow().as_ref().map_or(0, |s| s.events.len()));
+    let r = f();
+    let events = ACTIVE.with(|a| a.borrow().as_ref().map_or_else(Vec::new, |s| s.events[before..].to_vec()));
+    drop(paths);
+    drop(session);
+    (r, events)
+}
+
+/// The scopes of this thread, to continue reading on another one with [`resume`]
+#[cfg(feature = "rayon")]
+pub(crate) struct Fork {
+    limits: (usize, usize),
+    words: Option<WordPack>,
+    secrets: Option<crate::secrets::Secrets>,
+    policy: Option<Policy>,
+    path: Vec<String>,
+}
+
+/// What a forked conversion reported, added to the session by [`join`]
+#[cfg(feature = "rayon")]
+#[derive(Default)]
+pub(crate) struct Joined {
+    events: Vec<Event>,
+    read: HashSet<usize>,
+}
+
+#[cfg(feature = "rayon")]
+pub(crate) fn fork() -> Fork {
+    Fork {
+        limits: crate::limits::current(),
+        words: words::current(),
+        secrets: crate::secrets::current(),
+        policy: ACTIVE.with(|a| a.borrow().as_ref().map(|s| s.policy)),
+        path: PATH.with(|p| p.borrow().clone()),
+    }
+}
+
+/// Run `f` within the scopes of `fork`, on a rayon worker
+#[cfg(feature = "rayon")]
+pub(crate) fn resume<R>(fork: &Fork, f: impl FnOnce() -> R) -> (R, Joined) {
+    let _words = words::replace(fork.words.clone());
+    let _secrets = crate::secrets::replace(fork.secrets.clone());
+    let state = fork.policy.map(|policy| State { policy, events: Vec::new(), pending: None, read: HashSet::new() });
+    let previous = ACTIVE.with(|a| a.replace(state));
+    let path = PATH.with(|p| p.replace(fork.path.clone()));
+    let r = crate::limits::resume(fork.limits, f);
+    PATH.with(|p| *p.borrow_mut() = path);
+    let joined = ACTIVE.with(|a| a.replace(previous)).map_or_else(Joined::default, |s| Joined { events: s.events, read: s.read });
+    (r, joined)
+}
+
+/// Add what a forked conversion reported to the session of this thread
+#[cfg(feature = "rayon")]
+pub(crate) fn join(joined: Joined) {
+    ACTIVE.with(|a| if let Some(state) = a.borrow_mut().as_mut() {
+        state.events.extend(joined.events);
+        state.read.extend(joined.read);
+    })
+}
+
+/// Marks the member or element being converted, released on drop
+pub(crate) struct PathGuard(bool);
+
+impl Drop for PathGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            PATH.with(|p| p.borrow_mut().pop());
+        }
+    }
+}
+
+/// Descend into `key` for nested conversions
+pub(crate) fn enter<I: Key + ?Sized>(key: &I) -> PathGuard {
+    enter_with(|| key.name())
+}
+
+pub(crate) fn enter_index(index: usize) -> PathGuard {
+    enter_with(|| index.to_string())
+}
+
+fn enter_with(name: impl FnOnce() -> String) -> PathGuard {
+    let tracked = cfg!(any(feature = "tracing", feature = "log")) || crate::metrics::enabled() || ACTIVE.with(|a| a.borrow().is_some());
+    if tracked {
+        PATH.with(|p| p.borrow_mut().push(name()));
+    }
+    PathGuard(tracked)
+}
+
+fn address<J: ?Sized>(value: &J) -> usize {
+    value as *const J as *const () as usize
+}
+
+/// Remember that an accessor found `value`
+pub(crate) fn touch<J: ?Sized>(value: &J) {
+    ACTIVE.with(|a| if let Some(state) = a.borrow_mut().as_mut() {
+        state.read.insert(address(value));
+    })
+}
+
+/// Record the outcome read at `key` and apply the policy
+pub(crate) fn record<T, I: Key + ?Sized>(key: &I, from: &str, maybe: Maybe<T>) -> Maybe<T> {
+    ACTIVE.with(|a| {
+        let mut active = a.borrow_mut();
+        let state = match active.as_mut() {
+            Some(s) => s,
+            None => return maybe
+        };
+        let path = pointer_to(key);
+        state.pending = None;
+        let (kind, maybe) = match maybe {
+            Maybe::Relaxed(_) if state.policy == Policy::Strict => {
+                let error = FromJsonError::with_message(&format!("`{}` is not strictly typed, found {}", key.name(), from));
+                (Kind::Error, Maybe::Error(error))
+            },
+            Maybe::Relaxed(v) => (Kind::Relaxed, Maybe::Relaxed(v)),
+            Maybe::Error(e) => (Kind::Error, Maybe::Error(e)),
+            Maybe::Null => {
+                state.pending = Some(Event { path, kind: Kind::Default, from: s!(from), to: short_type_name::<T>(), message: None, default: None });
+                return Maybe::Null
+            },
+            m => return m
+        };
+        let message = match &maybe {
+            Maybe::Error(e) => Some(s!(e.message())),
+            _ => None
+        };
+        let event = Event { path, kind, from: s!(from), to: short_type_name::<T>(), message, default: None };
+        if kind == Kind::Error {
+            state.pending = Some(Event { kind: Kind::Default, ..event.clone() });
+        }
+        state.events.push(event);
+        maybe
+    })
+}
+
+/// Record that a default of type `T` replaced the outcome last recorded without value
+pub(crate) fn defaulted<T>(given: bool) {
+    ACTIVE.with(|a| if let Some(state) = a.borrow_mut().as_mut() {
+        let mut event = state.pending.take().unwrap_or_else(|| {
+            // the outcome was not read by an accessor, e.g. a Maybe built by hand
+            Event { path: current(), kind: Kind::Default, from: String::new(), to: short_type_name::<T>(), message: None, default: None }
+        });
+        let name = short_type_name::<T>();
+        event.default = Some(match given {
+            true => format!("given {}", name),
+            false => format!("{}::default()", name)
+        });
+        state.events.push(event);
+    })
+}
+
+/// The type name without module paths, `Vec<String>` instead of `alloc::vec::Vec<alloc::string::String>`
+fn short_type_name<T>() -> String {
+    let full = std::any::type_name::<T>();
+    let mut short = String::with_capacity(full.len());
+    let mut segment = String::new();
+    for c in full.chars() {
+        match c {
+            ':' => segment.clear(),
+            c if c.is_alphanumeric() || c == '_' => segment.push(c),
+            c => {
+                short.push_str(&segment);
+                segment.clear();
+                short.push(c);
+            }
+        }
+    }
+    short.push_str(&segment);
+    short
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{Kind, Policy, Session};
+    use crate::{FromJsonError, Maybe, MaybeValue, TryFromJson};
+
+    struct User {
+        name: String,
+    }
+
+    impl TryFromJson for User {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            if value.maybe_string("email").strict().is_none() {
+                super::warn("no email");
+            }
+            value.maybe_string("name").strict_ok().map(|name| User { name })
+        }
+    }
+
+    #[test]
+    fn session_trace() {
+        let doc = json!({"team": {"users": [{"name": "ada", "email": "a@x"}, {"name": 7}], "size": "2"}});
+
+        let (users, trace) = Session::new().run(|| {
+            let team = doc.maybe_node("team").strict_ok().unwrap();
+            assert!(matches!(team.maybe_uint("size"), Maybe::Relaxed(2)));
+            team.maybe_array::<User, _>("users").relaxed()
+        });
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name, "ada");
+
+        let events: Vec<(&str, Kind)> = trace.events.iter().map(|e| (e.path.as_str(), e.kind)).collect();
+        assert_eq!(events, vec!(("/size", Kind::Relaxed), ("/users/1", Kind::Warning), ("/users/1/name", Kind::Relaxed), ("/users", Kind::Relaxed)));
+        let audit = trace.audit();
+        assert!(!audit.is_ready());
+        assert_eq!((audit.findings[1].path.as_str(), audit.findings[1].from.as_str(), audit.findings[1].to.as_str()), ("/users/1/name", "number", "String"));
+
+        let (size, trace) = Session::new().policy(Policy::Strict).run(|| doc["team"].maybe_uint("size"));
+        assert!(matches!(size, Maybe::Error(_)));
+        assert_eq!(trace.of_kind(Kind::Error).count(), 1);
+        assert!(matches!(doc["team"].maybe_uint("size"), Maybe::Relaxed(2)));
+    }
+
+    #[test]
+    fn defaulted_fields() {
+        let doc = json!({"port": "http", "host": null, "tags": ["a"]});
+        let (_, trace) = Session::new().run(|| {
+            doc.maybe_uint("port").default(8080u64);
+            doc.maybe_string("host").relaxed();
+            doc.maybe_array::<Value, _>("tags").relaxed();
+            doc.maybe_bool("debug").default_for_null(false);
+        });
+
+        let defaults = trace.defaults();
+        assert_eq!(defaults.len(), 3);
+        assert_eq!((defaults[0].path.as_str(), defaults[0].default.as_str()), ("/port", "given u64"));
+        assert!(!defaults[0].reason.is_empty() && defaults[0].reason != "missing");
+        assert_eq!((defaults[1].path.as_str(), defaults[1].default.as_str(), defaults[1].reason.as_str()), ("/host", "String::default()", "null"));
+        assert_eq!((defaults[2].path.as_str(), defaults[2].reason.as_str()), ("/debug", "missing"));
+    }
+
+    #[test]
+    fn unused_keys() {
+        let doc = json!({"retires": 3, "server": {"port": 80, "hots": "a"}, "users": [{"name": "a", "x": 1}], "raw": {"a": 1}, "debug": true});
+        let (_, trace) = Session::new().run(|| {
+            doc.maybe_uint("retries").default(5u64);
+            let server = doc.maybe_node("server").strict_ok().unwrap();
+            server.maybe_uint("port");
+            server.maybe_string("host");
+            doc.maybe_array::<User, _>("users");
+            doc.maybe_object::<Value, _>("raw");
+        });
+        let mut unused = trace.unused(&doc);
+        unused.sort();
+        assert_eq!(unused, vec!("/debug", "/retires", "/server/hots", "/users/0/x"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Removing nulls and empty values from a document
+
+use serde_json::Value;
+
+/// What [`strip`] removes, nothing by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Strip {
+    pub nulls: bool,
+    pub empty_strings: bool,
+    pub empty_arrays: bool,
+    pub empty_objects: bool,
+}
+
+impl Strip {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Remove nulls, empty strings, empty arrays and empty objects
+    pub fn all() -> Self {
+        Strip { nulls: true, empty_strings: true, empty_arrays: true, empty_objects: true }
+    }
+
+    pub fn nulls(mut self, enable: bool) -> Self {
+        self.nulls = enable;
+        self
+    }
+
+    pub fn empty_strings(mut self, enable: bool) -> Self {
+        self.empty_strings = enable;
+        self
+    }
+
+    pub fn empty_arrays(mut self, enable: bool) -> Self {
+        self.empty_arrays = enable;
+        self
+    }
+
+    pub fn empty_objects(mut self, enable: bool) -> Self {
+        self.empty_objects = enable;
+        self
+    }
+
+    fn removes(&self, value: &Value) -> bool {
+        match value {
+            Value::Null => self.nulls,
+            Value::String(s) => self.empty_strings && s.is_empty(),
+            Value::Array(a) => self.empty_arrays && a.is_empty(),
+            Value::Object(o) => self.empty_objects && o.is_empty(),
+            _ => false
+        }
+    }
+}
+
+/// Remove members and elements selected by `options` throughout `value`.
+/// Children are stripped first, so containers that end up empty are removed as well.
+/// The root value itself is never removed.
+pub fn strip(value: &mut Value, options: &Strip) {
+    match value {
+        Value::Array(a) => {
+            for i in a.iter_mut() {
+                strip(i, options);
+            }
+            a.retain(|i| !options.removes(i));
+        },
+        Value::Object(o) => {
+            for (_, i) in o.iter_mut() {
+                strip(i, options);
+            }
+            o.retain(|_, i| !options.removes(i));
+        },
+        _ => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Strip;
+
+    #[test]
+    fn strip_empty_values() {
+        let doc = json!({"a": null, "b": "", "c": [null, 1, []], "d": {"e": null}, "f": 0});
+
+        let mut all = doc.clone();
+        super::strip(&mut all, &Strip::all());
+        assert_eq!(all, json!({"c": [1], "f": 0}));
+
+        let mut nulls = doc.clone();
+        super::strip(&mut nulls, &Strip::new().nulls(true));
+        assert_eq!(nulls, json!({"b": "", "c": [1, []], "d": {}, "f": 0}));
+    }
+}
@@ -0,0 +1,264 @@
+//! Resolving `${...}` placeholders in string values
+//!
+//! - `${other.key}` is replaced by the value at that (dotted) path of the same document
+//! - `${ENV_VAR}` is replaced by the environment variable, if no such path exists
+//! - `${VAR:-default}` falls back to `default` if neither can be resolved
+//! - `$${...}` is kept as the literal text `${...}`
+//!
+//! A string that consists of a single reference takes the type of the referenced
+//! value, so `"${server.port}"` can become the number `8080`.
+
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::{coerce, patch, Maybe};
+
+/// A placeholder that could not be resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    /// JSON Pointer to the string containing the placeholder
+    pub path: String,
+    /// The placeholder name, without a default
+    pub name: String,
+    /// The placeholder is part of a reference cycle
+    pub cycle: bool,
+}
+
+/// Resolve placeholders in all strings of `value` using the process environment,
+/// see [`Substitution`]. Unresolved placeholders are left in place and reported.
+pub fn substitute(value: &mut Value) -> Vec<Unresolved> {
+    Substitution::new().apply(value)
+}
+
+/// Looks up the value of a variable
+type Env = dyn Fn(&str) -> Option<String>;
+
+/// Configurable placeholder resolution
+pub struct Substitution {
+    env: Box<Env>,
+    references: bool,
+}
+
+impl Default for Substitution {
+    fn default() -> Self {
+        Substitution {
+            env: Box::new(|name| std::env::var(name).ok()),
+            references: true,
+        }
+    }
+}
+
+impl Substitution {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Look up variables with `env` instead of the process environment
+    pub fn env(mut self, env: impl Fn(&str) -> Option<String> + 'static) -> Self {
+        self.env = Box::new(env);
+        self
+    }
+
+    /// Resolve references to other values of the document, enabled by default
+    pub fn references(mut self, enable: bool) -> Self {
+        self.references = enable;
+        self
+    }
+
+    pub fn apply(&self, value: &mut Value) -> Vec<Unresolved> {
+        let original = value.clone();
+        let mut resolver = Resolver {
+            sub: self,
+            doc: &original,
+            memo: HashMap::new(),
+            stack: Vec::new(),
+        };
+        let mut unresolved = Vec::new();
+        resolver.walk(value, String::new(), &mut unresolved);
+        unresolved
+    }
+}
+
+enum Lookup {
+    Found(Value),
+    Missing,
+    Cycle,
+}
+
+struct Resolver<'a> {
+    sub: &'a Substitution,
+    doc: &'a Value,
+    /// resolved strings by pointer, None if they could not be resolved completely
+    memo: HashMap<String, Option<Value>>,
+    /// pointers currently being resolved
+    stack: Vec<String>,
+}
+
+impl <'a> Resolver<'a> {
+
+    fn walk(&mut self, value: &mut Value, path: String, unresolved: &mut Vec<Unresolved>) {
+        match value {
+            Value::String(s) if s.contains("${") => {
+                self.stack.push(path.clone());
+                let (resolved, problems) = self.expand(s);
+                self.stack.pop();
+                for (name, cycle) in problems {
+                    unresolved.push(Unresolved { path: path.clone(), name, cycle });
+                }
+                *value = resolved;
+            },
+            Value::Array(a) => {
+                for (i, v) in a.iter_mut().enumerate() {
+                    self.walk(v, format!("{}/{}", path, i), unresolved);
+                }
+            },
+            Value::Object(o) => {
+                for (k, v) in o.iter_mut() {
+                    self.walk(v, patch::child(&path, k), unresolved);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Resolve the value at `pointer` of the original document
+    fn resolve_at(&mut self, pointer: &str) -> Lookup {
+        if let Some(memo) = self.memo.get(pointer) {
+            return match memo {
+                Some(v) => Lookup::Found(v.clone()),
+                None => Lookup::Missing
+            };
+        }
+        if self.stack.iter().any(|p| p == pointer) {
+            return Lookup::Cycle;
+        }
+
+        let text = match self.doc.pointer(pointer) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => return Lookup::Found(other.clone()),
+            None => return Lookup::Missing
+        };
+
+        self.stack.push(s!(pointer));
+        let (value, problems) = self.expand(&text);
+        self.stack.pop();
+
+        match (problems.is_empty(), problems.iter().any(|(_, cycle)| *cycle)) {
+            // results depending on a cycle are only valid for the current stack
+            (_, true) => Lookup::Cycle,
+            (true, _) => {
+                self.memo.insert(s!(pointer), Some(value.clone()));
+                Lookup::Found(value)
+            },
+            (false, _) => {
+                self.memo.insert(s!(pointer), None);
+                Lookup::Missing
+            }
+        }
+    }
+
+    fn lookup(&mut self, name: &str) -> Lookup {
+        if self.sub.references {
+            let pointer = name.split('.').fold(String::new(), |p, k| patch::child(&p, k));
+            if self.doc.pointer(&pointer).is_some() {
+                return self.resolve_at(&pointer);
+            }
+        }
+        match (self.sub.env)(name) {
+            Some(v) => Lookup::Found(Value::String(v)),
+            None => Lookup::Missing
+        }
+    }
+
+    /// Expand all placeholders of `text`, returning unresolved names and whether they hit a cycle
+    fn expand(&mut self, text: &str) -> (Value, Vec<(String, bool)>) {
+        let mut out = String::new();
+        let mut problems = Vec::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("${") {
+            if rest[..start].ends_with('$') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("${");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            let end = match rest[start..].find('}') {
+                Some(e) => start + e,
+                None => break
+            };
+            out.push_str(&rest[..start]);
+            let inner = &rest[start + 2..end];
+            let (name, default) = match inner.find(":-") {
+                Some(p) => (&inner[..p], Some(&inner[p + 2..])),
+                None => (inner, None)
+            };
+
+            match (self.lookup(name), default) {
+                (Lookup::Found(v), _) => {
+                    // a lone reference keeps the type of the referenced value
+                    if start == 0 && end == text.len() - 1 && out.is_empty() {
+                        return (v, problems);
+                    }
+                    match (&v, coerce::to_string(Some(&v))) {
+                        (_, Maybe::Strict(s)) | (_, Maybe::Relaxed(s)) => out.push_str(&s),
+                        (Value::Null, _) => {},
+                        _ => out.push_str(&v.to_string())
+                    }
+                },
+                (_, Some(d)) => out.push_str(d),
+                (Lookup::Cycle, None) => {
+                    problems.push((s!(name), true));
+                    out.push_str(&rest[start..=end]);
+                },
+                (Lookup::Missing, None) => {
+                    problems.push((s!(name), false));
+                    out.push_str(&rest[start..=end]);
+                }
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        (Value::String(out), problems)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Substitution, Unresolved};
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some(s!("example.com")),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn substitute_placeholders() {
+        let mut doc = json!({
+            "a": "${b}",
+            "b": "x${a}",
+            "literal": "$${HOST}",
+            "missing": "${NOPE}",
+            "port": "${server.port}",
+            "server": {"host": "${HOST}", "port": 8080},
+            "url": "http://${server.host}:${server.port}/${PATH:-api}"
+        });
+        let unresolved = Substitution::new().env(env).apply(&mut doc);
+
+        assert_eq!(doc["url"], json!("http://example.com:8080/api"));
+        assert_eq!(doc["port"], json!(8080));
+        assert_eq!(doc["literal"], json!("${HOST}"));
+        assert_eq!(doc["missing"], json!("${NOPE}"));
+        assert_eq!(unresolved, vec!(
+            Unresolved { path: s!("/a"), name: s!("b"), cycle: true },
+            Unresolved { path: s!("/b"), name: s!("a"), cycle: true },
+            Unresolved { path: s!("/missing"), name: s!("NOPE"), cycle: false },
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+//! Loading arrays of records with a report of the rows that failed
+//!
+//! [`extract_table`] converts each element of an array with `TryFromJson`,
+//! keeping the rows that convert and reporting the others with the field
+//! that failed and a snippet of the record, for loading the good rows and
+//! quarantining the bad ones:
+//!
+//! ```
+//! use json_relaxed::table::extract_table;
+//! use json_relaxed::{FromJsonError, MaybeValue, TryFromJson};
+//! use serde_json::{json, Value};
+//!
+//! struct Order { qty: u64 }
+//!
+//! impl TryFromJson for Order {
+//!     fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+//!         Ok(Order { qty: value.maybe_uint("qty").strict_ok()? })
+//!     }
+//! }
+//!
+//! let feed = json!({"orders": [{"qty": 1}, {"qty": [2]}, {"qty": 3}]});
+//! let table = extract_table::<Order, _>(&feed, "orders").unwrap();
+//! assert_eq!(table.rows.len(), 2);
+//! assert_eq!((table.skipped[0].index, table.skipped[0].field.as_deref()), (1, Some("/qty")));
+//! assert_eq!(table.to_string(), "loaded 2 of 3 rows, skipped 1");
+//! ```
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::backend::Key;
+use crate::session::{self, Kind};
+use crate::{FromJsonError, TryFromJson};
+
+/// Length of the record snippets in [`Skipped`]
+const SNIPPET: usize = 120;
+
+/// A row that did not convert
+#[derive(Debug)]
+pub struct Skipped {
+    /// Index of the row in the array
+    pub index: usize,
+    /// JSON Pointer within the row to the first value that failed, if an
+    /// accessor failed
+    pub field: Option<String>,
+    pub error: FromJsonError,
+    /// The record as json, shortened to 120 characters
+    pub raw: String,
+}
+
+/// The converted rows and the skipped ones, see [`extract_table`]
+#[derive(Debug)]
+pub struct Table<T> {
+    pub rows: Vec<T>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl <T> Table<T> {
+
+    /// Number of rows read, converted or not
+    pub fn total(&self) -> usize {
+        self.rows.len() + self.skipped.len()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    /// Number of skipped rows by failing field, `""` for rows without one
+    pub fn by_field(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for skipped in &self.skipped {
+            *counts.entry(skipped.field.as_deref().unwrap_or_default()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+impl <T> fmt::Display for Table<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "loaded {} of {} rows, skipped {}", self.rows.len(), self.total(), self.skipped.len())
+    }
+}
+
+/// Convert the records of the array at `key` of `value`. A single record
+/// instead of an array is a table of one row, a missing or null array an error.
+/// Within a [session](crate::session) the rows report into it below `key`.
+pub fn extract_table<T: TryFromJson, I: Key>(value: &Value, key: I) -> Result<Table<T>, FromJsonError> {
+    let records = match key.lookup(value) {
+        Some(Value::Array(a)) => a.iter().collect(),
+        Some(Value::Null) | None => return Err(FromJsonError::with_message(&format!("missing `{}`", key.name()))),
+        Some(record) => vec!(record)
+    };
+
+    let mut table = Table { rows: Vec::new(), skipped: Vec::new() };
+    for (index, record) in records.into_iter().enumerate() {
+        let ((row, prefix), events) = session::scope(&[key.name(), index.to_string()], || (T::try_from_json(record), session::path()));
+        match row {
+            Ok(row) => table.rows.push(row),
+            Err(error) => {
+                let field = events.iter()
+                    .find(|e| e.kind == Kind::Error)
+                    .map(|e| s!(e.path.strip_prefix(prefix.as_str()).unwrap_or(&e.path)));
+                table.skipped.push(Skipped { index, field, error, raw: snippet(record) });
+            }
+        }
+    }
+    Ok(table)
+}
+
+fn snippet(record: &Value) -> String {
+    let text = record.to_string();
+    match text.char_indices().nth(SNIPPET) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::extract_table;
+    use crate::session::{Kind, Session};
+    use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+    #[derive(Debug, PartialEq)]
+    struct Line {
+        sku: String,
+        price: i64,
+    }
+
+    impl TryFromJson for Line {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            let sku = value.maybe_string("sku").strict_ok()?;
+            if sku.is_empty() {
+                return Err(FromJsonError::with_message("empty sku"));
+            }
+            Ok(Line { sku, price: value.maybe_int("price").strict_ok()? })
+        }
+    }
+
+    #[test]
+    fn rows_and_report() {
+        let long = "x".repeat(200);
+        let feed = json!({"lines": [
+            {"sku": "a", "price": 100},
+            {"sku": "b", "price": {}},
+            {"sku": "", "price": 1},
+            {"sku": long, "price": []},
+        ]});
+        let table = extract_table::<Line, _>(&feed, "lines").unwrap();
+        assert_eq!(table.rows, vec!(Line { sku: s!("a"), price: 100 }));
+        let report: Vec<_> = table.skipped.iter().map(|s| (s.index, s.field.as_deref())).collect();
+        assert_eq!(report, vec!((1, Some("/price")), (2, None), (3, Some("/price"))));
+        assert_eq!(table.skipped[2].raw.chars().count(), 121);
+        assert_eq!(table.by_field().get("/price"), Some(&2));
+        assert_eq!((table.total(), table.is_clean()), (4, false));
+
+        let single = extract_table::<Line, _>(&json!({"lines": {"sku": "s", "price": 5}}), "lines").unwrap();
+        assert!(single.is_clean());
+        assert!(extract_table::<Line, _>(&feed, "missing").is_err());
+    }
+
+    #[test]
+    fn within_session() {
+        let feed = json!({"lines": [{"sku": "a", "price": 1}, {"sku": "b", "price": {}, "note": ""}]});
+        let (table, trace) = Session::new().run(|| {
+            let table = extract_table::<Line, _>(&feed, "lines").unwrap();
+            feed.maybe_uint("count").relaxed();
+            table
+        });
+        assert_eq!((table.rows.len(), table.skipped[0].index, table.skipped[0].field.as_deref()), (1, 1, Some("/price")));
+
+        let events: Vec<(&str, Kind)> = trace.events.iter().map(|e| (e.path.as_str(), e.kind)).collect();
+        assert_eq!(events, vec!(("/lines/1/price", Kind::Error), ("/count", Kind::Default)));
+        assert_eq!(trace.unused(&feed), vec!("/lines/1/note"));
+    }
+}
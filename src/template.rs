@@ -0,0 +1,157 @@
+//! Conforming a document to the shape and types of a template document
+
+use serde_json::{Map, Number, Value};
+
+use crate::{coerce, patch, Maybe};
+
+/// What happens to keys of the input that are not part of the template
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtraKeys {
+    #[default]
+    Drop,
+    Keep,
+}
+
+/// A single modification made by [`coerce_to_template`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The value was converted to the type of the template
+    Coerced(String),
+    /// The value was missing or null and the template value was used
+    Defaulted(String),
+    /// The value could not be converted and was replaced by the template value
+    Replaced(String),
+    /// The key is not part of the template and was removed
+    Dropped(String),
+}
+
+/// Conform `value` to `template`, dropping extra keys.
+/// Missing values are filled in from the template, so the template doubles as defaults.
+/// A non-empty array in the template describes all elements by its first element,
+/// a null in the template accepts any value.
+pub fn coerce_to_template(value: &Value, template: &Value) -> (Value, Vec<Change>) {
+    coerce_to_template_with(value, template, ExtraKeys::Drop)
+}
+
+/// Like [`coerce_to_template`] with a choice about extra keys
+pub fn coerce_to_template_with(value: &Value, template: &Value, extra: ExtraKeys) -> (Value, Vec<Change>) {
+    let mut changes = Vec::new();
+    let conformed = conform(value, template, extra, String::new(), &mut changes);
+    (conformed, changes)
+}
+
+fn conform(value: &Value, template: &Value, extra: ExtraKeys, path: String, changes: &mut Vec<Change>) -> Value {
+    match (template, value) {
+        (Value::Null, _) => value.clone(),
+        (_, Value::Null) => {
+            changes.push(Change::Defaulted(path));
+            template.clone()
+        },
+        (Value::Object(t), Value::Object(v)) => {
+            let mut collect = Map::new();
+            for (k, tv) in t.iter() {
+                let child = patch::child(&path, k);
+                match v.get(k) {
+                    Some(vv) => {
+                        collect.insert(k.clone(), conform(vv, tv, extra, child, changes));
+                    },
+                    None => {
+                        changes.push(Change::Defaulted(child));
+                        collect.insert(k.clone(), tv.clone());
+                    }
+                }
+            }
+            for (k, vv) in v.iter().filter(|(k, _)| !t.contains_key(*k)) {
+                match extra {
+                    ExtraKeys::Keep => {
+                        collect.insert(k.clone(), vv.clone());
+                    },
+                    ExtraKeys::Drop => changes.push(Change::Dropped(patch::child(&path, k)))
+                }
+            }
+            Value::Object(collect)
+        },
+        (Value::Array(t), Value::Array(v)) => {
+            match t.first() {
+                Some(element) => Value::Array(v.iter().enumerate()
+                    .map(|(i, vv)| conform(vv, element, extra, format!("{}/{}", path, i), changes))
+                    .collect()),
+                None => value.clone()
+            }
+        },
+        (Value::Array(t), _) => {
+            // a single value where a list is expected, like maybe_array
+            changes.push(Change::Coerced(path.clone()));
+            let element = match t.first() {
+                Some(element) => conform(value, element, extra, format!("{}/0", path), changes),
+                None => value.clone()
+            };
+            Value::Array(vec!(element))
+        },
+        (Value::Object(_), _) => {
+            changes.push(Change::Replaced(path));
+            template.clone()
+        },
+        _ => {
+            match scalar(value, template) {
+                Maybe::Strict(v) => v,
+                Maybe::Relaxed(v) => {
+                    changes.push(Change::Coerced(path));
+                    v
+                },
+                _ => {
+                    changes.push(Change::Replaced(path));
+                    template.clone()
+                }
+            }
+        }
+    }
+}
+
+fn scalar(value: &Value, template: &Value) -> Maybe<Value> {
+    match template {
+        Value::Bool(_) => map(coerce::to_bool(Some(value)), Value::Bool),
+        Value::String(_) => map(coerce::to_string(Some(value)), Value::String),
+        Value::Number(n) if n.is_f64() => map(coerce::to_float(Some(value)), |f| {
+            Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+        }),
+        Value::Number(_) => map(coerce::to_int(Some(value)), |i| Value::Number(i.into())),
+        _ => Maybe::Error(coerce::mismatch(value))
+    }
+}
+
+fn map<T>(maybe: Maybe<T>, f: impl FnOnce(T) -> Value) -> Maybe<Value> {
+    match maybe {
+        Maybe::Strict(v) => Maybe::Strict(f(v)),
+        Maybe::Relaxed(v) => Maybe::Relaxed(f(v)),
+        Maybe::Null => Maybe::Null,
+        Maybe::Error(e) => Maybe::Error(e)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Change, ExtraKeys};
+
+    #[test]
+    fn conform_to_template() {
+        let template = json!({"debug": false, "hosts": ["localhost"], "port": 80, "ratio": 0.5, "tls": {"cert": ""}});
+        let input = json!({"port": "8080", "ratio": 1, "hosts": "example.com", "tls": 5, "extra": true});
+
+        let (value, changes) = super::coerce_to_template(&input, &template);
+        assert_eq!(value, json!({"port": 8080, "ratio": 1.0, "debug": false, "hosts": ["example.com"], "tls": {"cert": ""}}));
+        assert_eq!(changes, vec!(
+            Change::Defaulted("/debug".into()),
+            Change::Coerced("/hosts".into()),
+            Change::Coerced("/port".into()),
+            Change::Replaced("/tls".into()),
+            Change::Dropped("/extra".into()),
+        ));
+
+        let (value, _) = super::coerce_to_template_with(&input, &template, ExtraKeys::Keep);
+        assert_eq!(value["extra"], json!(true));
+    }
+}
@@ -0,0 +1,226 @@
+//! proptest strategies generating messy json for fuzzing `TryFromJson` models
+//!
+//! [`value`] generates arbitrary documents built the way real feeds go wrong:
+//! numbers and booleans as strings, empty strings, nulls and deep nesting.
+//! [`perturb`] starts from a valid example and swaps types, drops keys, nulls or
+//! wraps values. [`assert_never_panics`] runs a model over such documents.
+//!
+//! [`assert_json_relaxed_eq!`](crate::assert_json_relaxed_eq) compares documents
+//! with [`relaxed_eq`](crate::compare::relaxed_eq) and
+//! [`assert_extracts!`](crate::assert_extracts) checks the result of `TryFromJson`,
+//! both list the differing paths on failure:
+//!
+//! ```
+//! use json_relaxed::compare::Compare;
+//! use json_relaxed::{assert_extracts, assert_json_relaxed_eq};
+//! use serde_json::json;
+//!
+//! assert_json_relaxed_eq!(json!({"port": "8080", "tls": 1}), json!({"tls": true, "port": 8080}));
+//! assert_json_relaxed_eq!(json!(0.1 + 0.2), json!(0.3), compare = Compare::new().relative_tolerance(1e-12));
+//! assert_extracts!(json!(["a", 1]), json!(["a", 1]));
+//! ```
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestError, TestRunner};
+use serde_json::{Map, Number, Value};
+
+use crate::compare::Compare;
+use crate::{FromJsonError, TryFromJson};
+
+/// Scalars including numbers and booleans written as text
+pub fn scalar() -> BoxedStrategy<Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(|i| Value::Number(i.into())),
+        any::<u64>().prop_map(|u| Value::Number(u.into())),
+        any::<f64>().prop_map(|f| Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)),
+        any::<i64>().prop_map(|i| Value::String(i.to_string())),
+        any::<f64>().prop_map(|f| Value::String(f.to_string())),
+        prop::sample::select(WORDS).prop_map(|w| Value::String(s!(w))),
+        "\\PC{0,12}".prop_map(Value::String),
+    ].boxed()
+}
+
+const WORDS: &[&str] = &["", " ", "true", "false", "TRUE", "yes", "no", "on", "off", "1", "0", " 42 ", "1e3", "NaN", "null", "-"];
+
+/// Documents nested up to `depth` levels, objects use `keys` and random keys
+pub fn value(keys: &[&str], depth: u32) -> BoxedStrategy<Value> {
+    let known: Vec<String> = keys.iter().map(|k| s!(*k)).collect();
+    let key = match known.is_empty() {
+        true => "[a-z]{1,6}".boxed(),
+        false => prop_oneof![3 => prop::sample::select(known), 1 => "[a-z]{1,6}".prop_map(String::from)].boxed()
+    };
+    scalar().prop_recursive(depth, 64, 6, move |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::btree_map(key.clone(), inner, 0..6).prop_map(|m| Value::Object(m.into_iter().collect())),
+        ]
+    }).boxed()
+}
+
+/// Variations of `example` with values stringified, swapped, nulled, wrapped or dropped
+pub fn perturb(example: &Value) -> BoxedStrategy<Value> {
+    let nested = match example {
+        Value::Object(o) => {
+            let entries: Vec<_> = o.iter()
+                .map(|(k, v)| (Just(k.clone()), prop::option::weighted(0.8, perturb(v))))
+                .collect();
+            entries.prop_map(|entries| {
+                Value::Object(entries.into_iter().filter_map(|(k, v)| v.map(|v| (k, v))).collect::<Map<_, _>>())
+            }).boxed()
+        },
+        Value::Array(a) => a.iter().map(perturb).collect::<Vec<_>>().prop_map(Value::Array).boxed(),
+        v => Just(v.clone()).boxed()
+    };
+    let text = match example {
+        Value::String(s) => Value::String(s.clone()),
+        v => Value::String(v.to_string())
+    };
+    prop_oneof![
+        8 => nested,
+        1 => Just(text),
+        1 => Just(Value::Null),
+        1 => Just(Value::Array(vec!(example.clone()))),
+        1 => scalar(),
+    ].boxed()
+}
+
+/// Panic with the smallest failing document if `T::try_from_json` panics on any
+/// of `cases` documents from `strategy`, errors are fine
+pub fn assert_never_panics<T: TryFromJson>(strategy: impl Strategy<Value = Value>, cases: u32) {
+    let mut runner = TestRunner::new(Config { cases, failure_persistence: None, ..Config::default() });
+    let result = runner.run(&strategy, |value| {
+        let _ = T::try_from_json(&value);
+        Ok(())
+    });
+    match result {
+        Ok(()) => {},
+        Err(TestError::Fail(reason, value)) => {
+            panic!("{} panicked on {}: {}", std::any::type_name::<T>(), value, reason)
+        },
+        Err(TestError::Abort(reason)) => panic!("{}", reason)
+    }
+}
+
+/// The differences of `left` and `right` under relaxed comparison, one path a line,
+/// None if they are equal
+pub fn relaxed_diff_report(left: &Value, right: &Value) -> Option<String> {
+    relaxed_diff_report_with(left, right, &Compare::new())
+}
+
+/// Like [`relaxed_diff_report`], comparing with `compare`
+pub fn relaxed_diff_report_with(left: &Value, right: &Value, compare: &Compare) -> Option<String> {
+    let paths = compare.diff(left, right);
+    if paths.is_empty() {
+        return None;
+    }
+    let show = |v: Option<&Value>| v.map_or_else(|| s!("<missing>"), Value::to_string);
+    let lines: Vec<String> = paths.iter()
+        .map(|p| format!("  {}: left {}, right {}", if p.is_empty() { "/" } else { p }, show(left.pointer(p)), show(right.pointer(p))))
+        .collect();
+    Some(format!("{} differing paths:\n{}", paths.len(), lines.join("\n")))
+}
+
+/// `T::try_from_json(value)` with `T` taken from `_like`, for [`assert_extracts!`](crate::assert_extracts)
+pub fn extract_like<T: TryFromJson>(value: &Value, _like: &T) -> Result<T, FromJsonError> {
+    T::try_from_json(value)
+}
+
+/// Assert that two `serde_json::Value`s are equal under relaxed comparison,
+/// `1 == "1"` and `true == 1`. A [`Compare`] is given as `compare = ...`.
+#[macro_export]
+macro_rules! assert_json_relaxed_eq {
+    ($left:expr, $right:expr, compare = $compare:expr $(,)?) => {
+        if let Some(report) = $crate::testing::relaxed_diff_report_with(&$left, &$right, &$compare) {
+            panic!("assertion `left ~= right` failed, {}", report);
+        }
+    };
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(report) = $crate::testing::relaxed_diff_report(&$left, &$right) {
+            panic!("assertion `left ~= right` failed, {}", report);
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        if let Some(report) = $crate::testing::relaxed_diff_report(&$left, &$right) {
+            panic!("assertion `left ~= right` failed: {}, {}", format_args!($($arg)+), report);
+        }
+    };
+}
+
+/// Assert that a `serde_json::Value` converts with `TryFromJson` into `expected`,
+/// the type is that of `expected`
+#[macro_export]
+macro_rules! assert_extracts {
+    ($value:expr, $expected:expr $(,)?) => {{
+        let expected = $expected;
+        match $crate::testing::extract_like(&$value, &expected) {
+            Ok(found) => assert_eq!(found, expected, "extracted value differs from the expected one"),
+            Err(e) => panic!("extraction into {} failed: {}", std::any::type_name_of_val(&expected), e)
+        }
+    }};
+}
+
+
+#[cfg(test)]
+mod tests {
+    use proptest::strategy::{Strategy, ValueTree};
+    use proptest::test_runner::TestRunner;
+    use serde_json::{json, Value};
+
+    use crate::{FromJsonError, MaybeValue, TryFromJson};
+
+    struct Tolerant;
+
+    impl TryFromJson for Tolerant {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            value.maybe_int("id").strict_ok()?;
+            Ok(Tolerant)
+        }
+    }
+
+    struct Fragile;
+
+    impl TryFromJson for Fragile {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            let _ = value["tags"].as_array().unwrap();
+            Ok(Fragile)
+        }
+    }
+
+    #[test]
+    fn messy_documents() {
+        let example = json!({"id": 1, "tags": ["a"]});
+        super::assert_never_panics::<Tolerant>(super::value(&["id", "tags"], 4), 200);
+        super::assert_never_panics::<Tolerant>(super::perturb(&example), 200);
+
+        let result = std::panic::catch_unwind(|| super::assert_never_panics::<Fragile>(super::perturb(&example), 200));
+        assert!(result.is_err());
+
+        let mut runner = TestRunner::deterministic();
+        let strategy = super::perturb(&example);
+        let variants: Vec<Value> = (0..50).map(|_| strategy.new_tree(&mut runner).unwrap().current()).collect();
+        assert!(variants.contains(&example) && variants.iter().any(|v| v != &example));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Port(u64);
+
+    impl TryFromJson for Port {
+        fn try_from_json(value: &Value) -> Result<Self, FromJsonError> {
+            value.maybe_uint("port").strict_ok().map(Port)
+        }
+    }
+
+    #[test]
+    fn relaxed_assertions() {
+        crate::assert_json_relaxed_eq!(json!({"a": [1, "x"], "b": "true"}), json!({"b": true, "a": ["1", "x"]}));
+        crate::assert_extracts!(json!({"port": 80}), Port(80));
+
+        let report = super::relaxed_diff_report(&json!({"a": 1, "b": [1]}), &json!({"a": "2", "b": []})).unwrap();
+        assert_eq!(report, "2 differing paths:\n  /a: left 1, right \"2\"\n  /b/0: left 1, right <missing>");
+
+        let failed = std::panic::catch_unwind(|| crate::assert_extracts!(json!({"port": "80"}), Port(80)));
+        assert!(failed.is_err());
+    }
+}
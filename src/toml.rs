@@ -0,0 +1,132 @@
+//! Reading TOML documents into json values
+
+use serde_json::{Map, Number, Value};
+
+use crate::FromJsonError;
+
+/// How TOML datetimes are represented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Datetimes {
+    /// RFC 3339 text like `"1979-05-27T07:32:00Z"`
+    #[default]
+    String,
+    /// An object with the `date`, `time` and `offset` present, as text
+    Parts,
+    /// Fail on datetimes
+    Error,
+}
+
+/// Options for reading TOML input
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    datetimes: Datetimes,
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn datetimes(mut self, datetimes: Datetimes) -> Self {
+        self.datetimes = datetimes;
+        self
+    }
+}
+
+/// Parse a TOML document
+pub fn from_str(text: &str) -> Result<Value, FromJsonError> {
+    from_str_with(text, &Options::default())
+}
+
+/// Parse a TOML document using `options`
+pub fn from_str_with(text: &str, options: &Options) -> Result<Value, FromJsonError> {
+    match text.parse::<toml::Table>() {
+        Ok(table) => convert(toml::Value::Table(table), options),
+        Err(e) => {
+            let error = FromJsonError::with_message(e.message());
+            match e.span() {
+                Some(span) => {
+                    // 1-based like serde_json positions
+                    let before = &text[..span.start];
+                    let line = before.matches('\n').count() + 1;
+                    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+                    Err(error.with_position(line, column))
+                },
+                None => Err(error)
+            }
+        }
+    }
+}
+
+fn convert(toml: toml::Value, options: &Options) -> Result<Value, FromJsonError> {
+    use toml::Value as Toml;
+
+    Ok(match toml {
+        Toml::String(s) => Value::String(s),
+        Toml::Integer(i) => Value::Number(i.into()),
+        Toml::Float(f) => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+        Toml::Boolean(b) => Value::Bool(b),
+        Toml::Datetime(dt) => {
+            match options.datetimes {
+                Datetimes::String => Value::String(dt.to_string()),
+                Datetimes::Parts => {
+                    let mut parts = Map::new();
+                    if let Some(date) = dt.date {
+                        parts.insert(s!("date"), Value::String(date.to_string()));
+                    }
+                    if let Some(time) = dt.time {
+                        parts.insert(s!("time"), Value::String(time.to_string()));
+                    }
+                    if let Some(offset) = dt.offset {
+                        parts.insert(s!("offset"), Value::String(offset.to_string()));
+                    }
+                    Value::Object(parts)
+                },
+                Datetimes::Error => return Err(FromJsonError::with_message(&format!("unsupported datetime: {}", dt)))
+            }
+        },
+        Toml::Array(a) => {
+            Value::Array(a.into_iter().map(|v| convert(v, options)).collect::<Result<_, _>>()?)
+        },
+        Toml::Table(t) => {
+            let mut map = Map::new();
+            for (k, v) in t {
+                map.insert(k, convert(v, options)?);
+            }
+            Value::Object(map)
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Datetimes, Options};
+
+    #[test]
+    fn toml_documents() {
+        let text = r#"
+[package]
+name = "json-relaxed"
+edition = "2018"
+published = 1979-05-27T07:32:00Z
+
+[[bin]]
+name = "cli"
+ratio = nan
+"#;
+        assert_eq!(super::from_str(text).unwrap(), json!({
+            "package": {"name": "json-relaxed", "edition": "2018", "published": "1979-05-27T07:32:00Z"},
+            "bin": [{"name": "cli", "ratio": null}]
+        }));
+
+        let parts = super::from_str_with(text, &Options::new().datetimes(Datetimes::Parts)).unwrap();
+        assert_eq!(parts["package"]["published"], json!({"date": "1979-05-27", "time": "07:32:00", "offset": "Z"}));
+        assert!(super::from_str_with(text, &Options::new().datetimes(Datetimes::Error)).is_err());
+
+        assert_eq!(super::from_str("a = 1\nb = ").unwrap_err().position(), Some((2, 5)));
+    }
+}
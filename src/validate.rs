@@ -0,0 +1,326 @@
+//! Declarative validation of documents by path
+//!
+//! Constraints are declared per JSON Pointer, in code or as a json document,
+//! where a `*` segment stands for every member or element. Values are read with
+//! the relaxed rules before they are checked, so `"8080"` satisfies a range of
+//! ports. All violations are collected into one [`Report`]:
+//!
+//! ```
+//! use json_relaxed::validate::Validator;
+//! use serde_json::json;
+//!
+//! let validator = Validator::from_json(&json!({
+//!     "/server/port": {"required": true, "min": 1, "max": 65535},
+//!     "/users/*/name": {"required": true, "minLength": 1},
+//!     "/mode": {"enum": ["dev", "prod"]}
+//! })).unwrap();
+//!
+//! let report = validator.validate(&json!({"server": {"port": "80"}, "users": [{"name": ""}], "mode": "test"}));
+//! assert_eq!(report.violations.len(), 2);
+//! ```
+//!
+//! `pattern` needs the `regex` feature. Rules over several fields are given as
+//! closures with [`Validator::rule`].
+
+use serde_json::Value;
+
+use crate::schema::Violation;
+use crate::{coerce, compare, patch, pattern, FromJsonError, Maybe};
+
+enum Constraint {
+    Required,
+    Min(f64),
+    Max(f64),
+    MinLength(usize),
+    MaxLength(usize),
+    Allowed(Vec<Value>),
+    #[cfg(feature = "regex")]
+    Pattern(regex::Regex),
+}
+
+/// Checks the whole document, an `Err` is reported at the rule's path
+type Rule = dyn Fn(&Value) -> Result<(), String>;
+
+/// Constraints by path, see the [module documentation](self)
+#[derive(Default)]
+pub struct Validator {
+    constraints: Vec<(Vec<String>, Constraint)>,
+    rules: Vec<(String, Box<Rule>)>,
+}
+
+/// Outcome of [`Validator::validate`]
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub violations: Vec<Violation>,
+}
+
+impl Report {
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// An error listing all violations
+    pub fn into_result(self) -> Result<(), FromJsonError> {
+        match self.violations.is_empty() {
+            true => Ok(()),
+            false => {
+                let messages: Vec<String> = self.violations.iter()
+                    .map(|v| format!("{}: {}", if v.path.is_empty() { "/" } else { &v.path }, v.message))
+                    .collect();
+                Err(FromJsonError::with_message(&messages.join("; ")))
+            }
+        }
+    }
+}
+
+impl Validator {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Constraints as a document mapping paths to keywords: `required`, `min`,
+    /// `max`, `minLength`, `maxLength`, `enum` and `pattern`
+    pub fn from_json(rules: &Value) -> Result<Self, FromJsonError> {
+        let rules = match rules {
+            Value::Object(o) => o,
+            _ => return Err(FromJsonError::with_message("validation rules must be an object"))
+        };
+        let mut validator = Validator::new();
+        for (path, keywords) in rules {
+            let keywords = match keywords {
+                Value::Object(k) => k,
+                _ => return Err(FromJsonError::with_message(&format!("{}: constraints must be an object", path)))
+            };
+            for (keyword, arg) in keywords {
+                let invalid = || FromJsonError::with_message(&format!("{}: invalid argument for `{}`", path, keyword));
+                let number = || coerce::to_float(Some(arg)).strict().ok_or_else(invalid);
+                let length = || coerce::to_uint(Some(arg)).strict().map(|n| n as usize).ok_or_else(invalid);
+                let constraint = match keyword.as_str() {
+                    "required" if arg == &Value::Bool(true) => Constraint::Required,
+                    "required" if arg == &Value::Bool(false) => continue,
+                    "min" => Constraint::Min(number()?),
+                    "max" => Constraint::Max(number()?),
+                    "minLength" => Constraint::MinLength(length()?),
+                    "maxLength" => Constraint::MaxLength(length()?),
+                    "enum" => Constraint::Allowed(arg.as_array().ok_or_else(invalid)?.clone()),
+                    #[cfg(feature = "regex")]
+                    "pattern" => {
+                        let re = arg.as_str().ok_or_else(invalid)?;
+                        Constraint::Pattern(regex::Regex::new(re).map_err(|e| FromJsonError::with_message(&format!("{}: {}", path, e)))?)
+                    },
+                    _ => return Err(FromJsonError::with_message(&format!("{}: unsupported constraint `{}`", path, keyword)))
+                };
+                validator = validator.constraint(path, constraint);
+            }
+        }
+        Ok(validator)
+    }
+
+    fn constraint(mut self, path: &str, constraint: Constraint) -> Self {
+        self.constraints.push((pattern::tokens(path), constraint));
+        self
+    }
+
+    /// The value must exist and not be null
+    pub fn required(self, path: &str) -> Self {
+        self.constraint(path, Constraint::Required)
+    }
+
+    /// Numbers, or values read as numbers, of at least `min`
+    pub fn min(self, path: &str, min: f64) -> Self {
+        self.constraint(path, Constraint::Min(min))
+    }
+
+    pub fn max(self, path: &str, max: f64) -> Self {
+        self.constraint(path, Constraint::Max(max))
+    }
+
+    /// Strings of at least `min` characters or arrays of at least `min` elements
+    pub fn min_length(self, path: &str, min: usize) -> Self {
+        self.constraint(path, Constraint::MinLength(min))
+    }
+
+    pub fn max_length(self, path: &str, max: usize) -> Self {
+        self.constraint(path, Constraint::MaxLength(max))
+    }
+
+    /// One of `values`, compared with [`relaxed_eq`](crate::compare::relaxed_eq)
+    pub fn allowed(self, path: &str, values: Vec<Value>) -> Self {
+        self.constraint(path, Constraint::Allowed(values))
+    }
+
+    /// Strings, or values read as strings, matching `re`
+    #[cfg(feature = "regex")]
+    pub fn pattern(self, path: &str, re: regex::Regex) -> Self {
+        self.constraint(path, Constraint::Pattern(re))
+    }
+
+    /// A rule over the whole document, like a field that is required if another one is set
+    pub fn rule(mut self, path: &str, check: impl Fn(&Value) -> Result<(), String> + 'static) -> Self {
+        self.rules.push((s!(path), Box::new(check)));
+        self
+    }
+
+    pub fn validate(&self, value: &Value) -> Report {
+        let mut violations = Vec::new();
+        for (tokens, constraint) in &self.constraints {
+            let mut found = Vec::new();
+            walk(value, tokens, String::new(), &mut found);
+            for (path, v) in found {
+                if let Some(message) = check(constraint, v) {
+                    violations.push(Violation { path, message });
+                }
+            }
+        }
+        for (path, rule) in &self.rules {
+            if let Err(message) = rule(value) {
+                violations.push(Violation { path: path.clone(), message });
+            }
+        }
+        Report { violations }
+    }
+}
+
+/// The values at `pattern` with their JSON Pointers, as a constraint declared
+/// for `pattern` sees them. A `*` segment matches every member or element.
+pub fn select<'a>(value: &'a Value, pattern: &str) -> Vec<(String, &'a Value)> {
+    let mut found = Vec::new();
+    walk(value, &pattern::tokens(pattern), String::new(), &mut found);
+    found.into_iter().filter_map(|(path, v)| v.map(|v| (path, v))).collect()
+}
+
+/// The values at `tokens`, None where the last segment is missing
+fn walk<'a>(value: &'a Value, tokens: &[String], path: String, found: &mut Vec<(String, Option<&'a Value>)>) {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return found.push((path, Some(value)))
+    };
+    match (token.as_str(), value) {
+        ("*", Value::Object(o)) => {
+            for (k, v) in o {
+                walk(v, rest, patch::child(&path, k), found);
+            }
+        },
+        ("*", Value::Array(a)) => {
+            for (i, v) in a.iter().enumerate() {
+                walk(v, rest, patch::child(&path, &i.to_string()), found);
+            }
+        },
+        ("*", _) => {},
+        (t, v) => {
+            let child = match v {
+                Value::Object(o) => o.get(t),
+                Value::Array(a) => t.parse::<usize>().ok().and_then(|i| a.get(i)),
+                _ => None
+            };
+            match child {
+                Some(c) => walk(c, rest, patch::child(&path, t), found),
+                None if rest.is_empty() => found.push((patch::child(&path, t), None)),
+                // a missing parent is reported by its own constraints
+                None => {}
+            }
+        }
+    }
+}
+
+fn check(constraint: &Constraint, value: Option<&Value>) -> Option<String> {
+    let value = match (constraint, value) {
+        (Constraint::Required, None | Some(Value::Null)) => return Some(s!("is required")),
+        (_, None | Some(Value::Null)) => return None,
+        (_, Some(v)) => v
+    };
+    let number = || match coerce::to_float(Some(value)) {
+        Maybe::Strict(f) | Maybe::Relaxed(f) => Some(f),
+        _ => None
+    };
+    let length = || match value {
+        Value::Array(a) => Some(a.len()),
+        v => coerce::to_str(Some(v)).strict().map(|s| s.chars().count())
+    };
+    match constraint {
+        Constraint::Required => None,
+        Constraint::Min(min) => match number() {
+            Some(n) if n >= *min => None,
+            Some(n) => Some(format!("{} is less than {}", n, min)),
+            None => Some(format!("expected a number, found {}", coerce::type_name(value)))
+        },
+        Constraint::Max(max) => match number() {
+            Some(n) if n <= *max => None,
+            Some(n) => Some(format!("{} is greater than {}", n, max)),
+            None => Some(format!("expected a number, found {}", coerce::type_name(value)))
+        },
+        Constraint::MinLength(min) => match length() {
+            Some(l) if l >= *min => None,
+            Some(l) => Some(format!("length {} is less than {}", l, min)),
+            None => Some(format!("expected a string or array, found {}", coerce::type_name(value)))
+        },
+        Constraint::MaxLength(max) => match length() {
+            Some(l) if l <= *max => None,
+            Some(l) => Some(format!("length {} is greater than {}", l, max)),
+            None => Some(format!("expected a string or array, found {}", coerce::type_name(value)))
+        },
+        Constraint::Allowed(values) => match values.iter().any(|a| compare::relaxed_eq(a, value)) {
+            true => None,
+            false => Some(format!("{} is not one of {}", value, Value::Array(values.clone())))
+        },
+        #[cfg(feature = "regex")]
+        Constraint::Pattern(re) => match coerce::to_str(Some(value)) {
+            Maybe::Strict(s) | Maybe::Relaxed(s) if re.is_match(&s) => None,
+            Maybe::Strict(s) | Maybe::Relaxed(s) => Some(format!("{:?} does not match {}", s, re)),
+            _ => Some(format!("expected a string, found {}", coerce::type_name(value)))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Validator;
+
+    #[test]
+    fn declared_constraints() {
+        let validator = Validator::from_json(&json!({
+            "/port": {"required": true, "min": 1, "max": 65535},
+            "/users/*/name": {"required": true, "maxLength": 3},
+            "/users/*/role": {"enum": ["admin", "user"]},
+            "/tags": {"minLength": 1}
+        })).unwrap()
+            .rule("/tls", |doc| match (doc.get("tls"), doc.get("cert")) {
+                (Some(_), None) => Err(s!("tls needs a cert")),
+                _ => Ok(())
+            });
+
+        let report = validator.validate(&json!({"port": "443", "users": [{"name": "ada", "role": "admin"}], "tags": ["a"]}));
+        assert!(report.is_valid());
+
+        let report = validator.validate(&json!({"port": "0", "users": [{"name": "grace"}, {"role": "guest"}], "tags": [], "tls": true}));
+        let mut paths: Vec<&str> = report.violations.iter().map(|v| v.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec!("/port", "/tags", "/tls", "/users/0/name", "/users/1/name", "/users/1/role"));
+        assert!(report.into_result().unwrap_err().message().starts_with("/port: 0 is less than 1; "));
+
+        assert!(Validator::from_json(&json!({"/a": {"unknown": 1}})).is_err());
+    }
+
+    #[test]
+    fn selected_values() {
+        let doc = json!({"items": [{"id": 1}, {"id": 2}, {"name": "x"}], "a/b": true});
+        assert_eq!(super::select(&doc, "/items/*/id"), vec!((s!("/items/0/id"), &json!(1)), (s!("/items/1/id"), &json!(2))));
+        assert_eq!(super::select(&doc, "/a~1b"), vec!((s!("/a~1b"), &json!(true))));
+        assert_eq!(super::select(&doc, ""), vec!((s!(""), &doc)));
+        assert!(super::select(&doc, "/items/9").is_empty());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn patterns() {
+        let validator = Validator::new().pattern("/*", regex::Regex::new("^[a-z]+$").unwrap());
+        let report = validator.validate(&json!({"a": "abc", "b": "ABC", "c": 12}));
+        assert_eq!(report.violations.len(), 2);
+        assert!(Validator::from_json(&json!({"/a": {"pattern": "("}})).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Converting between `JsValue` and json values for browser-side tooling
+//!
+//! [`from_js`] reads plain JavaScript data the way `JSON.stringify` would see it,
+//! with some leniency: `undefined` becomes null, BigInts become their digits,
+//! Dates ISO text, `Map`s objects with string keys and integral numbers json
+//! integers. Functions and symbols are errors. The converted value then goes
+//! through the same maybe_* accessors as on the backend.
+
+use js_sys::{Array, BigInt, Date, Object, Reflect};
+use serde_json::{Map, Number, Value};
+use wasm_bindgen::{JsCast, JsValue};
+
+use crate::FromJsonError;
+
+/// Largest integer a JavaScript number represents exactly
+const SAFE_INTEGER: f64 = 9007199254740991.0;
+
+/// Convert a JavaScript value to json
+pub fn from_js(value: &JsValue) -> Result<Value, FromJsonError> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(Value::Null);
+    }
+    if let Some(b) = value.as_bool() {
+        return Ok(Value::Bool(b));
+    }
+    if let Some(f) = value.as_f64() {
+        return Ok(number(f));
+    }
+    if let Some(s) = value.as_string() {
+        return Ok(Value::String(s));
+    }
+    if value.is_bigint() {
+        let digits = BigInt::unchecked_from_js_ref(value).to_string(10)
+            .map_err(|e| FromJsonError::with_message(&String::from(e.message())))?;
+        return Ok(Value::String(String::from(digits)));
+    }
+    if value.is_function() || value.is_symbol() {
+        return Err(FromJsonError::with_message(&format!("unsupported JavaScript value: {:?}", value)));
+    }
+    if Array::is_array(value) {
+        let array = Array::unchecked_from_js_ref(value);
+        return Ok(Value::Array(array.iter().map(|v| from_js(&v)).collect::<Result<_, _>>()?));
+    }
+    if let Some(date) = value.dyn_ref::<Date>() {
+        return Ok(Value::String(String::from(date.to_iso_string())));
+    }
+    let entries = match value.dyn_ref::<js_sys::Map>() {
+        Some(map) => Array::from(map),
+        None => Object::entries(value.unchecked_ref::<Object>())
+    };
+    let mut object = Map::new();
+    for entry in entries.iter() {
+        let entry = Array::unchecked_from_js(entry);
+        let key = match from_js(&entry.get(0))? {
+            Value::String(s) => s,
+            k => k.to_string()
+        };
+        object.insert(key, from_js(&entry.get(1))?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// Convert json to a plain JavaScript value, integers beyond the safe range become BigInts
+pub fn to_js(value: &Value) -> JsValue {
+    match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Number(n) => {
+            match (n.as_i64(), n.as_u64(), n.as_f64()) {
+                (Some(i), _, _) if (i as f64).abs() > SAFE_INTEGER => JsValue::bigint_from_str(&i.to_string()),
+                (None, Some(u), _) if u as f64 > SAFE_INTEGER => JsValue::bigint_from_str(&u.to_string()),
+                (_, _, Some(f)) => JsValue::from_f64(f),
+                _ => JsValue::NULL
+            }
+        },
+        Value::String(s) => JsValue::from_str(s),
+        Value::Array(a) => a.iter().map(to_js).collect::<Array>().into(),
+        Value::Object(o) => {
+            let object = Object::new();
+            for (k, v) in o.iter() {
+                // setting a property on a plain object can not fail
+                let _ = Reflect::set(&object, &JsValue::from_str(k), &to_js(v));
+            }
+            object.into()
+        }
+    }
+}
+
+fn number(f: f64) -> Value {
+    match f.fract() == 0.0 && f.abs() <= SAFE_INTEGER {
+        true => Value::Number((f as i64).into()),
+        false => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null)
+    }
+}
+
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use serde_json::json;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    use crate::MaybeValue;
+
+    #[wasm_bindgen_test]
+    fn round_trip() {
+        let value = json!({"id": "17", "ratio": 0.5, "tags": [1, null], "big": 9007199254740993u64});
+        let js = super::to_js(&value);
+        assert_eq!(super::from_js(&js).unwrap(), json!({"id": "17", "ratio": 0.5, "tags": [1, null], "big": "9007199254740993"}));
+        assert_eq!(super::from_js(&js).unwrap().maybe_int("id").strict_ok().ok(), None);
+        assert_eq!(super::from_js(&JsValue::UNDEFINED).unwrap(), json!(null));
+    }
+}
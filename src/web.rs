@@ -0,0 +1,69 @@
+//! The rejection shared by the web framework extractors
+
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::FromJsonError;
+
+/// Why a body could not be extracted, rendered as problem details (RFC 7807)
+#[derive(Debug)]
+pub enum RelaxedJsonRejection {
+    /// The body could not be read, 400
+    Body(String),
+    /// The body is not json, 400
+    Syntax(FromJsonError),
+    /// The json does not fit the model, 422
+    Data(FromJsonError),
+}
+
+impl RelaxedJsonRejection {
+
+    /// The http status code of the response
+    pub fn status(&self) -> u16 {
+        match self {
+            RelaxedJsonRejection::Body(_) | RelaxedJsonRejection::Syntax(_) => 400,
+            RelaxedJsonRejection::Data(_) => 422,
+        }
+    }
+
+    /// The problem details document of the response
+    pub fn problem(&self) -> Value {
+        let mut problem = json!({
+            "type": "about:blank",
+            "title": match self.status() {
+                400 => "Bad Request",
+                _ => "Unprocessable Entity"
+            },
+            "status": self.status(),
+        });
+        match self {
+            RelaxedJsonRejection::Body(msg) => problem["detail"] = json!(msg),
+            RelaxedJsonRejection::Syntax(e) | RelaxedJsonRejection::Data(e) => {
+                problem["detail"] = json!(e.message());
+                if let Some((line, column)) = e.position() {
+                    problem["line"] = json!(line);
+                    problem["column"] = json!(column);
+                }
+            }
+        }
+        problem
+    }
+}
+
+impl fmt::Display for RelaxedJsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelaxedJsonRejection::Body(msg) => write!(f, "failed to read body: {}", msg),
+            RelaxedJsonRejection::Syntax(e) => write!(f, "invalid json: {}", e),
+            RelaxedJsonRejection::Data(e) => write!(f, "unexpected json: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RelaxedJsonRejection {}
+
+/// Read the body with the relaxed reader and convert it to `T`
+pub(crate) fn extract<T: crate::TryFromJson>(body: &[u8]) -> Result<T, RelaxedJsonRejection> {
+    let value = crate::read::from_slice(body).map_err(RelaxedJsonRejection::Syntax)?;
+    T::try_from_json(&value).map_err(RelaxedJsonRejection::Data)
+}
@@ -0,0 +1,178 @@
+//! Localized words for booleans and null
+//!
+//! Without a word pack every non-empty string other than `0` and `false` reads
+//! as `true`, so `"nein"` would be true. Within [`WordPack::scope`], or a
+//! [session](crate::session::Session::words) using a pack, strings matching a
+//! word of the pack (trimmed, any case) read as the boolean or as `Null` for
+//! every accessor:
+//!
+//! ```
+//! use json_relaxed::words::WordPack;
+//! use json_relaxed::{Maybe, MaybeValue};
+//! use serde_json::json;
+//!
+//! let row = json!({"active": "Nein", "manager": "k. A."});
+//! let (active, manager) = WordPack::german().scope(|| (row.maybe_bool("active"), row.maybe_string("manager")));
+//! assert!(matches!(active, Maybe::Relaxed(false)));
+//! assert!(matches!(manager, Maybe::Null));
+//! ```
+
+use std::cell::RefCell;
+
+/// Words read as `true`, `false` and `Null`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordPack {
+    truthy: Vec<String>,
+    falsy: Vec<String>,
+    null: Vec<String>,
+}
+
+thread_local! {
+    static SCOPED: RefCell<Option<WordPack>> = const { RefCell::new(None) };
+}
+
+fn lower(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| w.trim().to_lowercase()).collect()
+}
+
+impl WordPack {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn truthy(mut self, words: &[&str]) -> Self {
+        self.truthy.extend(lower(words));
+        self
+    }
+
+    pub fn falsy(mut self, words: &[&str]) -> Self {
+        self.falsy.extend(lower(words));
+        self
+    }
+
+    pub fn null(mut self, words: &[&str]) -> Self {
+        self.null.extend(lower(words));
+        self
+    }
+
+    /// The words of both packs, for documents mixing languages
+    pub fn with(mut self, other: &WordPack) -> Self {
+        self.truthy.extend(other.truthy.iter().cloned());
+        self.falsy.extend(other.falsy.iter().cloned());
+        self.null.extend(other.null.iter().cloned());
+        self
+    }
+
+    pub fn english() -> Self {
+        WordPack::new()
+            .truthy(&["true", "yes", "y", "on"])
+            .falsy(&["false", "no", "n", "off"])
+            .null(&["null", "none", "n/a"])
+    }
+
+    pub fn german() -> Self {
+        WordPack::new()
+            .truthy(&["ja", "j", "wahr", "an"])
+            .falsy(&["nein", "n", "falsch", "aus"])
+            .null(&["keine", "k. a.", "k.a."])
+    }
+
+    pub fn french() -> Self {
+        WordPack::new()
+            .truthy(&["oui", "o", "vrai"])
+            .falsy(&["non", "n", "faux"])
+            .null(&["aucun", "aucune", "n/d", "nd"])
+    }
+
+    pub fn spanish() -> Self {
+        WordPack::new()
+            .truthy(&["sí", "si", "s", "verdadero"])
+            .falsy(&["no", "n", "falso"])
+            .null(&["ninguno", "ninguna", "n/d"])
+    }
+
+    fn bool_word(&self, s: &str) -> Option<bool> {
+        let s = s.trim().to_lowercase();
+        match (self.truthy.contains(&s), self.falsy.contains(&s)) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None
+        }
+    }
+
+    fn null_word(&self, s: &str) -> bool {
+        !self.null.is_empty() && self.null.contains(&s.trim().to_lowercase())
+    }
+
+    /// Run `f` with the accessors using this pack on this thread
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _restore = install(self.clone());
+        f()
+    }
+}
+
+pub(crate) struct Restore(Option<WordPack>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SCOPED.with(|s| *s.borrow_mut() = previous);
+    }
+}
+
+/// Use `pack` on this thread until the guard is dropped
+pub(crate) fn install(pack: WordPack) -> Restore {
+    replace(Some(pack))
+}
+
+/// Use `pack`, or none, on this thread until the guard is dropped
+pub(crate) fn replace(pack: Option<WordPack>) -> Restore {
+    Restore(SCOPED.with(|s| s.replace(pack)))
+}
+
+/// The pack of this thread, to continue reading on another one
+#[cfg(feature = "rayon")]
+pub(crate) fn current() -> Option<WordPack> {
+    SCOPED.with(|s| s.borrow().clone())
+}
+
+/// The boolean `s` stands for in the current pack
+pub(crate) fn bool_word(s: &str) -> Option<bool> {
+    SCOPED.with(|p| p.borrow().as_ref().and_then(|pack| pack.bool_word(s)))
+}
+
+/// `s` stands for null in the current pack
+pub(crate) fn is_null(s: &str) -> bool {
+    SCOPED.with(|p| p.borrow().as_ref().is_some_and(|pack| pack.null_word(s)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::WordPack;
+    use crate::session::Session;
+    use crate::{Maybe, MaybeValue};
+
+    #[test]
+    fn word_packs() {
+        let row = json!({"a": "oui", "b": " NON ", "c": "n/d", "d": "nein"});
+        assert!(matches!(row.maybe_bool("b"), Maybe::Relaxed(true)));
+
+        let pack = WordPack::french().with(&WordPack::german());
+        pack.scope(|| {
+            assert!(matches!(row.maybe_bool("a"), Maybe::Relaxed(true)));
+            assert!(matches!(row.maybe_bool("b"), Maybe::Relaxed(false)));
+            assert!(matches!(row.maybe_int("c"), Maybe::Null));
+            assert!(matches!(row.maybe_bool("d"), Maybe::Relaxed(false)));
+        });
+        assert!(matches!(row.maybe_string("c"), Maybe::Strict(_)));
+
+        let (answer, _) = Session::new().words(WordPack::spanish()).run(|| json!({"x": "No"}).maybe_bool("x"));
+        assert!(matches!(answer, Maybe::Relaxed(false)));
+        let custom = WordPack::new().falsy(&["nope"]);
+        assert!(!custom.scope(|| json!(["Nope"]).maybe_bool(0usize).relaxed()));
+    }
+}
@@ -0,0 +1,379 @@
+//! Writing documents as text
+//!
+//! [`Json5`] writes the relaxed dialect hand-edited files are read in, with
+//! unquoted keys, trailing commas and comments. Comments kept by the
+//! [document model](crate::edit::Document) can be written along, so a file
+//! loaded leniently is written back in the same style. [`ndjson`] writes one
+//! document per line for export pipelines. [`Canonical`] writes the same text
+//! for equal values whatever their key order, for snapshots and generated files
+//! kept in git.
+//!
+//! ```
+//! use json_relaxed::edit::Document;
+//! use json_relaxed::write::Json5;
+//!
+//! let doc = Document::parse("{\n  \"hosts\": [\"a\"],\n  // seconds\n  \"timeout\": 30\n}").unwrap();
+//! let text = Json5::new().single_quotes(true).comments(doc.comments()).to_string(doc.value());
+//! assert_eq!(text, "{\n  hosts: [\n    'a',\n  ],\n  // seconds\n  timeout: 30,\n}\n");
+//! ```
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::{patch, ser, FromJsonError};
+
+/// Options for writing the relaxed json dialect
+#[derive(Debug, Clone)]
+pub struct Json5 {
+    indent: String,
+    single_quotes: bool,
+    trailing_commas: bool,
+    comments: BTreeMap<String, String>,
+}
+
+impl Default for Json5 {
+    fn default() -> Self {
+        Json5 { indent: s!("  "), single_quotes: false, trailing_commas: true, comments: BTreeMap::new() }
+    }
+}
+
+/// Write `value` with the default [`Json5`] options
+pub fn to_json5(value: &Value) -> String {
+    Json5::new().to_string(value)
+}
+
+impl Json5 {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Indentation of one level, two spaces by default
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = s!(indent);
+        self
+    }
+
+    /// Quote strings with `'` instead of `"`
+    pub fn single_quotes(mut self, enable: bool) -> Self {
+        self.single_quotes = enable;
+        self
+    }
+
+    /// End every member and element with a comma, enabled by default
+    pub fn trailing_commas(mut self, enable: bool) -> Self {
+        self.trailing_commas = enable;
+        self
+    }
+
+    /// Write `text` as line comments before the value at JSON Pointer `path`
+    pub fn comment(mut self, path: &str, text: &str) -> Self {
+        self.comments.insert(s!(path), s!(text));
+        self
+    }
+
+    /// Comments by JSON Pointer, as from [`Document::comments`](crate::edit::Document::comments)
+    pub fn comments(mut self, comments: BTreeMap<String, String>) -> Self {
+        self.comments.extend(comments);
+        self
+    }
+
+    pub fn to_string(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.comment_lines("", 0, &mut out);
+        self.write(value, "", 0, &mut out);
+        out.push('\n');
+        out
+    }
+
+    fn comment_lines(&self, path: &str, depth: usize, out: &mut String) {
+        if let Some(comment) = self.comments.get(path) {
+            for line in comment.lines() {
+                out.push_str(&self.indent.repeat(depth));
+                match line.is_empty() {
+                    true => out.push_str("//\n"),
+                    false => out.push_str(&format!("// {}\n", line))
+                }
+            }
+        }
+    }
+
+    fn write(&self, value: &Value, path: &str, depth: usize, out: &mut String) {
+        let (open, close, members): (char, char, Vec<(Option<&str>, &Value)>) = match value {
+            Value::Object(o) if !o.is_empty() => ('{', '}', o.iter().map(|(k, v)| (Some(k.as_str()), v)).collect()),
+            Value::Array(a) if !a.is_empty() => ('[', ']', a.iter().map(|v| (None, v)).collect()),
+            Value::String(s) => return out.push_str(&self.quote(s)),
+            other => return out.push_str(&other.to_string())
+        };
+
+        let len = members.len();
+        out.push(open);
+        for (i, (key, v)) in members.into_iter().enumerate() {
+            let child = patch::child(path, key.map_or_else(|| i.to_string(), |k| s!(k)).as_str());
+            out.push('\n');
+            self.comment_lines(&child, depth + 1, out);
+            out.push_str(&self.indent.repeat(depth + 1));
+            if let Some(key) = key {
+                match is_identifier(key) {
+                    true => out.push_str(key),
+                    false => out.push_str(&self.quote(key))
+                }
+                out.push_str(": ");
+            }
+            self.write(v, &child, depth + 1, out);
+            if i + 1 < len || self.trailing_commas {
+                out.push(',');
+            }
+        }
+        out.push('\n');
+        out.push_str(&self.indent.repeat(depth));
+        out.push(close);
+    }
+
+    fn quote(&self, s: &str) -> String {
+        let double = serde_json::to_string(s).unwrap_or_default();
+        match self.single_quotes {
+            true => format!("'{}'", double[1..double.len() - 1].replace("\\\"", "\"").replace('\'', "\\'")),
+            false => double
+        }
+    }
+}
+
+/// Keys that need no quotes, starting with a letter, `_` or `$`
+fn is_identifier(key: &str) -> bool {
+    key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Options for writing json with sorted keys and normalized numbers
+#[derive(Debug, Clone)]
+pub struct Canonical {
+    indent: String,
+}
+
+impl Default for Canonical {
+    fn default() -> Self {
+        Canonical { indent: s!("  ") }
+    }
+}
+
+/// Write `value` with the default [`Canonical`] options
+pub fn to_string_sorted(value: &Value) -> String {
+    Canonical::new().to_string(value)
+}
+
+/// Write `value` to `writer` with the default [`Canonical`] options
+pub fn to_writer_canonical<W: Write>(writer: W, value: &Value) -> Result<(), FromJsonError> {
+    Canonical::new().to_writer(writer, value)
+}
+
+impl Canonical {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Indentation of one level, two spaces by default, empty for a single line
+    pub fn indent(mut self, indent: &str) -> Self {
+        self.indent = s!(indent);
+        self
+    }
+
+    /// Keys are sorted by their UTF-8 bytes. Integral numbers are written as
+    /// integers, so `2.0` and `2` are the same, other floats in their shortest
+    /// form that reads back exactly.
+    pub fn to_string(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write(value, 0, &mut out);
+        out
+    }
+
+    pub fn to_writer<W: Write>(&self, mut writer: W, value: &Value) -> Result<(), FromJsonError> {
+        writer.write_all(self.to_string(value).as_bytes())?;
+        Ok(())
+    }
+
+    fn write(&self, value: &Value, depth: usize, out: &mut String) {
+        let (open, close, members): (char, char, Vec<(Option<&String>, &Value)>) = match value {
+            Value::Object(o) if !o.is_empty() => {
+                let mut entries: Vec<_> = o.iter().map(|(k, v)| (Some(k), v)).collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                ('{', '}', entries)
+            },
+            Value::Array(a) if !a.is_empty() => ('[', ']', a.iter().map(|v| (None, v)).collect()),
+            Value::Number(n) => return out.push_str(&number(n)),
+            other => return out.push_str(&other.to_string())
+        };
+
+        let pretty = !self.indent.is_empty();
+        out.push(open);
+        for (i, (key, v)) in members.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            if pretty {
+                out.push('\n');
+                out.push_str(&self.indent.repeat(depth + 1));
+            }
+            if let Some(key) = key {
+                out.push_str(&serde_json::to_string(key).unwrap_or_default());
+                out.push_str(if pretty { ": " } else { ":" });
+            }
+            self.write(v, depth + 1, out);
+        }
+        if pretty {
+            out.push('\n');
+            out.push_str(&self.indent.repeat(depth));
+        }
+        out.push(close);
+    }
+}
+
+/// Largest integer below which every integral f64 is exact
+const EXACT_INTEGERS: f64 = 9007199254740992.0;
+
+fn number(n: &Number) -> String {
+    if let Some(i) = n.as_i64() {
+        return i.to_string();
+    }
+    if let Some(u) = n.as_u64() {
+        return u.to_string();
+    }
+    let text = n.to_string();
+    // wide integers kept by arbitrary_precision
+    if text.trim_start_matches('-').bytes().all(|b| b.is_ascii_digit()) {
+        return text;
+    }
+    match n.as_f64() {
+        Some(f) if f.fract() == 0.0 && f.abs() < EXACT_INTEGERS => (f as i64).to_string(),
+        Some(f) => Number::from_f64(f).map_or(text, |n| n.to_string()),
+        None => text
+    }
+}
+
+/// Write newline delimited json (one document per line) to `writer`, flushed
+/// when finished unless [`flush_every`](NdJson::flush_every) is set
+pub fn ndjson<W: Write>(writer: W) -> NdJson<W> {
+    NdJson { writer, flush_every: 0, lines: 0 }
+}
+
+/// Writer of newline delimited json, see [`ndjson`]
+pub struct NdJson<W> {
+    writer: W,
+    flush_every: usize,
+    lines: usize,
+}
+
+impl <W: Write> NdJson<W> {
+
+    /// Flush after every `lines` documents, 0 flushes only when finished
+    pub fn flush_every(mut self, lines: usize) -> Self {
+        self.flush_every = lines;
+        self
+    }
+
+    pub fn write(&mut self, value: &Value) -> Result<(), FromJsonError> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.lines += 1;
+        if self.flush_every > 0 && self.lines.is_multiple_of(self.flush_every) {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write `value` serialized with [`ser::to_value_relaxed`]
+    pub fn serialize<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FromJsonError> {
+        self.write(&ser::to_value_relaxed(value)?)
+    }
+
+    /// Write all `values`, stopping at the first error
+    pub fn write_all<'a>(&mut self, values: impl IntoIterator<Item = &'a Value>) -> Result<(), FromJsonError> {
+        values.into_iter().try_for_each(|v| self.write(v))
+    }
+
+    /// Number of documents written
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Flush and return the writer
+    pub fn finish(mut self) -> Result<W, FromJsonError> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::io::{self, Write};
+
+    use super::{Canonical, Json5};
+    use crate::edit::Document;
+
+    #[test]
+    fn json5_round_trip() {
+        let value = json!({"name": "it's \"quoted\"", "ports": [80, 443], "x-forwarded": true, "empty": {}, "nested": {"a": null}});
+        let writer = Json5::new().single_quotes(true).indent("\t").comment("/ports/1", "tls\nonly").comment("", "generated");
+        let text = writer.to_string(&value);
+        assert!(text.starts_with("// generated\n{"));
+        assert!(text.contains("\tname: 'it\\'s \"quoted\"',\n"));
+        assert!(text.contains("\t\t80,\n\t\t// tls\n\t\t// only\n\t\t443,\n"));
+        assert!(text.contains("\t'x-forwarded': true,\n"));
+        assert!(text.contains("\tempty: {},\n"));
+
+        let doc = Document::parse(&text).unwrap();
+        assert_eq!(doc.value(), &value);
+        assert_eq!(doc.comments().get("/ports/1").map(String::as_str), Some("tls\nonly"));
+        assert_eq!(doc.comments().get("").map(String::as_str), Some("generated"));
+
+        let strict = Json5::new().trailing_commas(false).to_string(&json!({"a": [1]}));
+        assert_eq!(strict, "{\n  a: [\n    1\n  ]\n}\n");
+    }
+
+    #[test]
+    fn canonical_output() {
+        let a: serde_json::Value = serde_json::from_str(r#"{"b": [2.0, -0.0, 1.50, 1e300], "a": {"z": "\u00e9", "y": {}}}"#).unwrap();
+        let b = json!({"a": {"y": {}, "z": "é"}, "b": [2, 0, 1.5, 1e300]});
+        let text = super::to_string_sorted(&a);
+        assert_eq!(text, super::to_string_sorted(&b));
+        assert_eq!(text, "{\n  \"a\": {\n    \"y\": {},\n    \"z\": \"é\"\n  },\n  \"b\": [\n    2,\n    0,\n    1.5,\n    1e+300\n  ]\n}");
+
+        assert_eq!(Canonical::new().indent("").to_string(&b), r#"{"a":{"y":{},"z":"é"},"b":[2,0,1.5,1e+300]}"#);
+        let mut out = Vec::new();
+        super::to_writer_canonical(&mut out, &json!({"x": 0.1, "$": [true, null]})).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "{\n  \"$\": [\n    true,\n    null\n  ],\n  \"x\": 0.1\n}");
+    }
+
+    /// Counts flushes of the lines written so far
+    #[derive(Default)]
+    struct Flushes(Vec<u8>, Vec<usize>);
+
+    impl Write for Flushes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.1.push(self.0.iter().filter(|b| **b == b'\n').count());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn ndjson_export() {
+        let mut out = super::ndjson(Flushes::default()).flush_every(2);
+        out.write_all(&[json!({"id": 1}), json!([1, "a\nb"]), json!(null)]).unwrap();
+        out.serialize(&(f64::NAN, u128::MAX)).unwrap();
+        assert_eq!(out.lines(), 4);
+
+        let Flushes(text, flushes) = out.finish().unwrap();
+        assert_eq!(flushes, vec!(2, 4, 4));
+        let read: Vec<_> = crate::read::ndjson(text.as_slice()).map(Result::unwrap).collect();
+        assert_eq!(read, vec!(json!({"id": 1}), json!([1, "a\nb"]), json!(null), json!([null, u128::MAX.to_string()])));
+    }
+}
@@ -0,0 +1,162 @@
+//! Reading simple XML documents into json values
+//!
+//! The document becomes an object with the root element as its only key.
+//! Attributes are written as `"@name"`, text as `"#text"` and repeated child
+//! elements as arrays. Elements with only text become strings, empty elements
+//! null. Comments, processing instructions and the declaration are skipped,
+//! names keep their namespace prefix.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::{Map, Value};
+
+use crate::limits::Limits;
+use crate::FromJsonError;
+
+struct Element {
+    name: String,
+    map: Map<String, Value>,
+    text: String,
+}
+
+impl Element {
+
+    fn start(e: &BytesStart) -> Result<Self, FromJsonError> {
+        let mut map = Map::new();
+        for attribute in e.attributes() {
+            let attribute = attribute.map_err(|e| FromJsonError::with_message(&e.to_string()))?;
+            let value = attribute.unescape_value().map_err(|e| FromJsonError::with_message(&e.to_string()))?;
+            map.insert(format!("@{}", String::from_utf8_lossy(attribute.key.as_ref())), Value::String(value.into_owned()));
+        }
+        Ok(Element { name: String::from_utf8_lossy(e.name().as_ref()).into_owned(), map, text: String::new() })
+    }
+
+    fn finish(mut self) -> (String, Value) {
+        let value = match (self.map.is_empty(), self.text.is_empty()) {
+            (true, true) => Value::Null,
+            (true, false) => Value::String(self.text),
+            (false, true) => Value::Object(self.map),
+            (false, false) => {
+                self.map.insert(s!("#text"), Value::String(self.text));
+                Value::Object(self.map)
+            }
+        };
+        (self.name, value)
+    }
+
+    fn child(&mut self, name: String, value: Value) {
+        // element values are never arrays, so an array is a repeated element
+        match self.map.get_mut(&name) {
+            Some(Value::Array(a)) => a.push(value),
+            Some(existing) => *existing = Value::Array(vec!(existing.take(), value)),
+            None => {
+                self.map.insert(name, value);
+            }
+        }
+    }
+}
+
+/// Parse an XML document
+pub fn from_str(text: &str) -> Result<Value, FromJsonError> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let limits = Limits::default();
+
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+    loop {
+        let event = reader.read_event().map_err(|e| position(text, reader.buffer_position() as usize, &e.to_string()))?;
+        let done = match event {
+            Event::Start(e) => {
+                limits.check_depth(stack.len() + 1)?;
+                stack.push(Element::start(&e)?);
+                None
+            },
+            Event::Empty(e) => Some(Element::start(&e)?.finish()),
+            Event::End(_) => stack.pop().map(Element::finish),
+            Event::Text(t) => {
+                let t = t.unescape().map_err(|e| position(text, reader.buffer_position() as usize, &e.to_string()))?;
+                if let Some(top) = stack.last_mut() {
+                    top.text.push_str(&t);
+                }
+                None
+            },
+            Event::CData(c) => {
+                if let Some(top) = stack.last_mut() {
+                    top.text.push_str(&String::from_utf8_lossy(&c));
+                }
+                None
+            },
+            Event::Eof => break,
+            _ => None
+        };
+        if let Some((name, value)) = done {
+            match stack.last_mut() {
+                Some(parent) => parent.child(name, value),
+                None if root.is_none() => root = Some((name, value)),
+                None => return Err(position(text, reader.buffer_position() as usize, "more than one root element"))
+            }
+        }
+    }
+    match root {
+        Some((name, value)) if stack.is_empty() => {
+            let mut document = Map::new();
+            document.insert(name, value);
+            Ok(Value::Object(document))
+        },
+        _ => Err(FromJsonError::with_message("unexpected end of XML document"))
+    }
+}
+
+/// Parse an XML document from a byte slice
+pub fn from_slice(bytes: &[u8]) -> Result<Value, FromJsonError> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => from_str(text),
+        Err(e) => Err(FromJsonError::with_message(&e.to_string()))
+    }
+}
+
+/// 1-based line and column of byte `offset` like serde_json positions
+fn position(text: &str, offset: usize, message: &str) -> FromJsonError {
+    let mut end = offset.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let before = &text[..end];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    FromJsonError::with_message(message).with_position(line, column)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    #[test]
+    fn xml_documents() {
+        let text = r#"<?xml version="1.0"?>
+<!-- partner feed -->
+<order id="17" xmlns:p="urn:partner">
+  <item sku="A1">Widget &amp; Co</item>
+  <item sku="B2"/>
+  <note><![CDATA[<fragile>]]></note>
+  <p:total currency="EUR">12.50</p:total>
+  <empty></empty>
+</order>"#;
+        assert_eq!(super::from_str(text).unwrap(), json!({
+            "order": {
+                "@id": "17",
+                "@xmlns:p": "urn:partner",
+                "item": [{"@sku": "A1", "#text": "Widget & Co"}, {"@sku": "B2"}],
+                "note": "<fragile>",
+                "p:total": {"@currency": "EUR", "#text": "12.50"},
+                "empty": null
+            }
+        }));
+
+        let error = super::from_str("<a>\n  <b></c>\n</a>").unwrap_err();
+        assert_eq!(error.position().map(|p| p.0), Some(2));
+        assert!(super::from_str("<a/><b/>").is_err());
+    }
+}
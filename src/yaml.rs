@@ -0,0 +1,142 @@
+//! Reading YAML documents into json values
+//!
+//! YAML 1.2 rules apply, so `no`, `on` or `y` stay strings (the Norway problem)
+//! unless [`Options::booleans`] asks for YAML 1.1 booleans. Merge keys (`<<`) are
+//! resolved, tags are dropped, scalar keys become strings and NaN or infinite
+//! floats become null.
+
+use serde_json::{Map, Number, Value};
+
+use crate::FromJsonError;
+
+impl From<serde_yaml::Error> for FromJsonError {
+    fn from(e: serde_yaml::Error) -> Self {
+        match e.location() {
+            Some(l) => FromJsonError::with_message(&e.to_string()).with_position(l.line(), l.column()),
+            None => FromJsonError::with_message(&e.to_string())
+        }
+    }
+}
+
+/// Options for reading YAML input
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    booleans: bool,
+}
+
+impl Options {
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Read `yes`, `no`, `on`, `off`, `y` and `n` as booleans like YAML 1.1
+    pub fn booleans(mut self, enable: bool) -> Self {
+        self.booleans = enable;
+        self
+    }
+}
+
+/// Parse a single YAML document
+pub fn from_str(text: &str) -> Result<Value, FromJsonError> {
+    from_str_with(text, &Options::default())
+}
+
+/// Parse a single YAML document from a byte slice
+pub fn from_slice(bytes: &[u8]) -> Result<Value, FromJsonError> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => from_str(text),
+        Err(e) => Err(FromJsonError::with_message(&e.to_string()))
+    }
+}
+
+/// Parse a single YAML document using `options`
+pub fn from_str_with(text: &str, options: &Options) -> Result<Value, FromJsonError> {
+    let mut yaml: serde_yaml::Value = serde_yaml::from_str(text)?;
+    yaml.apply_merge()?;
+    convert(yaml, options)
+}
+
+fn convert(yaml: serde_yaml::Value, options: &Options) -> Result<Value, FromJsonError> {
+    use serde_yaml::Value as Yaml;
+
+    Ok(match yaml {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => {
+            match (n.as_i64(), n.as_u64(), n.as_f64()) {
+                (Some(i), _, _) => Value::Number(i.into()),
+                (_, Some(u), _) => Value::Number(u.into()),
+                (_, _, Some(f)) => Number::from_f64(f).map(Value::Number).unwrap_or(Value::Null),
+                _ => Value::Null
+            }
+        },
+        Yaml::String(s) if options.booleans => {
+            match s.to_lowercase().as_str() {
+                "yes" | "y" | "on" => Value::Bool(true),
+                "no" | "n" | "off" => Value::Bool(false),
+                _ => Value::String(s)
+            }
+        },
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(seq) => {
+            Value::Array(seq.into_iter().map(|v| convert(v, options)).collect::<Result<_, _>>()?)
+        },
+        Yaml::Mapping(mapping) => {
+            let mut map = Map::new();
+            for (k, v) in mapping {
+                map.insert(key(k)?, convert(v, options)?);
+            }
+            Value::Object(map)
+        },
+        Yaml::Tagged(tagged) => convert(tagged.value, options)?
+    })
+}
+
+/// Object keys must be strings, other scalars are written as their text
+fn key(yaml: serde_yaml::Value) -> Result<String, FromJsonError> {
+    use serde_yaml::Value as Yaml;
+
+    match yaml {
+        Yaml::String(s) => Ok(s),
+        Yaml::Number(n) => Ok(n.to_string()),
+        Yaml::Bool(b) => Ok(b.to_string()),
+        Yaml::Null => Ok(s!("null")),
+        Yaml::Tagged(tagged) => key(tagged.value),
+        _ => Err(FromJsonError::with_message("yaml key must be a scalar"))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Options;
+
+    #[test]
+    fn yaml_documents() {
+        let text = "
+defaults: &defaults
+  port: 8080
+  debug: no
+server:
+  <<: *defaults
+  host: example.com
+countries: [no, se]
+1: .nan
+";
+        assert_eq!(super::from_str(text).unwrap(), json!({
+            "defaults": {"port": 8080, "debug": "no"},
+            "server": {"port": 8080, "debug": "no", "host": "example.com"},
+            "countries": ["no", "se"],
+            "1": null
+        }));
+
+        let booleans = super::from_str_with(text, &Options::new().booleans(true)).unwrap();
+        assert_eq!(booleans["server"]["debug"], json!(false));
+
+        let error = super::from_str("a: [1, 2\nb: 3").unwrap_err();
+        assert!(error.position().is_some());
+    }
+}